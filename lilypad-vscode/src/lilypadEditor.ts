@@ -88,6 +88,10 @@ export class LilypadEditorProvider implements vscode.CustomTextEditorProvider {
                     type: "set_blocks_theme",
                     theme: newTheme
                 });
+            } else if (e.affectsConfiguration("editor.tabSize")) {
+                setTabSize();
+            } else if (e.affectsConfiguration("editor.bracketPairColorization.enabled")) {
+                setBracketColorization();
             } else if (e.affectsConfiguration("editor.fontFamily") || e.affectsConfiguration("editor.fontSize")) {
                 // TODO: support fallback fonts instead of only sending the first
                 // TODO: could this be called as a part of started instead of using the hacky js pass through thing?
@@ -102,6 +106,22 @@ export class LilypadEditorProvider implements vscode.CustomTextEditorProvider {
             }
         });
 
+        function setBracketColorization() {
+            const enabled = vscode.workspace.getConfiguration("editor", document).get("bracketPairColorization.enabled");
+            webviewPanel.webview.postMessage({
+                type: "set_bracket_colorization",
+                enabled
+            });
+        }
+
+        function setTabSize() {
+            const tabSize = vscode.workspace.getConfiguration("editor", document).get("tabSize");
+            webviewPanel.webview.postMessage({
+                type: "set_tab_width",
+                tabSize
+            });
+        }
+
         // Listen for new debug stuff
         function setBreakpoints() {
             let lines = [];
@@ -230,6 +250,12 @@ export class LilypadEditorProvider implements vscode.CustomTextEditorProvider {
                         text: document.getText(),
                     });
 
+                    // draw tabs the same width as the text editor
+                    setTabSize();
+
+                    // color brackets like the text editor
+                    setBracketColorization();
+
                     // send initial diagnostics
                     webviewPanel.webview.postMessage({
                         type: "set_diagnostics",
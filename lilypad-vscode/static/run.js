@@ -96,6 +96,25 @@ window.addEventListener("message", event => {
     case "set_font":
       handle.set_font(message.fontFamily, message.fontSize);
       break;
+    case "set_tab_width":
+      handle.set_tab_width(message.tabSize);
+      break;
+    case "set_bracket_colorization":
+      handle.set_bracket_colorization(message.enabled);
+      break;
+    case "set_language":
+      handle.set_language(message.language);
+      break;
+    case "register_language":
+      handle.register_language(
+        message.definition,
+        message.blocksQuery,
+        message.indentsQuery
+      );
+      break;
+    case "set_keymap":
+      handle.set_keymap(message.keymap);
+      break;
     case "apply_edit":
       handle.apply_edit(message.edit);
       break;
@@ -6,13 +6,25 @@ mod theme;
 mod util_widgets;
 mod vscode;
 
-use block_editor::{BlockEditor, MonospaceFont};
+use block_editor::{BlockEditor, ExternalCommand, MonospaceFont};
 use egui::Frame;
 use file_picker::FilePicker;
 
+/// Environment variable pointing to a directory of extra language definitions
+const LANGUAGE_DIR_VAR: &str = "LILYPAD_LANGUAGE_DIR";
+
+/// Environment variable pointing to a JSON keymap
+const KEYMAP_VAR: &str = "LILYPAD_KEYMAP";
+
 pub struct LilypadNative {
     file_picker: FilePicker,
     block_editor: BlockEditor,
+
+    /// commands to send to the editor on the next frame
+    pending_commands: Vec<ExternalCommand>,
+
+    /// if brackets are colored by how deeply they are nested
+    bracket_colorization: bool,
 }
 
 impl LilypadNative {
@@ -24,13 +36,96 @@ impl LilypadNative {
                 "syntax_colored",
                 MonospaceFont::new("SF Mono", 14.0),
             ),
+            pending_commands: load_language_definitions()
+                .into_iter()
+                .chain(load_keymap())
+                .collect(),
+            bracket_colorization: false,
+        }
+    }
+}
+
+/// Read the keymap in the file set by `LILYPAD_KEYMAP` (if any)
+fn load_keymap() -> Option<ExternalCommand> {
+    let path = std::env::var_os(KEYMAP_VAR)?;
+    match std::fs::read_to_string(&path) {
+        Ok(keymap) => Some(ExternalCommand::SetKeymap(keymap)),
+        Err(err) => {
+            log::warn!("Could not read keymap {path:?}: {err}");
+            None
         }
     }
 }
 
+/// Read the language definitions in the directory set by `LILYPAD_LANGUAGE_DIR` (if any).
+/// Each `<name>.json` definition needs a `<name>.blocks.scm` query next to it,
+/// and can have a `<name>.indents.scm` query.
+fn load_language_definitions() -> Vec<ExternalCommand> {
+    let Some(dir) = std::env::var_os(LANGUAGE_DIR_VAR) else {
+        return vec![];
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        log::warn!("Could not read language directory {dir:?}");
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let blocks_query_path = path.with_extension("blocks.scm");
+            let indents_query_path = path.with_extension("indents.scm");
+            let read = std::fs::read_to_string(&path).and_then(|definition| {
+                let blocks_query = std::fs::read_to_string(&blocks_query_path)?;
+                let indents_query =
+                    std::fs::read_to_string(&indents_query_path).unwrap_or_default();
+                Ok(ExternalCommand::RegisterLanguage(
+                    definition,
+                    blocks_query,
+                    indents_query,
+                ))
+            });
+            match read {
+                Ok(command) => Some(command),
+                Err(err) => {
+                    log::warn!("Could not read language definition {path:?}: {err}");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
 impl eframe::App for LilypadNative {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let mut commands = vec![];
+        let mut commands = std::mem::take(&mut self.pending_commands);
+
+        egui::TopBottomPanel::top("menu-bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("Language", |ui| {
+                    let current = self.block_editor.language_name();
+                    for config in lang::registry::all() {
+                        if ui
+                            .selectable_label(config.name == current, &config.name)
+                            .clicked()
+                        {
+                            commands.push(ExternalCommand::SetLanguage(config.name.clone()));
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    if ui
+                        .checkbox(&mut self.bracket_colorization, "Rainbow brackets")
+                        .changed()
+                    {
+                        commands.push(ExternalCommand::SetBracketColorization(
+                            self.bracket_colorization,
+                        ));
+                    }
+                });
+            });
+        });
 
         egui::SidePanel::left("file-picker")
             .default_width(200.0)
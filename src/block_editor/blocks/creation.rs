@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use tree_sitter::{Node, TreeCursor};
 
 use super::{Block, BlockTrees, BlockType, Padding};
@@ -8,15 +10,39 @@ use crate::block_editor::{
 };
 use crate::lang::config::{LanguageConfig, NewScopeChar};
 
+/// The block type of each node that gets a block, keyed by node ID
+type Categories = HashMap<usize, BlockType>;
+
 impl BlockType {
-    fn from_node(node: &Node, lang: &LanguageConfig) -> Option<Self> {
+    fn from_node(node: &Node, categories: &Categories) -> Option<Self> {
         use BlockType::*;
 
         if SHOW_ERROR_BLOCK_OUTLINES && node.is_error() {
             return Some(Error);
         }
 
-        lang.categorize_node(node)
+        categories.get(&node.id()).copied()
+    }
+
+    /// Get the block type for a blocks query capture name (without the `block.` prefix)
+    pub fn from_capture_name(name: &str) -> Option<Self> {
+        use BlockType::*;
+        match name {
+            "object" => Some(Object),
+            "function_def" => Some(FunctionDef),
+            "while" => Some(While),
+            "if" => Some(If),
+            "for" => Some(For),
+            "try" => Some(Try),
+            "switch" => Some(Switch),
+            "with" => Some(With),
+            "match" => Some(Match),
+            "generic" => Some(Generic),
+            "comment" => Some(Comment),
+            "error" => Some(Error),
+            "divider" => Some(Divider),
+            _ => None,
+        }
     }
 
     pub const fn as_str(&self) -> &'static str {
@@ -29,6 +55,8 @@ impl BlockType {
             For => "For",
             Try => "Try",
             Switch => "Switch",
+            With => "With",
+            Match => "Match",
             Generic => "Generic",
             Comment => "Comment",
             Error => "Error",
@@ -38,8 +66,8 @@ impl BlockType {
 }
 
 impl Block {
-    fn from_node(node: &Node, lang: &LanguageConfig) -> Option<Self> {
-        let syntax_type = BlockType::from_node(node, lang)?;
+    fn from_node(node: &Node, categories: &Categories) -> Option<Self> {
+        let syntax_type = BlockType::from_node(node, categories)?;
         let start_pos = node.start_position();
         let end_pos = node.end_position();
         Some(Block {
@@ -65,7 +93,8 @@ impl BlockTrees {
         source: &ropey::Rope,
         lang: &LanguageConfig,
     ) -> Self {
-        let mut trees = tree_to_blocks(cursor, lang);
+        let categories = lang.categorize_tree(cursor.node(), source);
+        let mut trees = tree_to_blocks(cursor, &categories);
 
         merge_comments(&mut trees, source);
 
@@ -80,26 +109,33 @@ impl BlockTrees {
         // if languages uses braces for new scopes,
         // adjust the block starts so that they contain their children
         // (since it would be possible for a block to start further in than its children)
-        if lang.new_scope_char == NewScopeChar::Brace {
+        if lang.new_scope_char == Some(NewScopeChar::Brace) {
             adjust_block_starts(&mut trees);
         }
 
         let padding = Padding::for_blocks(&trees, source.len_lines());
         BlockTrees { trees, padding }
     }
+
+    /// Blocks for a language without a grammar (no blocks, but still padding for every line)
+    pub fn without_tree(source: &ropey::Rope) -> Self {
+        let trees = vec![];
+        let padding = Padding::for_blocks(&trees, source.len_lines());
+        BlockTrees { trees, padding }
+    }
 }
 
 /// Converts a tree sitter tree to a tree of blocks (with no additional processing)
-fn tree_to_blocks(cursor: &mut TreeCursor, lang: &LanguageConfig) -> Vec<Block> {
+fn tree_to_blocks(cursor: &mut TreeCursor, categories: &Categories) -> Vec<Block> {
     // get the current node before moving the cursor
     let curr_node = cursor.node();
 
     // get all lower blocks
     let mut children: Vec<Block> = if cursor.goto_first_child() {
-        let mut blocks = tree_to_blocks(cursor, lang);
+        let mut blocks = tree_to_blocks(cursor, categories);
 
         while cursor.goto_next_sibling() {
-            blocks.append(&mut tree_to_blocks(cursor, lang));
+            blocks.append(&mut tree_to_blocks(cursor, categories));
         }
 
         cursor.goto_parent();
@@ -111,7 +147,7 @@ fn tree_to_blocks(cursor: &mut TreeCursor, lang: &LanguageConfig) -> Vec<Block>
 
     // get block for current level
     let mut root: Vec<Block> = vec![];
-    if let Some(mut block) = Block::from_node(&curr_node, lang) {
+    if let Some(mut block) = Block::from_node(&curr_node, categories) {
         // if the current node gets a block, add it to the root
         block.children = children;
         root.push(block);
@@ -270,3 +306,206 @@ fn adjust_block_starts(blocks: &mut Vec<Block>) -> usize {
     }
     max_col
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use BlockType::*;
+
+    /// Get the type and line of each block (in pre-order) for some source code
+    fn blocks_for(file_name: &str, code: &str) -> Vec<(BlockType, usize)> {
+        fn flatten(blocks: &[Block], out: &mut Vec<(BlockType, usize)>) {
+            for block in blocks {
+                out.push((block.syntax_type, block.line));
+                flatten(&block.children, out);
+            }
+        }
+
+        let lang = LanguageConfig::for_file(file_name);
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&lang.tree_sitter().unwrap()).unwrap();
+        let tree = parser.parse(code, None).unwrap();
+        let rope = ropey::Rope::from_str(code);
+
+        let trees = BlockTrees::for_ts_tree(&mut tree.walk(), &rope, lang);
+        let mut blocks = vec![];
+        flatten(trees.trees(), &mut blocks);
+        blocks
+    }
+
+    #[test]
+    fn python_clauses_are_dividers() {
+        let code = "if a:\n    pass\nelif b:\n    pass\nelse:\n    pass\n";
+        assert_eq!(
+            blocks_for("test.py", code),
+            [
+                (If, 0),
+                (Generic, 1),
+                (Divider, 2),
+                (Generic, 3),
+                (Divider, 4),
+                (Generic, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn python_decorated_with_and_match() {
+        let code = "@cache\ndef f():\n    with a:\n        pass\n    match b:\n        case 1:\n            pass\n";
+        assert_eq!(
+            blocks_for("test.py", code),
+            [
+                (FunctionDef, 0),
+                (With, 2),
+                (Generic, 3),
+                (Match, 4),
+                (Divider, 5),
+                (Generic, 6)
+            ]
+        );
+    }
+
+    #[test]
+    fn java_else_if_is_part_of_first_if() {
+        let code = "class A {\n  void f() {\n    if (a) {\n    } else if (b) {\n    }\n  }\n}\n";
+        let ifs = blocks_for("A.java", code)
+            .into_iter()
+            .filter(|(block, _)| *block == If)
+            .count();
+        assert_eq!(ifs, 1);
+    }
+
+    #[test]
+    fn java_switch_groups_and_lambdas() {
+        let code = "class A {\n  void f() {\n    for (int x : xs) {\n      switch (x) {\n        case 1:\n          break;\n        default:\n          run(() -> {\n            g();\n          });\n      }\n    }\n  }\n}\n";
+        assert_eq!(
+            blocks_for("A.java", code),
+            [
+                (Object, 0),
+                (FunctionDef, 1),
+                (For, 2),
+                (Switch, 3),
+                (Divider, 4),
+                (Generic, 5),
+                (Divider, 6),
+                (Divider, 7),
+                (FunctionDef, 7),
+                (Generic, 8)
+            ]
+        );
+    }
+
+    #[test]
+    fn c_sharp_namespaces_properties_and_foreach() {
+        let code = "namespace A\n{\n    class B\n    {\n        int X { get; set; }\n        void F()\n        {\n            foreach (var x in xs)\n            {\n                lock (x)\n                {\n                    G();\n                }\n            }\n        }\n    }\n}\n";
+        assert_eq!(
+            blocks_for("B.cs", code),
+            [
+                (Object, 0),
+                (Object, 2),
+                (Generic, 4),
+                (FunctionDef, 5),
+                (For, 7),
+                (With, 9),
+                (Generic, 11)
+            ]
+        );
+    }
+
+    #[test]
+    fn c_switch_cases_are_dividers() {
+        let code = "int main(void) {\n    switch (a) {\n        case 1:\n            break;\n        default:\n            break;\n    }\n}\n";
+        assert_eq!(
+            blocks_for("main.c", code),
+            [
+                (FunctionDef, 0),
+                (Switch, 1),
+                (Divider, 2),
+                (Generic, 3),
+                (Divider, 4),
+                (Generic, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn cpp_namespaces_range_for_and_lambdas() {
+        let code = "namespace a {\nvoid f() {\n    for (auto x : xs) {\n        auto g = [&]() {\n            h();\n        };\n    }\n}\n}\n";
+        assert_eq!(
+            blocks_for("a.cpp", code),
+            [
+                (Object, 0),
+                (FunctionDef, 1),
+                (For, 2),
+                (Divider, 3),
+                (FunctionDef, 3),
+                (Generic, 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn javascript_arrow_function_bodies() {
+        let code = "const f = (a) => {\n    return a;\n};\nlet x = 1;\n";
+        assert_eq!(
+            blocks_for("app.js", code),
+            [(Divider, 0), (FunctionDef, 0), (Generic, 1), (Generic, 3)]
+        );
+    }
+
+    #[test]
+    fn go_switch_cases_are_dividers() {
+        let code = "func main() {\n    switch a {\n    case 1:\n        defer f()\n    default:\n        go g()\n    }\n}\n";
+        assert_eq!(
+            blocks_for("main.go", code),
+            [
+                (FunctionDef, 0),
+                (Switch, 1),
+                (Divider, 2),
+                (Generic, 3),
+                (Divider, 4),
+                (Generic, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn rust_multiline_let_is_divider() {
+        let code = "fn main() {\n    let a = 1;\n    let b = [\n        1,\n    ];\n}\n";
+        assert_eq!(
+            blocks_for("main.rs", code),
+            [(FunctionDef, 0), (Generic, 1), (Divider, 2)]
+        );
+    }
+
+    #[test]
+    fn rust_test_file() {
+        let code = include_str!("../../../test-files/src/main.rs");
+        let blocks = blocks_for("main.rs", code);
+        let count = |block_type| blocks.iter().filter(|(b, _)| *b == block_type).count();
+        assert_eq!(count(FunctionDef), 12);
+        assert_eq!(count(Object), 3);
+        assert_eq!(count(If), 4);
+        assert_eq!(count(Match), 2);
+        assert_eq!(count(For), 1);
+        assert_eq!(count(While), 1);
+    }
+
+    #[test]
+    fn rust_modules_macros_loops_and_closures() {
+        let code = "mod a {\n    macro_rules! m {\n        () => {};\n    }\n    const X: i32 = 1;\n    fn f() {\n        loop {\n            let g = |x| {\n                x\n            };\n        }\n    }\n}\n";
+        assert_eq!(
+            blocks_for("main.rs", code),
+            [
+                (Object, 0),
+                (FunctionDef, 1),
+                (Generic, 4),
+                (FunctionDef, 5),
+                (While, 6),
+                (Divider, 7),
+                (FunctionDef, 7),
+                (Generic, 8)
+            ]
+        );
+    }
+}
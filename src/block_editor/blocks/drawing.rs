@@ -1,16 +1,22 @@
 use std::ops::RangeInclusive;
 
 use egui::{Painter, Pos2, Rect, Stroke, Vec2};
+use ropey::Rope;
 
 use super::{Block, BlockTrees, BlockType, BLOCK_CONFIG};
-use crate::{block_editor::MonospaceFont, theme::blocks_theme::BlocksTheme};
+use crate::{
+    block_editor::{rope_ext::RopeSliceExt, MonospaceFont},
+    theme::blocks_theme::BlocksTheme,
+};
 
 impl BlockTrees {
+    #[allow(clippy::too_many_arguments)]
     pub fn draw(
         &self,
         offset: Vec2,
         width: f32,
         visible_lines: Option<RangeInclusive<usize>>,
+        source: &Rope,
         blocks_theme: BlocksTheme,
         font: &MonospaceFont,
         painter: &Painter,
@@ -22,6 +28,7 @@ impl BlockTrees {
             offset,
             width,
             visible_lines,
+            source,
             blocks_theme,
             font,
             painter,
@@ -36,6 +43,7 @@ fn draw_blocks_helper(
     offset: Vec2,
     width: f32,
     visible_lines: Option<RangeInclusive<usize>>,
+    source: &Rope,
     blocks_theme: BlocksTheme,
     font: &MonospaceFont,
     painter: &Painter,
@@ -51,6 +59,7 @@ fn draw_blocks_helper(
                 offset,
                 width,
                 visible_lines.clone(),
+                source,
                 blocks_theme,
                 font,
                 painter,
@@ -66,6 +75,7 @@ fn draw_blocks_helper(
                 offset,
                 width,
                 visible_lines.clone(),
+                source,
                 blocks_theme,
                 font,
                 painter,
@@ -86,6 +96,7 @@ fn draw_blocks_helper(
                     inside_padding,
                     offset,
                     width,
+                    source,
                     blocks_theme,
                     font,
                     painter,
@@ -107,6 +118,7 @@ fn draw_block(
     padding_inside: f32,
     offset: Vec2,
     width: f32,
+    source: &Rope,
     blocks_theme: BlocksTheme,
     font: &MonospaceFont,
     painter: &Painter,
@@ -117,8 +129,12 @@ fn draw_block(
         None => return,
     };
 
+    // start where the text of the block is drawn (which is further right if indented with tabs)
+    let visual_col = source
+        .get_line(block.line)
+        .map_or(block.col, |line| line.visual_col(block.col, font.tab_width));
     let start_pt = Pos2::new(
-        (block.col as f32) * font.size.x - (BLOCK_CONFIG.stroke_width / 2.0),
+        (visual_col as f32) * font.size.x - (BLOCK_CONFIG.stroke_width / 2.0),
         (block.line as f32) * font.size.y
             - (BLOCK_CONFIG.stroke_width / 2.0)
             - (BLOCK_CONFIG.inner_pad / 2.0)
@@ -68,6 +68,8 @@ pub enum BlockType {
     For,
     Try,
     Switch,
+    With,
+    Match,
     Generic,
     Comment,
     Error,
@@ -0,0 +1,362 @@
+use egui::{
+    os::OperatingSystem, Align2, Color32, Key, Rect, Response, Sense, TextEdit, Ui, Vec2, Widget,
+};
+
+use super::{commands::Command, keymap, MonospaceFont};
+use crate::{lang::registry, theme, theme::blocks_theme::BlocksTheme, util_widgets::SelectableRow};
+
+const MARGIN: Vec2 = Vec2::splat(2.5);
+
+/// height of the text box the actions are filtered with
+const TEXT_BOX_HEIGHT: f32 = 30.0;
+
+/// the most actions that are listed at once
+const MAX_ROWS: usize = 12;
+
+/// Something that can be done from the command palette
+#[derive(Clone, PartialEq, Debug)]
+pub enum PaletteAction {
+    Command(Command),
+    SetLanguage(String),
+    SetBlocksTheme(&'static str),
+    GoToLine(usize),
+}
+
+struct Entry {
+    action: PaletteAction,
+    title: String,
+}
+
+/// A popup listing every action, filtered by what is typed
+pub struct CommandPalette {
+    entries: Vec<Entry>,
+
+    /// indices of the entries that match the filter, best match first
+    matches: Vec<usize>,
+
+    filter: String,
+
+    /// the index in `matches` of the selected entry
+    selection: usize,
+
+    /// the index in `matches` of the first row shown
+    scroll: usize,
+
+    show: bool,
+    is_appearing: bool,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        CommandPalette {
+            entries: vec![],
+            matches: vec![],
+            filter: "".to_string(),
+            selection: 0,
+            scroll: 0,
+            show: false,
+            is_appearing: false,
+        }
+    }
+
+    /// Show the palette, ready to go to a line
+    pub fn show_go_to_line(&mut self) {
+        self.show();
+        self.filter = ":".to_string();
+        self.update_matches();
+    }
+
+    pub fn show(&mut self) {
+        // list the languages that are registered now
+        self.entries = Command::ALL
+            .iter()
+            .filter(|command| **command != Command::ShowAllCommands)
+            .map(|command| Entry {
+                action: PaletteAction::Command(*command),
+                title: command.title().to_string(),
+            })
+            .chain(registry::all().into_iter().map(|config| Entry {
+                action: PaletteAction::SetLanguage(config.name.clone()),
+                title: format!("Change Language: {}", config.name),
+            }))
+            .chain(BlocksTheme::NAMES.iter().map(|&(name, title)| Entry {
+                action: PaletteAction::SetBlocksTheme(name),
+                title: format!("Blocks Theme: {title}"),
+            }))
+            .collect();
+
+        self.filter.clear();
+        self.update_matches();
+        self.show = true;
+        self.is_appearing = true;
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.show
+    }
+
+    /// The size of the popup when it is shown
+    pub fn calc_size(&self, font: &MonospaceFont) -> Vec2 {
+        let rows = self.matches.len().min(MAX_ROWS);
+        Vec2::new(400.0, TEXT_BOX_HEIGHT + rows as f32 * font.size.y)
+    }
+
+    pub fn widget<'a>(
+        &'a mut self,
+        chosen: &'a mut Option<PaletteAction>,
+        font: &'a MonospaceFont,
+    ) -> impl Widget + 'a {
+        move |ui: &mut Ui| -> Response {
+            if !self.show {
+                return ui.allocate_response(Vec2::ZERO, Sense::hover());
+            }
+
+            let (id, rect) = ui.allocate_space(ui.available_size());
+            let response = ui.interact(rect, id, Sense::click_and_drag());
+
+            // set background color
+            ui.painter().rect_filled(rect, 0.0, theme::POPUP_BACKGROUND);
+
+            self.draw_text_box(ui, rect, chosen);
+            self.draw_rows(ui, rect, chosen, font);
+
+            response
+        }
+    }
+
+    fn draw_text_box(&mut self, ui: &mut Ui, rect: Rect, chosen: &mut Option<PaletteAction>) {
+        let text_response = ui.put(
+            Rect::from_min_size(
+                rect.min + MARGIN,
+                Vec2::new(rect.width(), TEXT_BOX_HEIGHT) - (MARGIN * 2.0),
+            ),
+            TextEdit::singleline(&mut self.filter)
+                .hint_text("Type a command, or : and a line number")
+                .background_color(Color32::BLACK),
+        );
+
+        if text_response.changed() {
+            self.update_matches();
+        }
+
+        // grab focus if the palette is appearing
+        if self.is_appearing {
+            text_response.request_focus();
+            self.is_appearing = false;
+        }
+
+        // move through the list with the arrow keys
+        if text_response.has_focus() && !self.matches.is_empty() {
+            let len = self.matches.len();
+            if ui.input(|i| i.key_pressed(Key::ArrowDown)) {
+                self.selection = (self.selection + 1) % len;
+                self.scroll_to_selection();
+            } else if ui.input(|i| i.key_pressed(Key::ArrowUp)) {
+                self.selection = (self.selection + len - 1) % len;
+                self.scroll_to_selection();
+            }
+        }
+
+        // scroll through the list with the mouse wheel
+        if ui.rect_contains_pointer(rect) {
+            let scroll_delta = ui.input(|i| i.raw_scroll_delta.y);
+            let max_scroll = self.matches.len().saturating_sub(MAX_ROWS);
+            if scroll_delta < 0.0 {
+                self.scroll = (self.scroll + 1).min(max_scroll);
+            } else if scroll_delta > 0.0 {
+                self.scroll = self.scroll.saturating_sub(1);
+            }
+        }
+
+        // handle pressing enter (run the action), escape, or clicking away (close)
+        if text_response.lost_focus() {
+            if ui.input(|i| i.key_pressed(Key::Enter)) {
+                self.choose(self.selection, chosen);
+            } else if ui.input(|i| i.key_pressed(Key::Escape)) || !ui.rect_contains_pointer(rect) {
+                self.show = false;
+            } else {
+                // clicking a row takes the focus
+                text_response.request_focus();
+            }
+        }
+    }
+
+    fn draw_rows(
+        &mut self,
+        ui: &mut Ui,
+        rect: Rect,
+        chosen: &mut Option<PaletteAction>,
+        font: &MonospaceFont,
+    ) {
+        let is_mac = matches!(ui.ctx().os(), OperatingSystem::Mac | OperatingSystem::IOS);
+        let origin = rect.min + Vec2::new(0.0, TEXT_BOX_HEIGHT);
+
+        let mut clicked_row = None;
+        let shown = self
+            .matches
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(MAX_ROWS);
+        for (row, entry_idx) in shown {
+            let entry = &self.entries[*entry_idx];
+            let row_rect = Rect::from_min_size(
+                origin + Vec2::new(0.0, (row - self.scroll) as f32 * font.size.y),
+                Vec2::new(rect.width(), font.size.y),
+            );
+            let row_response = ui.put(
+                row_rect,
+                SelectableRow::new(
+                    &entry.title,
+                    theme::INTERFACE_TEXT,
+                    row == self.selection,
+                    font.id.clone(),
+                ),
+            );
+
+            // show the shortcut for commands on the right
+            if let PaletteAction::Command(command) = entry.action {
+                if let Some(shortcut) = keymap::shortcut_for(command, is_mac) {
+                    let text = ui.ctx().format_shortcut(&shortcut.keyboard_shortcut());
+                    ui.painter().text(
+                        row_rect.right_center() - Vec2::new(MARGIN.x, 0.0),
+                        Align2::RIGHT_CENTER,
+                        text,
+                        font.id.clone(),
+                        theme::LINE_NUMBERS,
+                    );
+                }
+            }
+
+            if row_response.clicked() {
+                clicked_row = Some(row);
+            }
+        }
+        if let Some(row) = clicked_row {
+            self.choose(row, chosen);
+        }
+
+        if ui.rect_contains_pointer(rect) {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+        }
+    }
+
+    fn choose(&mut self, row: usize, chosen: &mut Option<PaletteAction>) {
+        if let Some(entry_idx) = self.matches.get(row) {
+            *chosen = Some(self.entries[*entry_idx].action.clone());
+        }
+        self.show = false;
+    }
+
+    /// Scroll the list so the selected row is shown
+    fn scroll_to_selection(&mut self) {
+        if self.selection < self.scroll {
+            self.scroll = self.selection;
+        } else if self.selection >= self.scroll + MAX_ROWS {
+            self.scroll = self.selection + 1 - MAX_ROWS;
+        }
+    }
+
+    fn update_matches(&mut self) {
+        self.selection = 0;
+        self.scroll = 0;
+
+        // `:` and a line number goes to the line (with an entry that is only listed for it)
+        self.entries
+            .retain(|entry| !matches!(entry.action, PaletteAction::GoToLine(_)));
+        if let Some(line) = self.filter.strip_prefix(':') {
+            self.matches = vec![];
+            if let Ok(line) = line.trim().parse() {
+                self.matches.push(self.entries.len());
+                self.entries.push(Entry {
+                    action: PaletteAction::GoToLine(line),
+                    title: format!("Go to Line {line}"),
+                });
+            }
+            return;
+        }
+
+        let mut scored: Vec<(usize, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| Some((idx, fuzzy_score(&self.filter, &entry.title)?)))
+            .collect();
+
+        // best matches first, keeping the order of entries that match as well
+        scored.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        self.matches = scored.into_iter().map(|(idx, _)| idx).collect();
+    }
+}
+
+/// How well the query matches the text, if all of its characters are in the text in order.
+/// Characters that continue a match or start a word count for more.
+fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let mut text_chars = text.chars().enumerate().peekable();
+    let mut prev_char: Option<char> = None;
+    let mut last_match: Option<usize> = None;
+    let mut score = 0;
+
+    for query_char in query.chars().filter(|c| !c.is_whitespace()) {
+        loop {
+            let (idx, c) = text_chars.next()?;
+            let is_word_start = prev_char.is_none_or(|prev| !prev.is_alphanumeric());
+            prev_char = Some(c);
+
+            if c.to_lowercase().eq(query_char.to_lowercase()) {
+                score += 1;
+                if last_match.is_some_and(|last| last + 1 == idx) {
+                    score += 2;
+                }
+                if is_word_start {
+                    score += 3;
+                }
+                last_match = Some(idx);
+                break;
+            }
+        }
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matches_in_order() {
+        assert!(fuzzy_score("tgcm", "Toggle Comment").is_some());
+        assert!(fuzzy_score("cmt", "Toggle Comment").is_some());
+        assert_eq!(fuzzy_score("mc", "Toggle Comment"), None);
+        assert_eq!(fuzzy_score("", "Undo"), Some(0));
+
+        // word starts and runs of characters rank higher
+        assert!(fuzzy_score("tc", "Toggle Comment") > fuzzy_score("tc", "Go to Bracket"));
+        assert!(fuzzy_score("undo", "Undo") > fuzzy_score("undo", "Run Indented Code"));
+    }
+
+    #[test]
+    fn lists_a_line_to_go_to() {
+        let mut palette = CommandPalette::new();
+        palette.show_go_to_line();
+        assert!(palette.matches.is_empty());
+
+        palette.filter.push_str("12");
+        palette.update_matches();
+        let chosen: Vec<_> = palette
+            .matches
+            .iter()
+            .map(|idx| &palette.entries[*idx].action)
+            .collect();
+        assert_eq!(chosen, [&PaletteAction::GoToLine(12)]);
+
+        // every other entry comes back without the `:`
+        palette.filter = "line".to_string();
+        palette.update_matches();
+        assert!(palette.matches.len() > 1);
+        assert!(palette
+            .entries
+            .iter()
+            .all(|entry| !matches!(entry.action, PaletteAction::GoToLine(_))));
+    }
+}
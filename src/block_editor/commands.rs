@@ -0,0 +1,95 @@
+/// Declares the commands, with the name used for them in keymaps and the title shown to users
+macro_rules! commands {
+    ($($command:ident => $name:literal, $title:literal;)*) => {
+        /// An action that can be bound to a key (see `keymap`)
+        #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+        pub enum Command {
+            $($command,)*
+        }
+
+        impl Command {
+            /// Every command, in the order they are declared
+            pub const ALL: &[Command] = &[$(Command::$command,)*];
+
+            /// The name used for the command in keymaps
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Command::$command => $name,)*
+                }
+            }
+
+            /// The title shown to users
+            pub fn title(self) -> &'static str {
+                match self {
+                    $(Command::$command => $title,)*
+                }
+            }
+        }
+    };
+}
+
+commands! {
+    // editor
+    Find => "find", "Find";
+    ShowAllCommands => "command_palette", "Show All Commands";
+    GoToLine => "go_to_line", "Go to Line";
+
+    // editing
+    Undo => "undo", "Undo";
+    Redo => "redo", "Redo";
+    Newline => "newline", "Insert Line Break";
+    Indent => "indent", "Indent Line";
+    Unindent => "unindent", "Outdent Line";
+    DeleteLeft => "delete_left", "Delete Left";
+    DeleteRight => "delete_right", "Delete Right";
+    DeleteWordLeft => "delete_word_left", "Delete Word Left";
+    DeleteWordRight => "delete_word_right", "Delete Word Right";
+    DeleteLineLeft => "delete_line_left", "Delete to Line Start";
+    DeleteLineRight => "delete_line_right", "Delete to Line End";
+    ToggleComment => "toggle_comment", "Toggle Comment";
+
+    // cursor movement
+    CursorLeft => "cursor_left", "Move Cursor Left";
+    CursorRight => "cursor_right", "Move Cursor Right";
+    CursorUp => "cursor_up", "Move Cursor Up";
+    CursorDown => "cursor_down", "Move Cursor Down";
+    CursorWordLeft => "cursor_word_left", "Move Cursor to Previous Word";
+    CursorWordRight => "cursor_word_right", "Move Cursor to Next Word";
+    CursorLineStart => "cursor_line_start", "Move Cursor to Line Start";
+    CursorLineEnd => "cursor_line_end", "Move Cursor to Line End";
+    CursorDocumentStart => "cursor_document_start", "Move Cursor to Start";
+    CursorDocumentEnd => "cursor_document_end", "Move Cursor to End";
+
+    // selection
+    SelectLeft => "select_left", "Select Left";
+    SelectRight => "select_right", "Select Right";
+    SelectUp => "select_up", "Select Up";
+    SelectDown => "select_down", "Select Down";
+    SelectWordLeft => "select_word_left", "Select to Previous Word";
+    SelectWordRight => "select_word_right", "Select to Next Word";
+    SelectLineStart => "select_line_start", "Select to Line Start";
+    SelectLineEnd => "select_line_end", "Select to Line End";
+    SelectDocumentStart => "select_document_start", "Select to Start";
+    SelectDocumentEnd => "select_document_end", "Select to End";
+    SelectAll => "select_all", "Select All";
+    GrowSelection => "grow_selection", "Expand Selection";
+    ShrinkSelection => "shrink_selection", "Shrink Selection";
+    JumpToBracket => "jump_to_bracket", "Go to Bracket";
+    SelectToBracket => "select_to_bracket", "Select to Bracket";
+
+    // multiple cursors
+    AddNextOccurrence => "add_next_occurrence", "Add Selection to Next Match";
+    AddCursorAbove => "add_cursor_above", "Add Cursor Above";
+    AddCursorBelow => "add_cursor_below", "Add Cursor Below";
+    Cancel => "cancel", "Remove Extra Cursors";
+}
+
+impl Command {
+    /// Find a command by its name in keymaps
+    pub fn from_name(name: &str) -> Option<Command> {
+        Command::ALL
+            .iter()
+            .find(|command| command.name() == name)
+            .copied()
+    }
+}
@@ -24,10 +24,10 @@ struct PaletteItem {
 }
 
 impl PaletteItem {
-    fn new(snippet: &Snippet, lang: &mut Language, font: &MonospaceFont) -> Self {
+    fn new(snippet: &'static Snippet, lang: &mut Language, font: &MonospaceFont) -> Self {
         Self {
-            id: snippet.id,
-            block: LooseBlock::new(snippet.source, 10.0, lang, font),
+            id: &snippet.id,
+            block: LooseBlock::new(&snippet.source, 10.0, lang, font),
         }
     }
 }
@@ -55,7 +55,12 @@ impl BlockPalette {
                     .collect()
             })
             .collect();
-        self.palette_names = lang.config.palettes.iter().map(|p| p.name).collect();
+        self.palette_names = lang
+            .config
+            .palettes
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
         self.selected_palette = 0;
     }
 
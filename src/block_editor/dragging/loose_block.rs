@@ -2,13 +2,18 @@ use egui::{Painter, Response, Sense, Ui, Vec2, Widget};
 use ropey::Rope;
 
 use crate::{
-    block_editor::{blocks::BlockTrees, rope_ext::RopeExt, text_drawer::TextDrawer, MonospaceFont},
+    block_editor::{
+        blocks::BlockTrees,
+        rope_ext::{RopeExt, RopeSliceExt},
+        text_drawer::TextDrawer,
+        MonospaceFont,
+    },
     lang::{tree_manager::TreeManager, Language},
     theme::blocks_theme::BlocksTheme,
 };
 
 pub struct LooseBlock {
-    text: String,
+    text: Rope,
     blocks: BlockTrees,
     min_size: Vec2,
     tree_manager: TreeManager,
@@ -49,7 +54,7 @@ impl LooseBlock {
         font: &MonospaceFont,
     ) -> Self {
         let mut block = Self {
-            text: String::new(),
+            text: Rope::new(),
             blocks: BlockTrees::default(),
             min_size: Vec2::ZERO,
             tree_manager: TreeManager::new(lang),
@@ -61,19 +66,25 @@ impl LooseBlock {
     }
 
     fn set_text(&mut self, text: &str, lang: &mut Language, font: &MonospaceFont) {
-        self.text = text.to_string();
-        let rope = Rope::from_str(text);
+        self.text = Rope::from_str(text);
+        let rope = &self.text;
 
-        self.tree_manager.replace(&rope, lang);
-        self.text_drawer
-            .highlight(self.tree_manager.get_cursor().node(), &rope, lang);
+        self.tree_manager.replace(rope, lang);
+        let root_node = self.tree_manager.get_cursor().map(|cursor| cursor.node());
+        self.text_drawer.highlight(root_node, rope, lang);
 
         // find blocks
-        self.blocks =
-            BlockTrees::for_ts_tree(&mut self.tree_manager.get_cursor(), &rope, lang.config);
+        self.blocks = match self.tree_manager.get_cursor() {
+            Some(mut cursor) => BlockTrees::for_ts_tree(&mut cursor, rope, lang.config),
+            None => BlockTrees::without_tree(rope),
+        };
 
         // find dimensions
-        let max_chars = rope.lines().map(|l| l.len_chars()).max().unwrap_or(0);
+        let max_chars = rope
+            .lines()
+            .map(|l| l.visual_col(l.len_chars(), font.tab_width))
+            .max()
+            .unwrap_or(0);
         let width = max_chars as f32 * font.size.x + self.interior_padding;
         let line_count = rope.len_lines() - if rope.ends_with('\n') { 1 } else { 0 };
         let height = (font.size.y * line_count as f32) + self.blocks.padding().total();
@@ -89,7 +100,7 @@ impl LooseBlock {
         painter: &Painter,
     ) {
         self.blocks
-            .draw(offset, width, None, blocks_theme, font, painter);
+            .draw(offset, width, None, &self.text, blocks_theme, font, painter);
         self.text_drawer
             .draw(self.blocks.padding(), offset, None, font, painter);
     }
@@ -98,7 +109,7 @@ impl LooseBlock {
         self.min_size
     }
 
-    pub fn text(&self) -> &str {
+    pub fn text(&self) -> &Rope {
         &self.text
     }
 }
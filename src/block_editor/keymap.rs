@@ -0,0 +1,352 @@
+use std::{
+    fmt,
+    sync::{LazyLock, Mutex},
+};
+
+use egui::{Key, KeyboardShortcut, Modifiers};
+use serde::Deserialize;
+
+use super::commands::Command;
+
+/// The bindings used unless the host overrides them
+const DEFAULT_KEYMAP: &str = include_str!("default_keymap.json");
+
+/// The keymap that key presses are looked up in
+static KEYMAP: LazyLock<Mutex<Keymap>> = LazyLock::new(|| Mutex::new(Keymap::default_bindings()));
+
+/// Find the command bound to a key press
+pub fn command_for(key: Key, modifiers: Modifiers) -> Option<Command> {
+    KEYMAP.lock().unwrap().command_for(key, modifiers)
+}
+
+/// If a key press should be passed on to the host (like VS Code).
+/// Unbound hotkeys and function keys are passed on, along with bindings marked to propagate.
+#[allow(dead_code)] // only used by the web build
+pub fn should_propagate(key: Key, modifiers: Modifiers) -> bool {
+    KEYMAP.lock().unwrap().should_propagate(key, modifiers)
+}
+
+/// The first shortcut that runs a command (on Mac if `is_mac`)
+pub fn shortcut_for(command: Command, is_mac: bool) -> Option<Shortcut> {
+    KEYMAP.lock().unwrap().shortcut_for(command, is_mac)
+}
+
+/// Use the bindings from a JSON keymap, which take priority over the default ones
+pub fn set_custom(json: &str) -> Result<(), KeymapError> {
+    let custom = Keymap::from_json(json)?;
+    let mut keymap = KEYMAP.lock().unwrap();
+    *keymap = Keymap::default_bindings();
+    keymap.bindings.splice(0..0, custom.bindings);
+    Ok(())
+}
+
+/// A list of bindings from key presses to commands.
+/// When multiple bindings match a key press, the first one is used.
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+struct Binding {
+    shortcut: Shortcut,
+
+    /// the command to run, or `None` if the key is unbound
+    command: Option<Command>,
+
+    /// if the key press is also passed on to the host
+    propagate: bool,
+}
+
+/// A binding as it is written in a keymap, like
+/// `{ "key": "Cmd+Shift+Z", "command": "redo" }`.
+/// A `null` command unbinds the key.
+#[derive(Deserialize)]
+struct RawBinding {
+    key: String,
+    command: Option<String>,
+    #[serde(default)]
+    propagate: bool,
+}
+
+impl Keymap {
+    fn default_bindings() -> Self {
+        Keymap::from_json(DEFAULT_KEYMAP).expect("the default keymap should be valid")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, KeymapError> {
+        let raw: Vec<RawBinding> = serde_json::from_str(json).map_err(KeymapError::Json)?;
+        let bindings = raw
+            .into_iter()
+            .map(|raw| {
+                let command = match raw.command {
+                    Some(name) => {
+                        Some(Command::from_name(&name).ok_or(KeymapError::UnknownCommand(name))?)
+                    }
+                    None => None,
+                };
+                Ok(Binding {
+                    shortcut: Shortcut::parse(&raw.key)?,
+                    command,
+                    propagate: raw.propagate,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Keymap { bindings })
+    }
+
+    fn binding_for(&self, key: Key, modifiers: Modifiers) -> Option<&Binding> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.shortcut.matches(key, modifiers))
+    }
+
+    pub fn command_for(&self, key: Key, modifiers: Modifiers) -> Option<Command> {
+        self.binding_for(key, modifiers)
+            .and_then(|binding| binding.command)
+    }
+
+    pub fn shortcut_for(&self, command: Command, is_mac: bool) -> Option<Shortcut> {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.command == Some(command))
+            .find(|binding| {
+                // skip shortcuts that run an earlier binding instead
+                let shortcut = binding.shortcut;
+                let pressed = shortcut.pressed_modifiers(is_mac);
+                self.binding_for(shortcut.key, pressed)
+                    .is_some_and(|first| std::ptr::eq(first, *binding))
+            })
+            .map(|binding| binding.shortcut)
+    }
+
+    pub fn should_propagate(&self, key: Key, modifiers: Modifiers) -> bool {
+        match self.binding_for(key, modifiers) {
+            Some(Binding {
+                command: Some(_),
+                propagate,
+                ..
+            }) => *propagate,
+            _ => {
+                let is_hotkey = modifiers.any() && !modifiers.shift_only();
+                let is_function_key = matches!(
+                    key,
+                    Key::F1
+                        | Key::F2
+                        | Key::F3
+                        | Key::F4
+                        | Key::F5
+                        | Key::F6
+                        | Key::F7
+                        | Key::F8
+                        | Key::F9
+                        | Key::F10
+                        | Key::F11
+                        | Key::F12
+                );
+                is_hotkey || is_function_key
+            }
+        }
+    }
+}
+
+/// A key and the modifiers held with it, like `Cmd+Shift+Z`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Shortcut {
+    pub key: Key,
+
+    /// `Cmd`: the command key on Mac, and Ctrl elsewhere
+    pub command: bool,
+
+    /// `Ctrl`: the control key (which is the same as `Cmd` when not on Mac)
+    pub ctrl: bool,
+
+    /// `Alt` (or `Option`)
+    pub alt: bool,
+
+    /// `Shift`
+    pub shift: bool,
+}
+
+impl Shortcut {
+    /// Parse a shortcut written as modifiers and a key joined by `+`
+    pub fn parse(text: &str) -> Result<Self, KeymapError> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key_name = parts.pop().unwrap_or_default();
+        let key = Key::from_name(key_name).ok_or(KeymapError::UnknownKey(text.to_string()))?;
+
+        let mut shortcut = Shortcut {
+            key,
+            command: false,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        for modifier in parts {
+            match modifier {
+                "Cmd" => shortcut.command = true,
+                "Ctrl" => shortcut.ctrl = true,
+                "Alt" | "Option" => shortcut.alt = true,
+                "Shift" => shortcut.shift = true,
+                _ => return Err(KeymapError::UnknownModifier(modifier.to_string())),
+            }
+        }
+        Ok(shortcut)
+    }
+
+    /// If the shortcut is exactly the key and modifiers that were pressed
+    pub fn matches(&self, key: Key, modifiers: Modifiers) -> bool {
+        if key != self.key || modifiers.alt != self.alt || modifiers.shift != self.shift {
+            return false;
+        }
+
+        // when not on Mac, ctrl is the command key (and `mac_cmd` is never pressed)
+        match (self.command, self.ctrl) {
+            (false, false) => !modifiers.command && !modifiers.ctrl,
+            (true, false) => modifiers.command && !(modifiers.ctrl && modifiers.mac_cmd),
+            (false, true) => modifiers.ctrl && !modifiers.mac_cmd,
+            (true, true) => modifiers.command && modifiers.ctrl,
+        }
+    }
+
+    /// The modifiers that are pressed for the shortcut (on Mac if `is_mac`)
+    fn pressed_modifiers(&self, is_mac: bool) -> Modifiers {
+        if is_mac {
+            Modifiers {
+                alt: self.alt,
+                ctrl: self.ctrl,
+                shift: self.shift,
+                mac_cmd: self.command,
+                command: self.command,
+            }
+        } else {
+            Modifiers {
+                alt: self.alt,
+                ctrl: self.command || self.ctrl,
+                shift: self.shift,
+                mac_cmd: false,
+                command: self.command || self.ctrl,
+            }
+        }
+    }
+
+    /// The shortcut as egui describes it, for showing it to users
+    pub fn keyboard_shortcut(&self) -> KeyboardShortcut {
+        let modifiers = Modifiers {
+            alt: self.alt,
+            ctrl: self.ctrl,
+            shift: self.shift,
+            mac_cmd: false,
+            command: self.command,
+        };
+        KeyboardShortcut::new(modifiers, self.key)
+    }
+}
+
+/// Why a keymap could not be loaded
+#[derive(Debug)]
+pub enum KeymapError {
+    Json(serde_json::Error),
+    UnknownKey(String),
+    UnknownModifier(String),
+    UnknownCommand(String),
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapError::Json(err) => write!(f, "invalid keymap: {err}"),
+            KeymapError::UnknownKey(shortcut) => write!(f, "unknown key in shortcut: {shortcut}"),
+            KeymapError::UnknownModifier(name) => write!(f, "unknown modifier: {name}"),
+            KeymapError::UnknownCommand(name) => write!(f, "unknown command: {name}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAC_CMD: Modifiers = Modifiers {
+        mac_cmd: true,
+        command: true,
+        ..Modifiers::NONE
+    };
+    const MAC_CTRL: Modifiers = Modifiers::CTRL;
+    const WINDOWS_CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        command: true,
+        ..Modifiers::NONE
+    };
+
+    #[test]
+    fn matches_commands_on_each_platform() {
+        let keymap = Keymap::default_bindings();
+
+        // cmd on mac is ctrl elsewhere
+        assert_eq!(keymap.command_for(Key::Z, MAC_CMD), Some(Command::Undo));
+        assert_eq!(
+            keymap.command_for(Key::Z, WINDOWS_CTRL),
+            Some(Command::Undo)
+        );
+        assert_eq!(keymap.command_for(Key::Z, MAC_CTRL), None);
+
+        // the first binding wins, so ctrl bindings only apply on mac when cmd uses the key
+        assert_eq!(
+            keymap.command_for(Key::D, WINDOWS_CTRL),
+            Some(Command::AddNextOccurrence)
+        );
+        assert_eq!(
+            keymap.command_for(Key::D, MAC_CTRL),
+            Some(Command::DeleteRight)
+        );
+        assert_eq!(
+            keymap.command_for(Key::Backspace, WINDOWS_CTRL),
+            Some(Command::DeleteWordLeft)
+        );
+        assert_eq!(
+            keymap.command_for(Key::Backspace, MAC_CMD),
+            Some(Command::DeleteLineLeft)
+        );
+    }
+
+    #[test]
+    fn finds_shortcuts_that_are_not_shadowed() {
+        let keymap = Keymap::default_bindings();
+        let undo = keymap.shortcut_for(Command::Undo, false).unwrap();
+        assert_eq!((undo.key, undo.command), (Key::Z, true));
+
+        // ctrl+d is only delete on mac, since it is cmd+d elsewhere
+        let custom = r#"[
+            { "key": "Cmd+D", "command": "add_next_occurrence" },
+            { "key": "Ctrl+D", "command": "delete_right" }
+        ]"#;
+        let keymap = Keymap::from_json(custom).unwrap();
+        assert!(keymap.shortcut_for(Command::DeleteRight, true).is_some());
+        assert!(keymap.shortcut_for(Command::DeleteRight, false).is_none());
+    }
+
+    #[test]
+    fn custom_bindings_propagate() {
+        let custom = r#"[
+            { "key": "Cmd+Z", "command": "undo", "propagate": true },
+            { "key": "Cmd+D", "command": null }
+        ]"#;
+        let keymap = Keymap::from_json(custom).unwrap();
+        assert!(keymap.should_propagate(Key::Z, MAC_CMD));
+        assert!(keymap.should_propagate(Key::D, MAC_CMD));
+        assert_eq!(keymap.command_for(Key::D, MAC_CMD), None);
+
+        // bound keys are kept, but other hotkeys are passed on
+        let keymap = Keymap::default_bindings();
+        assert!(!keymap.should_propagate(Key::Z, MAC_CMD));
+        assert!(!keymap.should_propagate(Key::W, WINDOWS_CTRL));
+        assert!(!keymap.should_propagate(Key::P, WINDOWS_CTRL | Modifiers::SHIFT));
+        assert!(keymap.should_propagate(Key::S, MAC_CMD));
+        assert!(!keymap.should_propagate(Key::S, Modifiers::SHIFT));
+        assert!(keymap.should_propagate(Key::F5, Modifiers::NONE));
+
+        assert!(matches!(
+            Keymap::from_json(r#"[{ "key": "Hyper+Z", "command": "undo" }]"#),
+            Err(KeymapError::UnknownModifier(_))
+        ));
+    }
+}
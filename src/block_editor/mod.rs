@@ -1,17 +1,20 @@
 use egui::{
     text::Fonts, CentralPanel, FontFamily, FontId, Frame, Pos2, Rect, Sense, SidePanel, Widget,
 };
-use egui::{Event, Key, Modifiers, Vec2};
+use egui::{Event, Vec2};
 use ropey::Rope;
 use source::Source;
 
-use crate::lang::Language;
+use crate::lang::{config::LanguageConfig, registry, Language};
 use crate::lsp::completion::VSCodeCompletionItem;
 use crate::theme::blocks_theme::BlocksTheme;
 use crate::{theme, vscode};
 
 mod blocks;
+mod command_palette;
+pub mod commands;
 mod dragging;
+pub mod keymap;
 mod rope_ext;
 mod search;
 pub mod source;
@@ -21,6 +24,8 @@ pub mod text_range;
 
 pub use blocks::BlockType;
 
+use self::command_palette::{CommandPalette, PaletteAction};
+use self::commands::Command;
 use self::dragging::block_palette::BlockPalette;
 use self::dragging::loose_block::LooseBlock;
 use self::search::SearchPopup;
@@ -48,6 +53,16 @@ pub struct BlockEditor {
     /// the source code being edited and the associated language
     source: Source,
 
+    /// the name of the file being edited, for detecting its language
+    file_name: String,
+
+    /// if the language was chosen by the user (instead of detected)
+    language_overridden: bool,
+
+    /// if the language should be detected again when the text is set,
+    /// since the file was set before its contents were known
+    detect_on_set_text: bool,
+
     /// the color theme for the blocks
     blocks_theme: BlocksTheme,
 
@@ -63,6 +78,9 @@ pub struct BlockEditor {
     /// the find (and replace view)
     search_popup: SearchPopup,
 
+    /// the popup for running any command
+    command_palette: CommandPalette,
+
     /// text that is currently getting dragged
     drag_block: Option<DragSession>,
 
@@ -83,8 +101,13 @@ pub enum ExternalCommand {
     // setup
     SetText(String),
     SetFile { name: String, contents: String },
+    SetLanguage(String),
     SetBlocksTheme(BlocksTheme),
     SetFont(String, f32),
+    SetTabWidth(usize),
+    SetBracketColorization(bool),
+    SetKeymap(String),
+    RegisterLanguage(String, String, String),
 
     // external edits
     ApplyEdit(TextEdit<'static>),
@@ -110,8 +133,14 @@ pub struct MonospaceFont {
 
     /// The size in pixels of a single character
     size: Vec2,
+
+    /// How many characters wide a tab is drawn
+    tab_width: usize,
 }
 
+/// The number of characters a tab is drawn across, unless the editor sets a different amount
+const DEFAULT_TAB_WIDTH: usize = 4;
+
 impl MonospaceFont {
     /// Create a new monospace font.
     /// Note: does not calculate the size yet. Must also call `calculate_size` before using..
@@ -121,6 +150,7 @@ impl MonospaceFont {
         Self {
             id,
             size: Vec2::ZERO,
+            tab_width: DEFAULT_TAB_WIDTH,
         }
     }
 
@@ -134,15 +164,19 @@ impl BlockEditor {
         let lang = Language::for_file(file_name);
         vscode::log_event(
             "opened-file",
-            std::collections::HashMap::from([("lang", lang.config.name)]),
+            std::collections::HashMap::from([("lang", lang.config.name.as_str())]),
         );
         BlockEditor {
             source: Source::new(Rope::new(), lang),
+            file_name: file_name.to_string(),
+            language_overridden: false,
+            detect_on_set_text: true,
             blocks_theme: BlocksTheme::for_str(blocks_theme),
             font,
             text_editor: TextEditor::new(),
             block_palette: BlockPalette::new(),
             search_popup: SearchPopup::new(),
+            command_palette: CommandPalette::new(),
             drag_block: None,
             dragging_popup: None,
         }
@@ -164,20 +198,47 @@ impl BlockEditor {
                 match command {
                     ExternalCommand::SetText(text) => {
                         self.source.set_text(Rope::from_str(text));
+
+                        // the file might have been set before its contents were known
+                        // (but later syncs of the text don't change the language)
+                        if std::mem::take(&mut self.detect_on_set_text) && !self.language_overridden
+                        {
+                            self.detect_language(text);
+                        }
                     }
                     ExternalCommand::SetFile { name, contents } => {
-                        let language = Language::for_file(name);
+                        let language = Language::detect(name, contents);
                         self.source = Source::new(Rope::from_str(contents), language);
+                        self.file_name = name.clone();
+                        self.language_overridden = false;
+                        self.detect_on_set_text = contents.is_empty();
                         self.block_palette
                             .populate(&mut self.source.lang, &self.font)
                     }
+                    ExternalCommand::SetLanguage(name) => {
+                        self.override_language(name);
+                    }
                     ExternalCommand::SetBlocksTheme(theme) => {
                         self.blocks_theme = *theme;
                     }
                     ExternalCommand::SetFont(font_name, font_size) => {
+                        let tab_width = self.font.tab_width;
                         self.font = MonospaceFont::new(font_name, *font_size);
+                        self.font.tab_width = tab_width;
                         ui.fonts(|f| self.font.calculate_size(f));
                     }
+                    ExternalCommand::SetTabWidth(tab_width) => {
+                        // a width of 0 would put every character after a tab on top of each other
+                        self.font.tab_width = (*tab_width).max(1);
+                    }
+                    ExternalCommand::RegisterLanguage(definition, blocks_query, indents_query) => {
+                        self.register_language(definition, blocks_query, indents_query);
+                    }
+                    ExternalCommand::SetKeymap(json) => {
+                        if let Err(err) = keymap::set_custom(json) {
+                            log::error!("Could not load keymap: {err}");
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -198,23 +259,78 @@ impl BlockEditor {
         }
     }
 
+    /// The name of the language of the current file
+    #[allow(dead_code)] // only used by the native app's language menu
+    pub fn language_name(&self) -> &str {
+        &self.source.lang.config.name
+    }
+
+    /// Switch to the language detected for the file, if it changed
+    fn detect_language(&mut self, text: &str) {
+        let language = Language::detect(&self.file_name, text);
+        if language.config.name != self.source.lang.config.name {
+            self.set_language(language);
+        }
+    }
+
+    /// Switch to a language chosen by the user, keeping the text and editing state
+    fn override_language(&mut self, name: &str) {
+        let Some(config) = registry::for_name(name) else {
+            log::warn!("Unknown language: {name}");
+            return;
+        };
+        let language = Language::new(config).expect("registered languages should be valid");
+        self.language_overridden = true;
+        self.set_language(language);
+    }
+
+    fn set_language(&mut self, language: Language) {
+        self.source.set_language(language);
+        self.block_palette
+            .populate(&mut self.source.lang, &self.font);
+    }
+
+    /// Add a language definition, reloading the current language if it was replaced
+    fn register_language(&mut self, definition: &str, blocks_query: &str, indents_query: &str) {
+        let config = match LanguageConfig::load(definition, blocks_query, indents_query) {
+            Ok(config) => config,
+            Err(err) => {
+                log::error!("Could not load language definition: {err}");
+                return;
+            }
+        };
+
+        let lang = match registry::register(config) {
+            Ok(lang) => lang,
+            Err(err) => {
+                log::error!("Invalid language definition: {err}");
+                return;
+            }
+        };
+        if lang.config.name == self.source.lang.config.name {
+            self.set_language(lang);
+        }
+    }
+
     fn editor_contents(&mut self, ui: &mut egui::Ui, external_commands: &[ExternalCommand]) {
-        let palette_size = self.block_palette.find_size();
-        SidePanel::right("palette_panel")
-            .exact_width(palette_size.x)
-            .show_separator_line(false)
-            .resizable(false)
-            .frame(Frame::NONE)
-            .show(ui.ctx(), |ui| {
-                ui.add(self.block_palette.widget(
-                    &mut self.drag_block,
-                    self.blocks_theme,
-                    &self.font,
-                ));
-            });
+        // languages without snippets (like plain text) don't get a palette
+        if !self.source.lang.config.palettes.is_empty() {
+            let palette_size = self.block_palette.find_size();
+            SidePanel::right("palette_panel")
+                .exact_width(palette_size.x)
+                .show_separator_line(false)
+                .resizable(false)
+                .frame(Frame::NONE)
+                .show(ui.ctx(), |ui| {
+                    ui.add(self.block_palette.widget(
+                        &mut self.drag_block,
+                        self.blocks_theme,
+                        &self.font,
+                    ));
+                });
+        }
 
-        // trigger find popup if command-f is pressed
-        // TODO: this should be moved to a more general place as more hotkeys are added
+        // trigger the find popup or command palette if their keys are pressed
         let events = ui.input(|i| i.events.clone());
         for event in events {
             if let Event::Key {
@@ -224,8 +340,11 @@ impl BlockEditor {
                 ..
             } = event
             {
-                if modifiers.matches_logically(Modifiers::COMMAND) && key == Key::F {
-                    self.search_popup.show();
+                match keymap::command_for(key, modifiers) {
+                    Some(Command::Find) => self.search_popup.show(),
+                    Some(Command::ShowAllCommands) => self.command_palette.show(),
+                    Some(Command::GoToLine) => self.command_palette.show_go_to_line(),
+                    _ => {}
                 }
             }
         }
@@ -252,9 +371,49 @@ impl BlockEditor {
                     self.search_popup
                         .widget(&self.source, editor_response.changed()),
                 );
+
+                // draw the command palette
+                if self.command_palette.is_shown() {
+                    let size = self.command_palette.calc_size(&self.font);
+                    let mut chosen: Option<PaletteAction> = None;
+                    ui.put(
+                        Rect::from_min_size(
+                            ui.max_rect().center_top() + Vec2::new(-size.x / 2.0, 5.0),
+                            size,
+                        ),
+                        self.command_palette.widget(&mut chosen, &self.font),
+                    );
+                    if let Some(action) = chosen {
+                        self.run_palette_action(action);
+                        ui.ctx().request_repaint();
+                    }
+                }
             });
     }
 
+    fn run_palette_action(&mut self, action: PaletteAction) {
+        match action {
+            PaletteAction::Command(Command::Find) => self.search_popup.show(),
+            PaletteAction::Command(Command::GoToLine) => self.command_palette.show_go_to_line(),
+            PaletteAction::Command(command) => {
+                self.text_editor.run_command(command, &mut self.source);
+                self.text_editor.request_focus();
+            }
+            PaletteAction::SetLanguage(name) => {
+                self.override_language(&name);
+                self.text_editor.request_focus();
+            }
+            PaletteAction::SetBlocksTheme(name) => {
+                self.blocks_theme = BlocksTheme::for_str(name);
+                self.text_editor.request_focus();
+            }
+            PaletteAction::GoToLine(line) => {
+                self.text_editor.go_to_line(line, &mut self.source);
+                self.text_editor.request_focus();
+            }
+        }
+    }
+
     fn draw_dragged_block(&mut self, ui: &mut egui::Ui) {
         if let Some(drag_block) = &mut self.drag_block {
             // show dragging cursor
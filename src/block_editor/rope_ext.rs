@@ -88,6 +88,18 @@ pub trait RopeSliceExt {
     ///
     /// runs in O(log N) time
     fn excluding_linebreak(&self) -> Self;
+
+    /// the column that a character column is drawn at, with tabs
+    /// extending to the next multiple of tab_width.
+    /// columns past the end of the slice are treated as one character wide
+    ///
+    /// runs in O(M log N) time (N number chars, M the column)
+    fn visual_col(&self, col: usize, tab_width: usize) -> usize;
+
+    /// the character column closest to a column that is drawn at (the inverse of `visual_col`)
+    ///
+    /// runs in O(M log N) time (N number chars, M the column)
+    fn col_at_visual(&self, visual_col: usize, tab_width: usize) -> usize;
 }
 
 impl<'a> RopeSliceExt for RopeSlice<'a> {
@@ -123,6 +135,40 @@ impl<'a> RopeSliceExt for RopeSlice<'a> {
         let new_end = self.len_chars() - linebreak_len;
         self.slice(..new_end)
     }
+
+    fn visual_col(&self, col: usize, tab_width: usize) -> usize {
+        let visual = self
+            .chars()
+            .take(col)
+            .fold(0, |visual, ch| next_visual_col(visual, ch, tab_width));
+        visual + col.saturating_sub(self.len_chars())
+    }
+
+    fn col_at_visual(&self, visual_col: usize, tab_width: usize) -> usize {
+        let mut curr = 0;
+        for (col, ch) in self.chars().enumerate() {
+            let next = next_visual_col(curr, ch, tab_width);
+            if visual_col < next {
+                // pick the closer side of a wide character
+                return if visual_col - curr <= (next - curr) / 2 {
+                    col
+                } else {
+                    col + 1
+                };
+            }
+            curr = next;
+        }
+        self.len_chars() + (visual_col - curr)
+    }
+}
+
+/// the column after drawing a character at a column (tabs go to the next tab stop)
+pub fn next_visual_col(visual_col: usize, ch: char, tab_width: usize) -> usize {
+    if ch == '\t' {
+        (visual_col / tab_width + 1) * tab_width
+    } else {
+        visual_col + 1
+    }
 }
 
 /// returns linebreak at the end of the slice (if any)
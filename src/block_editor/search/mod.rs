@@ -146,10 +146,7 @@ pub struct SearchResults {
 
 impl SearchResults {
     pub fn search(source: &Rope, pat: &str) -> Option<Self> {
-        let iter = rope_search::SearchIter::from_rope_slice(source, pat);
-        let ranges: Vec<TextRange> = iter
-            .map(|(start, end)| TextRange::from_char_range_in(source, start..end))
-            .collect();
+        let ranges = find_all(source, pat);
         if ranges.is_empty() {
             None
         } else {
@@ -217,3 +214,10 @@ impl SearchResults {
         }
     }
 }
+
+/// Find every match of the pattern in the source, in order
+pub fn find_all(source: &Rope, pat: &str) -> Vec<TextRange> {
+    rope_search::SearchIter::from_rope_slice(source, pat)
+        .map(|(start, end)| TextRange::from_char_range_in(source, start..end))
+        .collect()
+}
@@ -1,7 +1,7 @@
 use ropey::Rope;
 use std::borrow::Cow;
 
-use super::TextEdit;
+use super::{IndentStyle, TextEdit};
 use crate::{
     block_editor::{
         rope_ext::{RopeExt, RopeSliceExt},
@@ -9,16 +9,18 @@ use crate::{
         text_range::TextPoint,
         TextRange,
     },
-    lang::config::NewScopeChar,
+    lang::{
+        config::{AutoPair, CommentTokens, NewScopeChar},
+        indents::NewlineIndent,
+    },
 };
 
-const TAB_SIZE: usize = 4;
-
 /// Find the edit for inserting a single character. Returns the edit and the new selection.
 pub fn edit_for_insert_char<'a>(
     selection: TextRange,
     source: &Rope,
     add: &'a str,
+    auto_pairs: &'static [AutoPair],
     input_ignore_stack: &mut Vec<&'static str>,
     paired_delete_stack: &mut Vec<bool>,
 ) -> (Option<TextEdit<'a>>, TextRange) {
@@ -39,70 +41,122 @@ pub fn edit_for_insert_char<'a>(
         return (None, new_selection);
     }
 
-    // (what is added, full insertion, string)
-    let pair_completion = match add {
-        "'" => Some(("'", "''", true)),
-        "\"" => Some(("\"", "\"\"", true)),
-        "(" => Some((")", "()", false)),
-        "[" => Some(("]", "[]", false)),
-        "{" => Some(("}", "{}", false)),
-        _ => None,
-    };
-
-    let actual_add = if let Some((additional, full_add, for_string)) = pair_completion {
-        // only insert if the previous and next characters meet the conditions
-        // (different conditions for string or not)
-        let start_char = old_selection.start.char_idx_in(source);
-        let (prev_char, next_char) = source.surrounding_chars(start_char);
-
-        let should_insert_pair = if for_string {
-            let add_char = add.chars().next().unwrap();
-
-            // if the user is typing a quote adjacent to an alphanumeric character (excluding f strings),
-            // they are probably closing a string instead of creating a new one, so don't insert a pair
-            !(next_char.is_alphanumeric() || prev_char.is_alphanumeric() && prev_char != 'f')
-                // multiline strings are 3 long, this prevents jumping to 4
-                && prev_char != add_char
-                && next_char != add_char
-        } else {
-            // only care about next character because parenthesis and brackets
-            // can be attached to the character before
-            !next_char.is_alphanumeric()
-        };
-
-        if should_insert_pair {
-            input_ignore_stack.push(additional);
-            paired_delete_stack.push(true);
-            full_add
-        } else {
-            if !paired_delete_stack.is_empty() {
-                paired_delete_stack.push(false);
-            }
-            add
-        }
+    // if multiple pairs could be opened (like `"` and `"""`), use the longest
+    let start_char = old_selection.start.char_idx_in(source);
+    let pair = auto_pairs
+        .iter()
+        .filter(|pair| should_insert_pair(pair, add, source, start_char))
+        .max_by_key(|pair| pair.open.len());
+
+    let actual_add = if let Some(pair) = pair {
+        // the closing text is typed over one character at a time
+        input_ignore_stack.extend(
+            pair.close
+                .char_indices()
+                .rev()
+                .map(|(idx, c)| &pair.close[idx..idx + c.len_utf8()]),
+        );
+        paired_delete_stack.push(true);
+        Cow::Owned(format!("{add}{}", pair.close))
     } else {
         if !paired_delete_stack.is_empty() {
             paired_delete_stack.push(false);
         }
-        add
+        Cow::Borrowed(add)
     };
 
-    let edit = TextEdit::new(Cow::Borrowed(actual_add), old_selection);
+    let edit = TextEdit::new(actual_add, old_selection);
     (Some(edit), new_selection)
 }
 
+/// Check if typing `add` at `cursor` should insert the closing text of `pair`,
+/// based on the characters around it
+fn should_insert_pair(pair: &AutoPair, add: &str, source: &Rope, cursor: usize) -> bool {
+    // the rest of the opening must already be typed
+    let Some(already_typed) = pair.open.strip_suffix(add) else {
+        return false;
+    };
+    let Some(open_start) = cursor.checked_sub(already_typed.chars().count()) else {
+        return false;
+    };
+    if source.slice(open_start..cursor) != already_typed {
+        return false;
+    }
+
+    let (prev_char, _) = source.surrounding_chars(open_start);
+    let (_, next_char) = source.surrounding_chars(cursor);
+
+    if pair.not_after.contains(prev_char) || pair.after_word && !is_word_char(prev_char) {
+        return false;
+    }
+
+    if pair.quote {
+        // if the user is typing a quote adjacent to a word, they are probably closing
+        // a string instead of creating a new one (unless the word is a string prefix)
+        let word_len = source
+            .chars_at(open_start)
+            .reversed()
+            .take_while(|c| is_word_char(*c))
+            .count();
+        let word_before = source.slice(open_start - word_len..open_start).to_string();
+        let prefixed = word_before.is_empty()
+            || pair
+                .prefixes
+                .iter()
+                .any(|prefix| prefix.eq_ignore_ascii_case(&word_before));
+
+        let quote = pair.open.chars().next().unwrap_or_default();
+        prefixed
+            && !next_char.is_alphanumeric()
+            // this prevents jumping from 3 quotes to 4 in multiline strings
+            && prev_char != quote
+            && next_char != quote
+    } else {
+        // only care about next character because brackets
+        // can be attached to the character before
+        !next_char.is_alphanumeric()
+    }
+}
+
+/// Find the longest pair whose opening is right before the cursor and closing is right after it
+fn pair_around<'p>(
+    auto_pairs: &'p [AutoPair],
+    source: &Rope,
+    cursor: usize,
+) -> Option<&'p AutoPair> {
+    auto_pairs
+        .iter()
+        .filter(|pair| {
+            let open_len = pair.open.chars().count();
+            let close_len = pair.close.chars().count();
+            cursor >= open_len
+                && cursor + close_len <= source.len_chars()
+                && source.slice(cursor - open_len..cursor) == pair.open.as_str()
+                && source.slice(cursor..cursor + close_len) == pair.close.as_str()
+        })
+        .max_by_key(|pair| pair.open.len())
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
 pub fn edit_for_insert_newline<'a>(
     selection: TextRange,
     source: &Rope,
-    new_scope_char: NewScopeChar,
+    new_scope_char: Option<NewScopeChar>,
+    indent_style: IndentStyle,
+    newline_indent: NewlineIndent,
 ) -> (TextEdit<'a>, TextRange) {
     // find linebreak used in source
     let linebreak = source.detect_linebreak();
 
-    // find previous indent level and set new line to that many spaces
+    // find previous indent and start the new line with the same whitespace
     let old_selection = selection.ordered();
     let curr_line = source.line(old_selection.start.line);
-    let prev_indent = curr_line.whitespace_at_start();
+    let prev_indent = curr_line
+        .slice(..curr_line.whitespace_at_start())
+        .to_string();
 
     let char_before_cursor = if old_selection.start.col > 1 {
         curr_line.get_char(old_selection.start.col - 1)
@@ -111,47 +165,108 @@ pub fn edit_for_insert_newline<'a>(
     };
     let char_after_cursor = curr_line.get_char(old_selection.start.col);
 
-    let middle_of_bracket = new_scope_char == NewScopeChar::Brace
-        && char_before_cursor == Some('{')
-        && char_after_cursor == Some('}');
+    // the syntax tree is used when it can be, but a scope char still opens a scope
+    // while the tree is broken (like `if a:` before its body is written)
+    let opens_scope = newline_indent.opens_scope
+        || new_scope_char.is_some_and(|c| char_before_cursor == Some(c.char()));
+    let middle_of_bracket = opens_scope
+        && (newline_indent.closes_scope
+            || new_scope_char == Some(NewScopeChar::Brace)
+                && char_before_cursor == Some('{')
+                && char_after_cursor == Some('}'));
+
+    // only close a scope if nothing is after the cursor, since that would be moved inside it
+    let rest_of_line = source
+        .line(old_selection.end.line)
+        .slice(old_selection.end.col..);
+    let missing_close = newline_indent
+        .missing_close
+        .filter(|_| opens_scope && rest_of_line.chars().all(char::is_whitespace));
 
     // find the indent level of the next line
-    // (same as current line & increase if character before cursor is a scope char)
-    let indent_inc = if char_before_cursor == Some(new_scope_char.char()) {
-        TAB_SIZE
+    // (increase if a scope was opened, decrease if the line ended its block)
+    let next_indent = if opens_scope {
+        prev_indent.clone() + &indent_style.unit()
+    } else if newline_indent.ends_block {
+        let kept = prev_indent
+            .chars()
+            .count()
+            .saturating_sub(indent_style.unit_chars());
+        prev_indent.chars().take(kept).collect()
     } else {
-        0
+        prev_indent.clone()
     };
-    let next_indent = prev_indent + indent_inc;
 
     // update source
-    let indent: &str = &" ".repeat(next_indent);
-    let to_insert = format!("{linebreak}{indent}");
+    let to_insert = format!("{linebreak}{next_indent}");
 
-    if !middle_of_bracket {
+    let after_cursor = if middle_of_bracket {
+        // if in the middle of a bracket, insert an extra linebreak and indent
+        // so the closing bracket goes on its own line
+        format!("{linebreak}{prev_indent}")
+    } else if let Some(close) = missing_close {
+        format!("{linebreak}{prev_indent}{close}")
+    } else {
         let edit = TextEdit::new(Cow::Owned(to_insert), old_selection);
         let new_selection = TextRange::new_cursor(edit.new_end());
-        (edit, new_selection)
-    } else {
-        // if in the middle of a bracket, insert an extra linebreak and indent
-        // but only move the cursor to the newline in the middle
-        let following_indent = " ".repeat(prev_indent);
-        let extra_to_insert = format!("{to_insert}{linebreak}{following_indent}");
+        return (edit, new_selection);
+    };
 
-        let edit = TextEdit::new(Cow::Owned(extra_to_insert), old_selection);
-        let new_selection =
-            TextRange::new_cursor(TextPoint::new(old_selection.start.line + 1, next_indent));
-        (edit, new_selection)
+    // only move the cursor to the newline in the middle
+    let edit = TextEdit::new(Cow::Owned(to_insert + &after_cursor), old_selection);
+    let new_selection = TextRange::new_cursor(TextPoint::new(
+        old_selection.start.line + 1,
+        next_indent.chars().count(),
+    ));
+    (edit, new_selection)
+}
+
+/// Find the edit for lining up a line with the indent of another line,
+/// like when typing `else:` or `}` lines them up with the start of their scope.
+/// Returns `None` if the line is not indented further than the other line.
+pub fn edit_for_match_indent<'a>(
+    selection: TextRange,
+    source: &Rope,
+    line: usize,
+    match_line: usize,
+) -> Option<(TextEdit<'a>, TextRange)> {
+    let target = source.line(match_line);
+    let target_indent = target.slice(..target.whitespace_at_start()).to_string();
+    let curr_indent = source.line(line).whitespace_at_start();
+
+    // only dedent, since branches are typed after the lines inside the scope
+    let removed = curr_indent.checked_sub(target_indent.chars().count())?;
+    if removed == 0 {
+        return None;
     }
+
+    let edit = TextEdit::new(
+        Cow::Owned(target_indent),
+        TextRange::new(TextPoint::new(line, 0), TextPoint::new(line, curr_indent)),
+    );
+
+    // move the selection with the text on the line
+    let shift = |point: TextPoint| {
+        if point.line == line {
+            TextPoint::new(line, point.col.saturating_sub(removed))
+        } else {
+            point
+        }
+    };
+    let new_selection = TextRange::new(shift(selection.start), shift(selection.end));
+    Some((edit, new_selection))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn edit_for_delete<'a>(
     selection: TextRange,
     source: &Rope,
     movement: TextMovement,
     pseudo_selection: Option<TextRange>,
+    auto_pairs: &[AutoPair],
     input_ignore_stack: &mut Vec<&'static str>,
     paired_delete_stack: &mut Vec<bool>,
+    indent_style: IndentStyle,
 ) -> (Option<TextEdit<'a>>, TextRange) {
     let old_selection = selection.ordered();
 
@@ -170,22 +285,30 @@ pub fn edit_for_delete<'a>(
             let line_indent = source.line(old_selection.start.line).whitespace_at_start();
             let at_indent = old_selection.start.col == line_indent;
             if at_indent {
-                let (edit, new_selection) = edit_for_unindent(old_selection, source);
+                let (edit, new_selection) = edit_for_unindent(old_selection, source, indent_style);
                 return (Some(edit), new_selection);
             }
 
-            // see if there is a paired character to delete
+            // see if there is a pair to delete (both the opening and closing text)
             let paired = paired_delete_stack.pop().unwrap_or(false);
-            let after_delete_amount = if paired {
-                // pop because we're going to delete the character to ignore
-                input_ignore_stack.pop();
-                1
-            } else {
-                0
-            };
+            let cursor = old_selection.start.char_idx_in(source);
+            let (before_delete_amount, after_delete_amount) =
+                match pair_around(auto_pairs, source, cursor).filter(|_| paired) {
+                    Some(pair) => {
+                        // pop because we're going to delete the characters to ignore
+                        let close_len = pair.close.chars().count();
+                        let ignored = input_ignore_stack.len().saturating_sub(close_len);
+                        input_ignore_stack.truncate(ignored);
+                        (pair.open.chars().count(), close_len)
+                    }
+                    None => (1, 0),
+                };
 
             TextRange::new(
-                TextPoint::new(old_selection.start.line, old_selection.start.col - 1),
+                TextPoint::new(
+                    old_selection.start.line,
+                    old_selection.start.col.saturating_sub(before_delete_amount),
+                ),
                 TextPoint::new(
                     old_selection.start.line,
                     old_selection.start.col + after_delete_amount,
@@ -215,7 +338,11 @@ pub fn edit_for_delete<'a>(
     (Some(edit), new_selection)
 }
 
-pub fn edit_for_indent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'a>, TextRange) {
+pub fn edit_for_indent<'a>(
+    selection: TextRange,
+    source: &Rope,
+    indent_style: IndentStyle,
+) -> (TextEdit<'a>, TextRange) {
     let ordered = selection.ordered();
 
     // expand selection to include entire lines
@@ -238,8 +365,12 @@ pub fn edit_for_indent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'a>
         let line_len = line.len_chars_no_linebreak();
 
         // make what to add to start of line
-        let indent_amount = TAB_SIZE - (curr_indent % TAB_SIZE);
-        let indent = " ".repeat(indent_amount);
+        // (spaces go to the next multiple of the indent size to line up unaligned lines)
+        let indent_amount = match indent_style {
+            IndentStyle::Spaces(spaces) => spaces - (curr_indent % spaces),
+            IndentStyle::Tabs => 1,
+        };
+        let indent = indent_style.whitespace(indent_amount);
 
         // add it
         let start_of_line = TextPoint::new(line_num, 0);
@@ -266,7 +397,11 @@ pub fn edit_for_indent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'a>
     )
 }
 
-pub fn edit_for_unindent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'a>, TextRange) {
+pub fn edit_for_unindent<'a>(
+    selection: TextRange,
+    source: &Rope,
+    indent_style: IndentStyle,
+) -> (TextEdit<'a>, TextRange) {
     // apply to every line of selection
     let ordered = selection.ordered();
 
@@ -293,10 +428,12 @@ pub fn edit_for_unindent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'
         }
 
         // remove start of line
-        let unindent_amount = if curr_indent.is_multiple_of(TAB_SIZE) {
-            TAB_SIZE
-        } else {
-            curr_indent % TAB_SIZE
+        let unindent_amount = match indent_style {
+            IndentStyle::Spaces(spaces) if curr_indent.is_multiple_of(spaces) => spaces,
+            IndentStyle::Spaces(spaces) => curr_indent % spaces,
+            // remove a tab, or the spaces in front of one if the indent is mixed
+            IndentStyle::Tabs if line.char(0) == '\t' => 1,
+            IndentStyle::Tabs => line.chars().take_while(|c| *c == ' ').count(),
         };
         let remove_range = TextRange::new(
             TextPoint::new(line_num, 0),
@@ -319,9 +456,216 @@ pub fn edit_for_unindent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'
     )
 }
 
+/// Find the edit for commenting out the lines of the selection, or uncommenting them if they
+/// are all commented already. Line comments are used if the language has them, otherwise the
+/// lines are wrapped in a block comment. Returns `None` if the language has no comments.
+pub fn edit_for_toggle_comment<'a>(
+    selection: TextRange,
+    source: &Rope,
+    comments: &CommentTokens,
+) -> Option<(TextEdit<'a>, TextRange)> {
+    let ordered = selection.ordered();
+
+    // a selection that ends at the start of a line doesn't include that line
+    let mut end_line = ordered.end.line;
+    if end_line > ordered.start.line && ordered.end.col == 0 {
+        end_line -= 1;
+    }
+
+    // expand selection to include entire lines
+    let full_selection = TextRange::new(
+        TextPoint::new(ordered.start.line, 0),
+        TextPoint::new(end_line, source.line(end_line).len_chars_no_linebreak()),
+    );
+    let mut new_text: Rope = source.slice(full_selection.char_range_in(source)).into();
+
+    let changes = match (&comments.line, &comments.block) {
+        (Some(token), _) => line_comment_changes(&new_text, token),
+        (None, Some((start, end))) => block_comment_changes(&new_text, start, end),
+        (None, None) => return None,
+    };
+
+    // apply from the end so the earlier columns stay the same
+    let mut new_selection = selection;
+    let start_is_earlier = selection.start == ordered.start;
+    for change in changes.iter().rev() {
+        let start = TextPoint::new(change.line, change.col);
+        let end = TextPoint::new(change.line, change.col + change.remove);
+        new_text.remove(TextRange::new(start, end).char_range_in(&new_text));
+        new_text.insert(start.char_idx_in(&new_text), &change.insert);
+
+        let line = full_selection.start.line + change.line;
+        let is_cursor = selection.is_cursor();
+        new_selection.start =
+            change.move_point(new_selection.start, line, start_is_earlier && !is_cursor);
+        new_selection.end =
+            change.move_point(new_selection.end, line, !start_is_earlier && !is_cursor);
+    }
+
+    Some((
+        TextEdit::new(Cow::Owned(new_text.to_string()), full_selection),
+        new_selection,
+    ))
+}
+
+/// A change to a single line for toggling a comment
+struct CommentChange {
+    /// The line, relative to the first line being toggled
+    line: usize,
+    col: usize,
+
+    /// How many characters to remove at the column
+    remove: usize,
+
+    /// The text to insert at the column
+    insert: String,
+}
+
+impl CommentChange {
+    /// Find where a selection point on `line` ends up after the change.
+    /// The start of a selection stays before text inserted at it, so the selection covers it.
+    fn move_point(&self, point: TextPoint, line: usize, is_selection_start: bool) -> TextPoint {
+        if point.line != line || point.col < self.col {
+            return point;
+        }
+        if point.col == self.col && self.remove == 0 && is_selection_start {
+            return point;
+        }
+
+        let col = if point.col < self.col + self.remove {
+            self.col
+        } else {
+            point.col - self.remove
+        };
+        TextPoint::new(line, col + self.insert.chars().count())
+    }
+}
+
+/// The changes to comment out the lines with a line comment, or to uncomment them if
+/// every line with code is commented. Comments are lined up with the smallest indent.
+fn line_comment_changes(text: &Rope, token: &str) -> Vec<CommentChange> {
+    let lines: Vec<(usize, usize)> = text
+        .lines()
+        .enumerate()
+        .map(|(line_num, line)| (line_num, line.whitespace_at_start()))
+        .collect();
+
+    // blank lines are skipped, unless there's nothing else
+    let code_lines: Vec<(usize, usize)> = lines
+        .iter()
+        .copied()
+        .filter(|(line_num, indent)| *indent < text.line(*line_num).len_chars_no_linebreak())
+        .collect();
+    let lines = if code_lines.is_empty() {
+        lines
+    } else {
+        code_lines
+    };
+
+    let is_commented = |(line_num, indent): &(usize, usize)| {
+        text.line(*line_num)
+            .chars()
+            .skip(*indent)
+            .take(token.chars().count())
+            .eq(token.chars())
+    };
+
+    if lines.iter().all(is_commented) {
+        lines
+            .iter()
+            .map(|(line_num, indent)| {
+                // remove the space after the token too, if there is one
+                let after_token = text
+                    .line(*line_num)
+                    .chars()
+                    .nth(indent + token.chars().count());
+                CommentChange {
+                    line: *line_num,
+                    col: *indent,
+                    remove: token.chars().count() + usize::from(after_token == Some(' ')),
+                    insert: String::new(),
+                }
+            })
+            .collect()
+    } else {
+        let col = lines.iter().map(|(_, indent)| *indent).min().unwrap_or(0);
+        lines
+            .iter()
+            .map(|(line_num, _)| CommentChange {
+                line: *line_num,
+                col,
+                remove: 0,
+                insert: format!("{token} "),
+            })
+            .collect()
+    }
+}
+
+/// The changes to wrap the text in a block comment (leaving out the whitespace around it),
+/// or to unwrap it if it already is
+fn block_comment_changes(text: &Rope, start: &str, end: &str) -> Vec<CommentChange> {
+    let whole = text.to_string();
+    let inner = whole.trim();
+
+    // there's nothing to wrap in only whitespace
+    if inner.is_empty() {
+        return vec![];
+    }
+
+    let point_at = |byte: usize| {
+        let char_idx = text.byte_to_char(byte);
+        let line = text.char_to_line(char_idx);
+        TextPoint::new(line, char_idx - text.line_to_char(line))
+    };
+    let inner_start = point_at(whole.len() - whole.trim_start().len());
+    let inner_end = point_at(whole.trim_end().len());
+
+    // a single comment around all of it (not one that ends and another that starts in it)
+    let inside = inner
+        .strip_prefix(start)
+        .and_then(|rest| rest.strip_suffix(end))
+        .filter(|inside| !inside.contains(end));
+    if let Some(inside) = inside {
+        // remove the spaces added when commenting too, if they are there
+        let start_remove = start.chars().count() + usize::from(inside.starts_with(' '));
+        let end_remove =
+            end.chars().count() + usize::from(inside.len() > 1 && inside.ends_with(' '));
+        vec![
+            CommentChange {
+                line: inner_start.line,
+                col: inner_start.col,
+                remove: start_remove,
+                insert: String::new(),
+            },
+            CommentChange {
+                line: inner_end.line,
+                col: inner_end.col - end_remove,
+                remove: end_remove,
+                insert: String::new(),
+            },
+        ]
+    } else {
+        vec![
+            CommentChange {
+                line: inner_start.line,
+                col: inner_start.col,
+                remove: 0,
+                insert: format!("{start} "),
+            },
+            CommentChange {
+                line: inner_end.line,
+                col: inner_end.col,
+                remove: 0,
+                insert: format!(" {end}"),
+            },
+        ]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lang::config::LanguageConfig;
 
     #[test]
     fn test_char_insert() {
@@ -360,6 +704,107 @@ mod tests {
         char_insert_test("aa\n→bb←", "a", "aa\na→←")
     }
 
+    #[test]
+    fn test_language_pairs() {
+        // string prefixes and triple quotes in python
+        lang_char_insert_test("test.py", "f→←", "\"", "f\"→←\"");
+        lang_char_insert_test("test.py", "x→←", "\"", "x\"→←");
+        lang_char_insert_test("test.py", "\"\"→←", "\"", "\"\"\"→←\"\"\"");
+
+        // lifetimes and generics in rust
+        lang_char_insert_test("main.rs", "&→←", "'", "&'→←");
+        lang_char_insert_test("main.rs", "Vec→←", "<", "Vec<→←>");
+        lang_char_insert_test("main.rs", "a →←", "<", "a <→←");
+
+        // template strings in javascript
+        lang_char_insert_test("test.js", "→←", "`", "`→←`");
+        char_insert_test("→←", "`", "`→←");
+
+        // block comments
+        lang_char_insert_test("test.c", "/→←", "*", "/*→← */");
+
+        // the whole pair is deleted with the opening
+        let (mut src, start_sel) = generate_state("\"\"\"→←\"\"\"");
+        let (edit, end_sel) = edit_for_delete(
+            start_sel,
+            &src,
+            TextMovement::horizontal(HUnit::Grapheme, HDir::Left),
+            None,
+            &LanguageConfig::for_file("test.py").auto_pairs,
+            &mut vec!["\"", "\"", "\""],
+            &mut vec![true],
+            IndentStyle::Spaces(4),
+        );
+        edit.unwrap().apply_to_rope(&mut src);
+        assert_eq!(src, "");
+        assert_eq!(end_sel, TextRange::new_cursor(TextPoint::ZERO));
+    }
+
+    #[test]
+    fn test_newline() {
+        // keep the indent of the current line
+        newline_test(
+            "    print('hi')→←",
+            "    print('hi')\n    →←",
+            Some(NewScopeChar::Colon),
+        );
+
+        // indent after a scope character
+        newline_test("if a:→←", "if a:\n    →←", Some(NewScopeChar::Colon));
+
+        // split braces onto separate lines
+        newline_test(
+            "fn a() {→←}",
+            "fn a() {\n    →←\n}",
+            Some(NewScopeChar::Brace),
+        );
+
+        // without scopes (plain text), only keep the indent
+        newline_test("  notes:→←", "  notes:\n  →←", None);
+        newline_test("a {→←}", "a {\n→←}", None);
+    }
+
+    #[test]
+    fn test_newline_from_tree() {
+        // dedent after the end of a block
+        lang_newline_test(
+            "test.py",
+            "def f():\n    return 1→←",
+            "def f():\n    return 1\n→←",
+        );
+        lang_newline_test(
+            "test.py",
+            "while a:\n    if b:\n        break→←\n    c()\n",
+            "while a:\n    if b:\n        break\n    →←\n    c()\n",
+        );
+
+        // continue inside brackets
+        lang_newline_test("test.py", "f(a,→←)", "f(a,\n    →←\n)");
+        lang_newline_test(
+            "main.rs",
+            "fn a() {\n    b(→←);\n}\n",
+            "fn a() {\n    b(\n        →←\n    );\n}\n",
+        );
+
+        // close a brace that is missing
+        lang_newline_test("main.rs", "fn a() {→←", "fn a() {\n    →←\n}");
+        lang_newline_test("main.c", "int a() {→← b();", "int a() {\n    →← b();");
+    }
+
+    #[test]
+    fn test_match_indent() {
+        let (src, sel) = generate_state("if a {\n    b\n    }→←");
+        let (edit, new_sel) = edit_for_match_indent(sel, &src, 2, 0).unwrap();
+        let mut src = src;
+        edit.apply_to_rope(&mut src);
+        assert_eq!(src, "if a {\n    b\n}");
+        assert_eq!(new_sel, TextRange::new_cursor(TextPoint::new(2, 1)));
+
+        // lines that are already dedented are left alone
+        let (src, sel) = generate_state("if a:\n    b\nelse:→←");
+        assert!(edit_for_match_indent(sel, &src, 2, 0).is_none());
+    }
+
     #[test]
     fn test_indent() {
         // indent empty line
@@ -466,6 +911,66 @@ mod tests {
         unindent_test("→    ༄༄༄ᜃᜃᜃ←", "→༄༄༄ᜃᜃᜃ←");
     }
 
+    #[test]
+    fn test_indent_styles() {
+        // tabs
+        indent_style_test("→a\n\tb←", "→\ta\n\t\tb←", IndentStyle::Tabs);
+        unindent_style_test("\t\ta→←", "\ta→←", IndentStyle::Tabs);
+        newline_style_test(
+            "\tif a:→←",
+            "\tif a:\n\t\t→←",
+            Some(NewScopeChar::Colon),
+            IndentStyle::Tabs,
+        );
+
+        // other numbers of spaces
+        indent_style_test(" a→←", "  a→←", IndentStyle::Spaces(2));
+        unindent_style_test("    a→←", "  a→←", IndentStyle::Spaces(2));
+        newline_style_test(
+            "a {→←}",
+            "a {\n  →←\n}",
+            Some(NewScopeChar::Brace),
+            IndentStyle::Spaces(2),
+        );
+    }
+
+    #[test]
+    fn test_toggle_comment() {
+        // comment and uncomment a single line, keeping the cursor with the code
+        comment_test("main.py", "    a = →←1\n", "    # a = →←1\n");
+        comment_test("main.py", "    # a = →←1\n", "    a = →←1\n");
+        comment_test("main.rs", "    →←a();\n", "    // →←a();\n");
+
+        // comments line up with the smallest indent and skip blank lines
+        comment_test("main.py", "→if a:\n\n    b\n←", "→# if a:\n\n#     b\n←");
+
+        // only uncomment if every line is commented
+        comment_test("main.py", "→# a\nb←", "→# # a\n# b←");
+        comment_test("main.py", "→# a\n#b←", "→a\nb←");
+
+        // languages without line comments use block comments
+        comment_test("style.css", "→a { b: c; }←", "→/* a { b: c; } */←");
+        comment_test("style.css", "  →/* a { b: c; } */←\n", "  →a { b: c; }←\n");
+        comment_test(
+            "index.html",
+            "→<p>\n  a\n</p>←",
+            "→<!-- <p>\n  a\n</p> -->←",
+        );
+
+        // blank lines are left alone, and separate comments are wrapped instead of joined
+        comment_test("style.css", "  →←\n", "  →←\n");
+        comment_test(
+            "style.css",
+            "→/* a */ b /* c */←",
+            "→/* /* a */ b /* c */ */←",
+        );
+
+        // languages without comments can't toggle them
+        let (src, sel) = generate_state("→a←");
+        let comments = &LanguageConfig::plain_text().comments;
+        assert!(edit_for_toggle_comment(sel, &src, comments).is_none());
+    }
+
     #[test]
     fn test_backspace() {
         // Paired delete test
@@ -651,10 +1156,21 @@ mod tests {
 
     /* --------------------------------- helpers -------------------------------- */
     fn char_insert_test(start: &str, add: &str, target: &str) {
+        lang_char_insert_test("test.txt", start, add, target);
+    }
+
+    fn lang_char_insert_test(file_name: &str, start: &str, add: &str, target: &str) {
         let (mut src, start_sel) = generate_state(start);
         let (target_src, target_sel) = generate_state(target);
-        let (edit, end_sel) =
-            edit_for_insert_char(start_sel, &src, add, &mut Vec::new(), &mut Vec::new());
+        let auto_pairs = &LanguageConfig::for_file(file_name).auto_pairs;
+        let (edit, end_sel) = edit_for_insert_char(
+            start_sel,
+            &src,
+            add,
+            auto_pairs,
+            &mut Vec::new(),
+            &mut Vec::new(),
+        );
 
         edit.unwrap().apply_to_rope(&mut src);
 
@@ -662,10 +1178,66 @@ mod tests {
         assert_eq!(end_sel, target_sel);
     }
 
+    fn newline_test(start: &str, target: &str, new_scope_char: Option<NewScopeChar>) {
+        newline_style_test(start, target, new_scope_char, IndentStyle::Spaces(4));
+    }
+
+    fn newline_style_test(
+        start: &str,
+        target: &str,
+        new_scope_char: Option<NewScopeChar>,
+        indent_style: IndentStyle,
+    ) {
+        let (mut src, start_sel) = generate_state(start);
+        let (target_src, target_sel) = generate_state(target);
+        let (edit, end_sel) = edit_for_insert_newline(
+            start_sel,
+            &src,
+            new_scope_char,
+            indent_style,
+            NewlineIndent::default(),
+        );
+
+        edit.apply_to_rope(&mut src);
+
+        assert_eq!(src, target_src);
+        assert_eq!(end_sel, target_sel);
+    }
+
+    /// Test a newline using the indents query of the language for the file
+    fn lang_newline_test(file_name: &str, start: &str, target: &str) {
+        let (mut src, start_sel) = generate_state(start);
+        let (target_src, target_sel) = generate_state(target);
+
+        let config = LanguageConfig::for_file(file_name);
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&config.tree_sitter().unwrap()).unwrap();
+        let tree = parser.parse(src.to_string(), None).unwrap();
+        let byte = src.char_to_byte(start_sel.start.char_idx_in(&src));
+        let newline_indent = config.newline_indent(tree.root_node(), &src, byte);
+
+        let (edit, end_sel) = edit_for_insert_newline(
+            start_sel,
+            &src,
+            config.new_scope_char,
+            IndentStyle::Spaces(4),
+            newline_indent,
+        );
+
+        edit.apply_to_rope(&mut src);
+
+        assert_eq!(src, target_src);
+        assert_eq!(end_sel, target_sel);
+    }
+
     fn indent_test(start: &str, target: &str) {
+        indent_style_test(start, target, IndentStyle::Spaces(4));
+    }
+
+    fn indent_style_test(start: &str, target: &str, indent_style: IndentStyle) {
         let (mut src, start_sel) = generate_state(start);
         let (target_src, target_sel) = generate_state(target);
-        let (edit, end_sel) = edit_for_indent(start_sel, &src);
+        let (edit, end_sel) = edit_for_indent(start_sel, &src, indent_style);
 
         edit.apply_to_rope(&mut src);
 
@@ -674,9 +1246,25 @@ mod tests {
     }
 
     fn unindent_test(start: &str, target: &str) {
+        unindent_style_test(start, target, IndentStyle::Spaces(4));
+    }
+
+    fn unindent_style_test(start: &str, target: &str, indent_style: IndentStyle) {
+        let (mut src, start_sel) = generate_state(start);
+        let (target_src, target_sel) = generate_state(target);
+        let (edit, end_sel) = edit_for_unindent(start_sel, &src, indent_style);
+
+        edit.apply_to_rope(&mut src);
+
+        assert_eq!(src, target_src);
+        assert_eq!(end_sel, target_sel);
+    }
+
+    fn comment_test(file_name: &str, start: &str, target: &str) {
         let (mut src, start_sel) = generate_state(start);
         let (target_src, target_sel) = generate_state(target);
-        let (edit, end_sel) = edit_for_unindent(start_sel, &src);
+        let comments = &LanguageConfig::for_file(file_name).comments;
+        let (edit, end_sel) = edit_for_toggle_comment(start_sel, &src, comments).unwrap();
 
         edit.apply_to_rope(&mut src);
 
@@ -699,8 +1287,10 @@ mod tests {
             &src,
             movement,
             pseudo_selection,
+            &LanguageConfig::plain_text().auto_pairs,
             input_ignore_stack,
             paired_delete_stack,
+            IndentStyle::Spaces(4),
         );
 
         edit.unwrap().apply_to_rope(&mut src);
@@ -0,0 +1,172 @@
+use ropey::Rope;
+
+use crate::block_editor::rope_ext::RopeSliceExt;
+
+/// How a file indents its code
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IndentStyle {
+    /// a number of spaces per level
+    Spaces(usize),
+    /// a tab character per level
+    Tabs,
+}
+
+impl IndentStyle {
+    /// Used when the indentation of a file can't be detected (like when it is empty)
+    pub const DEFAULT: IndentStyle = IndentStyle::Spaces(4);
+
+    /// Find the indentation used by the text.
+    ///
+    /// Uses tabs if more lines start with a tab than a space, otherwise uses
+    /// the most common increase in spaces between consecutive lines.
+    pub fn detect(text: &Rope) -> Self {
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+
+        // how many times the indent increased by each amount
+        let mut increases = [0usize; 9];
+        let mut prev_spaces = 0;
+
+        for line in text.lines() {
+            // blank lines don't say anything about the indent
+            let whitespace = line.whitespace_at_start();
+            if whitespace == line.len_chars_no_linebreak() {
+                continue;
+            }
+
+            if line.char(0) == '\t' {
+                tab_lines += 1;
+                continue;
+            }
+
+            let spaces = line.chars().take_while(|c| *c == ' ').count();
+            if spaces > 0 {
+                space_lines += 1;
+            }
+
+            // increases of 1 are usually alignment (like the * in block comments)
+            let increase = spaces.saturating_sub(prev_spaces);
+            if (2..increases.len()).contains(&increase) {
+                increases[increase] += 1;
+            }
+            prev_spaces = spaces;
+        }
+
+        if tab_lines > space_lines {
+            return IndentStyle::Tabs;
+        }
+
+        // ties go to the smaller indent
+        increases
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, count)| **count > 0)
+            .max_by_key(|(_, count)| **count)
+            .map_or(Self::DEFAULT, |(spaces, _)| IndentStyle::Spaces(spaces))
+    }
+
+    /// The text for a single level of indentation
+    pub fn unit(&self) -> String {
+        self.whitespace(self.unit_chars())
+    }
+
+    /// The number of characters in a single level of indentation
+    pub fn unit_chars(&self) -> usize {
+        match self {
+            IndentStyle::Spaces(spaces) => *spaces,
+            IndentStyle::Tabs => 1,
+        }
+    }
+
+    /// The number of columns a single level of indentation is drawn across
+    pub fn unit_width(&self, tab_width: usize) -> usize {
+        match self {
+            IndentStyle::Spaces(spaces) => *spaces,
+            IndentStyle::Tabs => tab_width,
+        }
+    }
+
+    /// Indentation that is `chars` characters long
+    pub fn whitespace(&self, chars: usize) -> String {
+        match self {
+            IndentStyle::Spaces(_) => " ".repeat(chars),
+            IndentStyle::Tabs => "\t".repeat(chars),
+        }
+    }
+
+    /// Convert text indented with this style to be indented with `to`.
+    /// Leftover spaces that don't make up a full level are kept as they are.
+    pub fn reindent(&self, text: &str, to: IndentStyle) -> String {
+        if *self == to {
+            return text.to_string();
+        }
+
+        text.split_inclusive('\n')
+            .map(|line| {
+                let (levels, indent_len) = match self {
+                    IndentStyle::Spaces(spaces) => {
+                        let count = line.chars().take_while(|c| *c == ' ').count();
+                        (count / spaces, count - (count % spaces))
+                    }
+                    IndentStyle::Tabs => {
+                        let count = line.chars().take_while(|c| *c == '\t').count();
+                        (count, count)
+                    }
+                };
+                to.unit().repeat(levels) + &line[indent_len..]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(text: &str) -> IndentStyle {
+        IndentStyle::detect(&Rope::from_str(text))
+    }
+
+    #[test]
+    fn detects_spaces_and_tabs() {
+        assert_eq!(
+            detect("if a:\n    b\n    if c:\n        d\n"),
+            IndentStyle::Spaces(4)
+        );
+        assert_eq!(
+            detect("fn a() {\n  b();\n  if c {\n    d();\n  }\n}\n"),
+            IndentStyle::Spaces(2)
+        );
+        assert_eq!(
+            detect("func a() {\n\tb()\n\tif c {\n\t\td()\n\t}\n}\n"),
+            IndentStyle::Tabs
+        );
+    }
+
+    #[test]
+    fn ignores_blank_lines_and_alignment() {
+        // the blank line has trailing spaces, and the comment is aligned by 1
+        let text = "/*\n * docs\n */\nint a() {\n  b();\n    \n  return c;\n}\n";
+        assert_eq!(detect(text), IndentStyle::Spaces(2));
+    }
+
+    #[test]
+    fn defaults_without_indentation() {
+        assert_eq!(detect(""), IndentStyle::DEFAULT);
+        assert_eq!(detect("a = 1\nb = 2\n"), IndentStyle::DEFAULT);
+    }
+
+    #[test]
+    fn reindents_between_styles() {
+        let text = "if a:\n    b\n        c\n      d\n";
+        assert_eq!(
+            IndentStyle::Spaces(4).reindent(text, IndentStyle::Tabs),
+            "if a:\n\tb\n\t\tc\n\t  d\n"
+        );
+        assert_eq!(
+            IndentStyle::Tabs.reindent("a {\n\tb\n}\n", IndentStyle::Spaces(2)),
+            "a {\n  b\n}\n"
+        );
+    }
+}
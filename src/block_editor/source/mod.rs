@@ -2,16 +2,18 @@ use ropey::Rope;
 use std::borrow::Cow;
 
 use crate::{
-    block_editor::TextRange,
+    block_editor::{rope_ext::RopeSliceExt, text_range::TextPoint, TextRange},
     lang::{tree_manager::TreeManager, Language},
 };
 use edit_generation::*;
-use undo_manager::{UndoItem, UndoStopCondition::*};
+use undo_manager::{SavedSelections, UndoItem, UndoStopCondition::*};
 
 mod edit_generation;
+mod indent;
 pub mod text_edit;
 mod undo_manager;
 
+pub use indent::IndentStyle;
 pub use text_edit::TextEdit;
 pub use undo_manager::UndoStopCondition;
 
@@ -27,6 +29,9 @@ pub struct Source {
     /// generates syntax tree from source code
     tree_manager: TreeManager,
 
+    /// how the text is indented, detected when the text is set
+    indent_style: IndentStyle,
+
     /// undos from previous edits
     undo_stack: Vec<UndoItem>,
 
@@ -49,6 +54,7 @@ impl Source {
         let mut tree_manager = TreeManager::new(&mut lang);
         tree_manager.replace(&text, &mut lang);
         Self {
+            indent_style: IndentStyle::detect(&text),
             text,
             lang,
             tree_manager,
@@ -64,6 +70,7 @@ impl Source {
     pub fn set_text(&mut self, text: Rope) {
         self.text = text;
         self.tree_manager.replace(&self.text, &mut self.lang);
+        self.indent_style = IndentStyle::detect(&self.text);
         self.undo_stack.clear();
         self.redo_stack.clear();
         self.input_ignore_stack.clear();
@@ -71,11 +78,25 @@ impl Source {
         self.text_changed = true;
     }
 
+    /// Change the language of the source, keeping the text and editing state
+    pub fn set_language(&mut self, mut lang: Language) {
+        self.tree_manager = TreeManager::new(&mut lang);
+        self.tree_manager.replace(&self.text, &mut lang);
+        self.lang = lang;
+        self.text_changed = true;
+    }
+
     pub fn text(&self) -> &Rope {
         &self.text
     }
 
-    pub fn get_tree_cursor(&self) -> tree_sitter::TreeCursor<'_> {
+    /// How the text is indented (tabs or a number of spaces)
+    pub fn indent_style(&self) -> IndentStyle {
+        self.indent_style
+    }
+
+    /// Get a cursor for the syntax tree, if the language has a grammar
+    pub fn get_tree_cursor(&self) -> Option<tree_sitter::TreeCursor<'_>> {
         self.tree_manager.get_cursor()
     }
 
@@ -120,81 +141,328 @@ impl Source {
         selections.set_selection(TextRange::new_cursor(edit.new_end()), self);
     }
 
-    /// Handle inserting a string at the selection
+    /// Apply an edit at each of the ranges as a single undo step, and return the new ranges
+    /// (and if anything was edited).
+    /// `edit_for` finds the edit and new range for a range (given its index).
+    ///
+    /// The ranges are edited from the last to the first, so the ranges that haven't been
+    /// edited yet stay where they are, and the new ranges after an edit are moved by it.
+    fn edit_each<'e>(
+        &mut self,
+        ranges: &[TextRange],
+        undo_stop_before: UndoStopCondition,
+        mut edit_for: impl FnMut(&mut Self, usize, TextRange) -> (Option<TextEdit<'e>>, TextRange),
+    ) -> (Vec<TextRange>, bool) {
+        let mut new_ranges = ranges.to_vec();
+        let mut undo_stop_before = undo_stop_before;
+        let mut edited = false;
+        for index in (0..ranges.len()).rev() {
+            let (edit, new_range) = edit_for(self, index, ranges[index]);
+            if let Some(edit) = edit {
+                self.apply_edit_helper(&edit, undo_stop_before, false);
+                for later in &mut new_ranges[index + 1..] {
+                    *later =
+                        TextRange::new(edit.moved_point(later.start), edit.moved_point(later.end));
+                }
+
+                // the rest are undone along with the first
+                undo_stop_before = Never;
+                edited = true;
+            }
+            new_ranges[index] = new_range;
+        }
+        (new_ranges, edited)
+    }
+
+    /// Apply an edit at every selection (see `edit_each`)
+    fn edit_selections<'e>(
+        &mut self,
+        selections: &mut Selections,
+        undo_stop_before: UndoStopCondition,
+        undo_stop_after: bool,
+        edit_for: impl FnMut(&mut Self, usize, TextRange) -> (Option<TextEdit<'e>>, TextRange),
+    ) {
+        let before = SavedSelections::of(selections);
+        let (new_ranges, edited) = self.edit_each(&before.ranges, undo_stop_before, edit_for);
+        selections.set_edited(new_ranges, self);
+        if edited {
+            self.add_undo_selections(before, selections);
+        }
+        if undo_stop_after {
+            self.add_undo_stop();
+        }
+    }
+
+    /// Add spaces to the end of the lines of a column selection that are too short to reach it,
+    /// so text typed or pasted into it lines up. Returns if any lines were padded.
+    fn pad_column_selection(&mut self, selections: &mut Selections) -> bool {
+        let Some(column) = selections.column_selection() else {
+            return false;
+        };
+
+        let line_ends: Vec<TextRange> = column
+            .lines()
+            .map(|line| {
+                let len = self.text.line(line).len_chars_no_linebreak();
+                TextRange::new_cursor(TextPoint::new(line, len))
+            })
+            .collect();
+        let before = SavedSelections::of(selections);
+        let (_, padded) = self.edit_each(&line_ends, Always, |source, _, range| {
+            let spaces = column.padding_needed(range.start.line, &source.text);
+            if spaces == 0 {
+                return (None, range);
+            }
+            let edit = TextEdit::new(Cow::Owned(" ".repeat(spaces)), range);
+            (Some(edit), range)
+        });
+
+        selections.refresh_column(self);
+        if padded {
+            self.add_undo_selections(before, selections);
+        }
+        padded
+    }
+
+    /// Apply an edit to the lines of every selection (like indenting them) as a single undo step.
+    /// Selections that share lines are edited together, so each line is only changed once.
+    /// The edit must keep the lines where they are.
+    fn edit_selected_lines<'e>(
+        &mut self,
+        selections: &mut Selections,
+        edit_for: impl Fn(TextRange, &Rope) -> Option<(TextEdit<'e>, TextRange)>,
+    ) {
+        let ranges = selections.all().to_vec();
+
+        // the indices of the selections that share lines
+        let mut groups: Vec<Vec<usize>> = vec![];
+        for (index, range) in ranges.iter().enumerate() {
+            let shares_lines = groups.last().is_some_and(|group| {
+                let last = ranges[*group.last().unwrap()].ordered();
+                range.ordered().start.line <= last.end.line
+            });
+            match groups.last_mut() {
+                Some(group) if shares_lines => group.push(index),
+                _ => groups.push(vec![index]),
+            }
+        }
+        let group_ranges: Vec<TextRange> = groups
+            .iter()
+            .map(|group| match group[..] {
+                [index] => ranges[index],
+                _ => TextRange::new(
+                    ranges[group[0]].ordered().start,
+                    ranges[*group.last().unwrap()].ordered().end,
+                ),
+            })
+            .collect();
+
+        let old_text = self.text.clone();
+        let (new_group_ranges, edited) =
+            self.edit_each(&group_ranges, Always, |source, _, range| {
+                match edit_for(range, &source.text) {
+                    Some((edit, new_range)) => (Some(edit), new_range),
+                    None => (None, range),
+                }
+            });
+
+        // the edit finds where a single selection goes, but ones that share lines
+        // are moved by how much the text after the indent of their line changed
+        let moved_point = |point: TextPoint| {
+            let old_line = old_text.line(point.line);
+            let old_len = old_line.len_chars_no_linebreak();
+            let new_len = self.text.line(point.line).len_chars_no_linebreak();
+            let col = if point.col >= old_line.whitespace_at_start() {
+                (point.col + new_len).saturating_sub(old_len)
+            } else {
+                point.col.min(new_len)
+            };
+            TextPoint::new(point.line, col)
+        };
+        let mut new_ranges = ranges.clone();
+        for (group, new_group_range) in groups.iter().zip(new_group_ranges) {
+            match group[..] {
+                [index] => new_ranges[index] = new_group_range,
+                _ => {
+                    for index in group {
+                        let range = ranges[*index];
+                        new_ranges[*index] =
+                            TextRange::new(moved_point(range.start), moved_point(range.end));
+                    }
+                }
+            }
+        }
+        let before = SavedSelections::of(selections);
+        selections.set_all(new_ranges, before.primary, self);
+        if edited {
+            self.add_undo_selections(before, selections);
+        }
+        self.add_undo_stop();
+    }
+
+    /// Handle inserting a string at the selections.
+    /// If there are multiple cursors and the string has a line for each (like when it
+    /// was copied from them), each cursor gets its own line.
     pub fn insert_str(&mut self, add: &str, selections: &mut Selections) {
-        let old_selection = selections.selection().ordered();
-        let edit = TextEdit::new(Cow::Borrowed(add), old_selection);
-        self.apply_edit_helper(&edit, Always, true);
-        selections.set_selection(TextRange::new_cursor(edit.new_end()), self)
+        let lines: Vec<&str> = add.lines().collect();
+        let line_per_cursor = selections.is_multiple() && lines.len() == selections.all().len();
+
+        // undone along with the padding if the column selection needed it
+        let padded = !add.is_empty() && self.pad_column_selection(selections);
+        let undo_stop_before = if padded { Never } else { Always };
+        self.edit_selections(selections, undo_stop_before, true, |_, index, range| {
+            let text = if line_per_cursor { lines[index] } else { add };
+            let edit = TextEdit::new(Cow::Borrowed(text), range);
+            let new_range = TextRange::new_cursor(edit.new_end());
+            (Some(edit), new_range)
+        });
     }
 
     /// Handle typing a single character
     /// (separate from `insert_str` because it also handles paired completion)
     pub fn insert_char(&mut self, add: &str, selections: &mut Selections) {
-        let (edit, new_selection) = edit_for_insert_char(
-            selections.selection(),
-            &self.text,
-            add,
-            &mut self.input_ignore_stack,
-            &mut self.paired_delete_stack,
-        );
-
-        if let Some(edit) = edit {
-            self.apply_edit_helper(&edit, IfNotMerged, false);
+        let config = self.lang.config;
+
+        // undone along with the padding if the column selection needed it
+        let undo_stop_before = if self.pad_column_selection(selections) {
+            Never
+        } else {
+            IfNotMerged
+        };
+
+        // every cursor types the same thing, so they start with the same pairs to ignore
+        let input_ignore_stack = self.input_ignore_stack.clone();
+        let paired_delete_stack = self.paired_delete_stack.clone();
+        let before = SavedSelections::of(selections);
+        let (new_ranges, edited) =
+            self.edit_each(&before.ranges, undo_stop_before, |source, _, range| {
+                source.input_ignore_stack.clone_from(&input_ignore_stack);
+                source.paired_delete_stack.clone_from(&paired_delete_stack);
+                edit_for_insert_char(
+                    range,
+                    &source.text,
+                    add,
+                    &config.auto_pairs,
+                    &mut source.input_ignore_stack,
+                    &mut source.paired_delete_stack,
+                )
+            });
+
+        // line up branches with their scope as they are finished (like `else:` or `}`)
+        let finishes_branch = config
+            .new_scope_char
+            .is_some_and(|c| add.chars().eq([c.reindent_char()]));
+        let (new_ranges, reindented) = if finishes_branch {
+            // undone along with the character that finished the branch
+            self.edit_each(&new_ranges, Never, |source, _, range| {
+                let line = range.start.line;
+                let reindent = source
+                    .get_tree_cursor()
+                    .and_then(|cursor| config.branch_scope_line(cursor.node(), &source.text, line))
+                    .and_then(|scope_line| {
+                        edit_for_match_indent(range, &source.text, line, scope_line)
+                    });
+                match reindent {
+                    Some((edit, new_range)) => (Some(edit), new_range),
+                    None => (None, range),
+                }
+            })
+        } else {
+            (new_ranges, false)
+        };
+
+        selections.set_edited(new_ranges, self);
+        if edited || reindented {
+            self.add_undo_selections(before, selections);
         }
-
-        selections.set_selection(new_selection, self);
     }
 
     pub fn insert_newline(&mut self, selections: &mut Selections) {
-        let (edit, new_selection) = edit_for_insert_newline(
-            selections.selection(),
-            &self.text,
-            self.lang.config.new_scope_char,
-        );
-        self.apply_edit_helper(&edit, Always, false);
-
-        selections.set_selection(new_selection, self);
+        let config = self.lang.config;
+        let indent_style = self.indent_style;
+        self.edit_selections(selections, Always, false, |source, _, range| {
+            let selection = range.ordered();
+            let byte = source
+                .text
+                .char_to_byte(selection.start.char_idx_in(&source.text));
+            let newline_indent = source
+                .get_tree_cursor()
+                .map(|cursor| config.newline_indent(cursor.node(), &source.text, byte))
+                .unwrap_or_default();
+
+            let (edit, new_range) = edit_for_insert_newline(
+                selection,
+                &source.text,
+                config.new_scope_char,
+                indent_style,
+                newline_indent,
+            );
+            (Some(edit), new_range)
+        });
     }
 
     pub fn delete(&mut self, movement: TextMovement, selections: &mut Selections) {
-        let (edit, new_selection) = edit_for_delete(
-            selections.selection(),
-            &self.text,
-            movement,
-            selections.pseudo_selection(),
-            &mut self.input_ignore_stack,
-            &mut self.paired_delete_stack,
-        );
-        if let Some(edit) = edit {
-            self.apply_edit_helper(&edit, IfNotMerged, false);
-        }
-        selections.set_selection(new_selection, self);
+        let config = self.lang.config;
+        let indent_style = self.indent_style;
+        let pseudo_selection = selections.pseudo_selection();
+        let column = selections.column_selection();
+
+        // every cursor deletes the same way, so they start with the same pairs
+        let input_ignore_stack = self.input_ignore_stack.clone();
+        let paired_delete_stack = self.paired_delete_stack.clone();
+        self.edit_selections(selections, IfNotMerged, false, |source, _, range| {
+            // lines that end before a column selection have nothing to delete
+            if column.is_some_and(|column| column.is_past_end(range.start.line, &source.text)) {
+                return (None, range);
+            }
+            source.input_ignore_stack.clone_from(&input_ignore_stack);
+            source.paired_delete_stack.clone_from(&paired_delete_stack);
+            edit_for_delete(
+                range,
+                &source.text,
+                movement,
+                pseudo_selection,
+                &config.auto_pairs,
+                &mut source.input_ignore_stack,
+                &mut source.paired_delete_stack,
+                indent_style,
+            )
+        });
     }
 
     pub fn indent(&mut self, selections: &mut Selections) {
-        let (edit, new_selection) = edit_for_indent(selections.selection(), &self.text);
-        self.apply_edit_helper(&edit, Always, true);
-        selections.set_selection(new_selection, self);
+        let indent_style = self.indent_style;
+        self.edit_selected_lines(selections, |range, text| {
+            Some(edit_for_indent(range, text, indent_style))
+        });
     }
 
     pub fn unindent(&mut self, selections: &mut Selections) {
-        let (edit, new_selection) = edit_for_unindent(selections.selection(), &self.text);
-        self.apply_edit_helper(&edit, Always, true);
-        selections.set_selection(new_selection, self);
+        let indent_style = self.indent_style;
+        self.edit_selected_lines(selections, |range, text| {
+            Some(edit_for_unindent(range, text, indent_style))
+        });
+    }
+
+    /// Comment out the lines of the selections, or uncomment them if they are all commented
+    pub fn toggle_comment(&mut self, selections: &mut Selections) {
+        let comments = &self.lang.config.comments;
+        self.edit_selected_lines(selections, |range, text| {
+            edit_for_toggle_comment(range, text, comments)
+        });
     }
 
     pub fn undo(&mut self, selections: &mut Selections) {
-        if let Some(new_selection) = self.apply_undo() {
+        if let Some(saved) = self.apply_undo() {
             self.text_changed = true;
-            selections.set_selection(new_selection, self);
+            selections.set_all(saved.ranges, saved.primary, self);
         }
     }
 
     pub fn redo(&mut self, selections: &mut Selections) {
-        if let Some(new_selection) = self.apply_redo() {
+        if let Some(saved) = self.apply_redo() {
             self.text_changed = true;
-            selections.set_selection(new_selection, self);
+            selections.set_all(saved.ranges, saved.primary, self);
         }
     }
 
@@ -208,3 +476,118 @@ impl Source {
         self.add_undo_stop()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_editor::text_editor::selections::ColumnSelection,
+        block_editor::text_range::movement::{HDir, HUnit},
+        lang::config::LanguageConfig,
+    };
+
+    fn cursor(line: usize, col: usize) -> TextRange {
+        TextRange::new_cursor(TextPoint::new(line, col))
+    }
+
+    /// A source with a cursor at each of the points
+    fn source_with_cursors(
+        file_name: &str,
+        text: &str,
+        cursors: &[TextRange],
+    ) -> (Source, Selections) {
+        let lang = Language::new(LanguageConfig::for_file(file_name)).unwrap();
+        let source = Source::new(Rope::from_str(text), lang);
+        let mut selections = Selections::new();
+        selections.set_all(cursors.to_vec(), 0, &source);
+        (source, selections)
+    }
+
+    #[test]
+    fn edits_every_cursor() {
+        let cursors = [cursor(0, 1), cursor(1, 1)];
+        let (mut source, mut selections) =
+            source_with_cursors("test.py", "a = 1\nb = 2\n", &cursors);
+
+        source.insert_char("x", &mut selections);
+        source.insert_char("(", &mut selections);
+        assert_eq!(source.text(), "ax() = 1\nbx() = 2\n");
+        assert_eq!(selections.all(), [cursor(0, 3), cursor(1, 3)]);
+
+        // the pairs are deleted at every cursor too
+        let backspace = TextMovement::horizontal(HUnit::Grapheme, HDir::Left);
+        source.delete(backspace, &mut selections);
+        assert_eq!(source.text(), "ax = 1\nbx = 2\n");
+        assert_eq!(selections.all(), [cursor(0, 2), cursor(1, 2)]);
+
+        // each cursor's edit is undone together
+        source.undo(&mut selections);
+        assert_eq!(source.text(), "ax() = 1\nbx() = 2\n");
+    }
+
+    #[test]
+    fn undo_restores_every_cursor() {
+        let cursors = [cursor(0, 1), cursor(1, 1), cursor(2, 1)];
+        let (mut source, mut selections) = source_with_cursors("test.py", "a\nb\nc\n", &cursors);
+        selections.set_all(cursors.to_vec(), 1, &source);
+
+        source.insert_str("xy", &mut selections);
+        assert_eq!(source.text(), "axy\nbxy\ncxy\n");
+
+        source.undo(&mut selections);
+        assert_eq!(source.text(), "a\nb\nc\n");
+        assert_eq!(selections.all(), cursors);
+        assert_eq!(selections.primary_index(), 1);
+
+        source.redo(&mut selections);
+        assert_eq!(source.text(), "axy\nbxy\ncxy\n");
+        assert_eq!(selections.all(), [cursor(0, 3), cursor(1, 3), cursor(2, 3)]);
+    }
+
+    #[test]
+    fn pastes_a_line_per_cursor() {
+        let cursors = [cursor(0, 0), cursor(1, 0)];
+        let (mut source, mut selections) = source_with_cursors("test.py", "a\nb\n", &cursors);
+        source.insert_str("1\n2", &mut selections);
+        assert_eq!(source.text(), "1a\n2b\n");
+
+        // otherwise every cursor gets everything
+        source.insert_str("x", &mut selections);
+        assert_eq!(source.text(), "1xa\n2xb\n");
+    }
+
+    #[test]
+    fn types_into_column_selections() {
+        let (mut source, mut selections) = source_with_cursors("test.py", "abcd\na\nabcd\n", &[]);
+        let column = ColumnSelection {
+            anchor: TextPoint::new(0, 3),
+            head: TextPoint::new(2, 3),
+            tab_width: 4,
+        };
+        selections.set_column(column, &source);
+
+        // lines that end before the column are skipped when deleting
+        let backspace = TextMovement::horizontal(HUnit::Grapheme, HDir::Left);
+        source.delete(backspace, &mut selections);
+        assert_eq!(source.text(), "abd\na\nabd\n");
+
+        // and padded when typing
+        source.insert_char("x", &mut selections);
+        assert_eq!(source.text(), "abxd\na x\nabxd\n");
+        assert_eq!(selections.all(), [cursor(0, 3), cursor(1, 3), cursor(2, 3)]);
+        assert!(selections.column_selection().is_some());
+
+        // the padding is undone with the typing
+        source.undo(&mut selections);
+        assert_eq!(source.text(), "abd\na\nabd\n");
+    }
+
+    #[test]
+    fn indents_shared_lines_once() {
+        let cursors = [cursor(0, 1), cursor(0, 3), cursor(2, 1)];
+        let (mut source, mut selections) = source_with_cursors("test.py", "abcd\n\nef\n", &cursors);
+        source.indent(&mut selections);
+        assert_eq!(source.text(), "    abcd\n\n    ef\n");
+        assert_eq!(selections.all(), [cursor(0, 5), cursor(0, 7), cursor(2, 5)]);
+    }
+}
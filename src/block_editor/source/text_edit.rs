@@ -79,6 +79,26 @@ impl<'a> TextEdit<'a> {
         self.new_end_point
     }
 
+    /// Find where a point in the text ends up after the edit is applied.
+    /// Points inside of the replaced range move to the end of the new text.
+    pub fn moved_point(&self, point: TextPoint) -> TextPoint {
+        if point <= self.range.start {
+            point
+        } else if point < self.range.end {
+            self.new_end_point
+        } else if point.line == self.range.end.line {
+            TextPoint::new(
+                self.new_end_point.line,
+                self.new_end_point.col + (point.col - self.range.end.col),
+            )
+        } else {
+            TextPoint::new(
+                point.line + self.new_end_point.line - self.range.end.line,
+                point.col,
+            )
+        }
+    }
+
     #[cfg(test)]
     pub fn apply_to_rope(&self, source: &mut ropey::Rope) {
         let char_range = self.range.char_range_in(source);
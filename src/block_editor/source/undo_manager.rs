@@ -1,16 +1,46 @@
 use std::borrow::Cow;
 
-use super::{Source, TextEdit, TextRange};
+use super::{Selections, Source, TextEdit, TextRange};
 
 pub(super) enum UndoItem {
     /// An edit to apply
     Edit(TextEdit<'static>),
 
+    /// The selections from before and after an edit at multiple cursors,
+    /// so undoing or redoing it brings back every cursor
+    Selections {
+        before: SavedSelections,
+        after: SavedSelections,
+    },
+
     /// A stopper to separate undoable actions. Undoing applies edits until it hits a stop, allowing multiple edits to be undone at once.
     Stop,
 }
 
-#[derive(PartialEq)]
+/// The ranges of a set of selections, and the index of the primary one
+#[derive(Clone)]
+pub(super) struct SavedSelections {
+    pub ranges: Vec<TextRange>,
+    pub primary: usize,
+}
+
+impl SavedSelections {
+    pub fn of(selections: &Selections) -> Self {
+        SavedSelections {
+            ranges: selections.all().to_vec(),
+            primary: selections.primary_index(),
+        }
+    }
+
+    fn cursor(point: super::TextPoint) -> Self {
+        SavedSelections {
+            ranges: vec![TextRange::new_cursor(point)],
+            primary: 0,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
 pub enum UndoStopCondition {
     Always,
     IfNotMerged,
@@ -72,31 +102,52 @@ impl Source {
         // remove the oldest undo if there are too many to prevent memory buildup
         // remove up to the first stop to prevent leaving a partial undo
         if self.undo_stack.len() > 30 {
-            if let Some(first_stop) = self.undo_stack.iter().position(|x| match x {
-                UndoItem::Edit(_) => false,
-                UndoItem::Stop => true,
-            }) {
+            if let Some(first_stop) = self
+                .undo_stack
+                .iter()
+                .position(|x| matches!(x, UndoItem::Stop))
+            {
                 self.undo_stack.drain(0..=first_stop);
             }
         }
     }
 
+    /// Remember the selections from before and after an edit, if it was at multiple cursors
+    /// (otherwise undoing it puts the cursor where the edit was)
+    pub(super) fn add_undo_selections(&mut self, before: SavedSelections, after: &Selections) {
+        if before.ranges.len() > 1 || after.is_multiple() {
+            self.undo_stack.push(UndoItem::Selections {
+                before,
+                after: SavedSelections::of(after),
+            });
+        }
+    }
+
     // Prevents whatever is added to undo next from being undone alongside what is already there
     pub(super) fn add_undo_stop(&mut self) {
         // add a stop to the undo stack if there is not already one
-        if let Some(UndoItem::Edit(_)) = self.undo_stack.last() {
+        if self
+            .undo_stack
+            .last()
+            .is_some_and(|item| !matches!(item, UndoItem::Stop))
+        {
             self.undo_stack.push(UndoItem::Stop)
         }
     }
 
     fn add_redo_stop(&mut self) {
-        if let Some(UndoItem::Edit(_)) = self.redo_stack.last() {
+        if self
+            .redo_stack
+            .last()
+            .is_some_and(|item| !matches!(item, UndoItem::Stop))
+        {
             self.redo_stack.push(UndoItem::Stop)
         }
     }
 
     // Apply all the undos on the stack until it hits a stop. Adding their inverses to the redo stack.
-    pub(super) fn apply_undo(&mut self) -> Option<TextRange> {
+    // Returns the selections from before the first undone edit.
+    pub(super) fn apply_undo(&mut self) -> Option<SavedSelections> {
         // remove a stop from the top of the stack if it is there
         if let Some(UndoItem::Stop) = self.undo_stack.last() {
             self.undo_stack.pop();
@@ -106,21 +157,27 @@ impl Source {
         // add inverses of each of those edits to the redo stack
         self.add_redo_stop();
         let mut selection = None;
+        let mut saved = None;
         while let Some(item) = self.undo_stack.pop() {
             match item {
                 UndoItem::Stop => break,
                 UndoItem::Edit(edit) => {
                     let redo = self.apply(&edit);
                     self.redo_stack.push(UndoItem::Edit(redo));
-                    selection = Some(TextRange::new_cursor(edit.new_end()));
+                    selection = Some(SavedSelections::cursor(edit.new_end()));
+                }
+                UndoItem::Selections { before, after } => {
+                    saved = Some(before.clone());
+                    self.redo_stack.push(UndoItem::Selections { before, after });
                 }
             }
         }
-        selection
+        saved.or(selection)
     }
 
     // Apply all the redos on the stack until it hits a stop. Adding their inverses to the undo stack.
-    pub(super) fn apply_redo(&mut self) -> Option<TextRange> {
+    // Returns the selections from after the last redone edit.
+    pub(super) fn apply_redo(&mut self) -> Option<SavedSelections> {
         // remove a stop from the top of the stack if it is there
         if let Some(UndoItem::Stop) = self.undo_stack.last() {
             self.undo_stack.pop();
@@ -129,17 +186,22 @@ impl Source {
         // same as undo but reversed
         self.add_undo_stop();
         let mut selection = None;
+        let mut saved = None;
         while let Some(item) = self.redo_stack.pop() {
             match item {
                 UndoItem::Stop => break,
                 UndoItem::Edit(edit) => {
                     let undo = self.apply(&edit);
                     self.undo_stack.push(UndoItem::Edit(undo));
-                    selection = Some(TextRange::new_cursor(edit.new_end()));
+                    selection = Some(SavedSelections::cursor(edit.new_end()));
+                }
+                UndoItem::Selections { before, after } => {
+                    saved = Some(after.clone());
+                    self.undo_stack.push(UndoItem::Selections { before, after });
                 }
             }
         }
-        selection
+        saved.or(selection)
     }
 
     /// Combine two text edits if they are adjacent and can be combined (both insertions or deletions)
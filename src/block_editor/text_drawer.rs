@@ -9,12 +9,15 @@ use std::{
     ops::{Range, RangeInclusive},
 };
 
-use super::{blocks::Padding, source::Source, MonospaceFont};
+use super::{
+    blocks::Padding, rope_ext::next_visual_col, source::Source,
+    text_editor::bracket_matching::BRACKET_PAIRS, MonospaceFont,
+};
 use crate::{
     lang::{
         config::LanguageConfig,
         highlighter::{Highlight, HighlightEvent},
-        Language,
+        injections, Language,
     },
     theme,
 };
@@ -22,11 +25,23 @@ use crate::{
 // TODO: probably should have text drawers share highlight configurations
 pub struct TextDrawer {
     cache: Vec<ColoredText>,
+
+    /// if brackets are colored by how deeply they are nested
+    bracket_colorization: bool,
 }
 
 impl TextDrawer {
     pub fn new() -> Self {
-        Self { cache: vec![] }
+        Self {
+            cache: vec![],
+            bracket_colorization: false,
+        }
+    }
+
+    /// Turn coloring brackets by their depth on or off.
+    /// The text has to be highlighted again for it to take effect.
+    pub fn set_bracket_colorization(&mut self, enabled: bool) {
+        self.bracket_colorization = enabled;
     }
 
     pub fn draw(
@@ -55,29 +70,54 @@ impl TextDrawer {
     }
 
     pub fn highlight_source(&mut self, source: &mut Source) {
-        let mut highlighter = source.lang.highlighter.borrow_mut();
-        let highlight_config = source.lang.highlight_config.borrow_mut();
-        let node = source.get_tree_cursor().node();
-        let highlights = highlighter
-            .highlight_existing_tree(source.text().slice(..), node, &highlight_config)
-            .peekable();
-
-        self.handle_highlights(highlights, source.text(), source.lang.config);
+        let root_node = source.get_tree_cursor().map(|cursor| cursor.node());
+        self.highlight(root_node, source.text(), &source.lang);
     }
 
-    pub fn highlight(&mut self, root_node: Node, source: &Rope, lang: &mut Language) {
-        let mut highlighter = lang.highlighter.borrow_mut();
-        let highlight_config = lang.highlight_config.borrow_mut();
+    /// Highlight the text for the tree starting at `root_node`.
+    /// If there is no tree (the language doesn't have a grammar), the text is left uncolored.
+    pub fn highlight(&mut self, root_node: Option<Node>, source: &Rope, lang: &Language) {
+        let (Some(root_node), Some(syntax)) = (root_node, &lang.syntax) else {
+            self.handle_highlights(
+                std::iter::empty::<HighlightEvent>().peekable(),
+                vec![],
+                source,
+                lang.config,
+            );
+            return;
+        };
+
+        let bracket_colors = if self.bracket_colorization {
+            bracket_colors(root_node)
+        } else {
+            vec![]
+        };
+
+        let mut highlighter = syntax.highlighter.borrow_mut();
         let highlights = highlighter
-            .highlight_existing_tree(source.slice(..), root_node, &highlight_config)
+            .highlight_existing_tree(
+                source.slice(..),
+                root_node,
+                &syntax.highlight_config,
+                |name| {
+                    injections::resolve(name)
+                        // highlights are colored using the names of the outer language
+                        .filter(|injected| injected.config.highlight == lang.config.highlight)
+                        .and_then(|injected| injected.syntax.as_ref())
+                        .map(|syntax| &syntax.highlight_config)
+                },
+            )
             .peekable();
 
-        self.handle_highlights(highlights, source, lang.config);
+        self.handle_highlights(highlights, bracket_colors, source, lang.config);
     }
 
+    /// Build the colored text of each line from the highlights,
+    /// with the colors of brackets (given by their byte in the source) drawn over them
     fn handle_highlights(
         &mut self,
         mut highlights: Peekable<impl Iterator<Item = HighlightEvent>>,
+        bracket_colors: Vec<(usize, Color32)>,
         source: &Rope,
         lang: &LanguageConfig,
     ) {
@@ -88,6 +128,7 @@ impl TextDrawer {
         let mut next_to_handle = 0;
         let mut start_of_line = 0;
         let mut category_stack: Vec<Highlight> = vec![];
+        let mut bracket_colors = bracket_colors.into_iter().peekable();
 
         for line in source.lines() {
             // Cow::from uses a reference in most cases (since lines are usually short)
@@ -166,6 +207,12 @@ impl TextDrawer {
                 }
             }
 
+            while let Some((byte, color)) = bracket_colors.next_if(|(byte, _)| *byte < end_of_line)
+            {
+                let start = byte - start_of_line;
+                colored_text.override_color(color, start..start + 1);
+            }
+
             // build
             self.cache.push(colored_text.build());
 
@@ -175,6 +222,51 @@ impl TextDrawer {
     }
 }
 
+/// The color of each bracket token in the tree (by its byte), based on how deeply it is nested.
+/// Brackets in strings and comments aren't tokens, so they are left alone.
+fn bracket_colors(root_node: Node) -> Vec<(usize, Color32)> {
+    let palette = theme::syntax::BRACKET_PALETTE;
+    let mut colors = vec![];
+    let mut depth: usize = 0;
+
+    let mut cursor = root_node.walk();
+    'outer: loop {
+        let node = cursor.node();
+        if node.child_count() == 0 && !node.is_missing() {
+            let mut kind = node.kind().chars();
+            let bracket = kind.next().filter(|_| kind.next().is_none()).and_then(|c| {
+                BRACKET_PAIRS
+                    .iter()
+                    .find_map(|(open, close)| (c == *open || c == *close).then_some(c == *open))
+            });
+            match bracket {
+                Some(true) => {
+                    colors.push((node.start_byte(), palette[depth % palette.len()]));
+                    depth += 1;
+                }
+                Some(false) => {
+                    depth = depth.saturating_sub(1);
+                    colors.push((node.start_byte(), palette[depth % palette.len()]));
+                }
+                None => {}
+            }
+        }
+
+        // go to the next node in order
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        while cursor.goto_parent() {
+            if cursor.goto_next_sibling() {
+                continue 'outer;
+            }
+        }
+        break;
+    }
+
+    colors
+}
+
 struct ColorRange {
     color: Color32,
     range: Range<usize>,
@@ -183,6 +275,9 @@ struct ColorRange {
 struct ColoredTextBuilder<'a> {
     text: Cow<'a, str>,
     color_ranges: Vec<ColorRange>,
+
+    /// colors drawn over the others (in order, without overlapping)
+    overrides: Vec<ColorRange>,
 }
 
 struct ColoredText {
@@ -194,6 +289,7 @@ impl<'a> ColoredTextBuilder<'a> {
         Self {
             text,
             color_ranges: vec![],
+            overrides: vec![],
         }
     }
 
@@ -201,28 +297,51 @@ impl<'a> ColoredTextBuilder<'a> {
         self.color_ranges.push(ColorRange { color, range });
     }
 
+    fn override_color(&mut self, color: Color32, range: Range<usize>) {
+        self.overrides.push(ColorRange { color, range });
+    }
+
     fn build(self) -> ColoredText {
-        let mut chunks = vec![];
+        let mut ranges = vec![];
 
         // apply colors
         let mut handled_up_to = 0;
         for color_range in self.color_ranges {
             // add anything this might have skipped
             if handled_up_to < color_range.range.start {
-                let text = &self.text[handled_up_to..color_range.range.start];
-                chunks.push((text.to_string(), theme::syntax::DEFAULT));
+                ranges.push(ColorRange {
+                    color: theme::syntax::DEFAULT,
+                    range: handled_up_to..color_range.range.start,
+                });
             }
 
             // add this
             handled_up_to = color_range.range.end;
-            let text = &self.text[color_range.range];
-            chunks.push((text.to_string(), color_range.color));
+            ranges.push(color_range);
         }
 
         // add the rest
         if handled_up_to != self.text.len() {
-            let text = &self.text[handled_up_to..];
-            chunks.push((text.to_string(), theme::syntax::DEFAULT));
+            ranges.push(ColorRange {
+                color: theme::syntax::DEFAULT,
+                range: handled_up_to..self.text.len(),
+            });
+        }
+
+        // split the ranges around the overrides
+        let mut chunks = vec![];
+        let mut overrides = self.overrides.into_iter().peekable();
+        for ColorRange { color, mut range } in ranges {
+            while let Some(over) = overrides.next_if(|over| over.range.start < range.end) {
+                if range.start < over.range.start {
+                    chunks.push((self.text[range.start..over.range.start].to_string(), color));
+                }
+                chunks.push((self.text[over.range.clone()].to_string(), over.color));
+                range.start = max(range.start, over.range.end);
+            }
+            if range.start < range.end {
+                chunks.push((self.text[range].to_string(), color));
+            }
         }
 
         ColoredText { chunks }
@@ -230,22 +349,53 @@ impl<'a> ColoredTextBuilder<'a> {
 }
 
 impl ColoredText {
-    fn draw(&self, mut offset: Vec2, font: &MonospaceFont, painter: &Painter) {
+    fn draw(&self, offset: Vec2, font: &MonospaceFont, painter: &Painter) {
         // draw by character until egui fixes monospace layout by switching to cosmic-text:
         // https://github.com/emilk/egui/issues/3378
+        let mut col = 0;
         for (text, color) in &self.chunks {
             for char in text.chars() {
                 if !char.is_whitespace() {
                     painter.text(
-                        offset.to_pos2(),
+                        (offset + Vec2::new(col as f32 * font.size.x, 0.0)).to_pos2(),
                         Align2::LEFT_TOP,
                         char,
                         font.id.clone(),
                         *color,
                     );
                 }
-                offset.x += font.size.x;
+
+                col = next_visual_col(col, char, font.tab_width);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::config::LanguageConfig;
+
+    #[test]
+    fn colors_brackets_by_depth() {
+        let code = "f(a[\"(\"], (b))\n";
+        let config = LanguageConfig::for_file("test.py");
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&config.tree_sitter().unwrap()).unwrap();
+        let tree = parser.parse(code, None).unwrap();
+
+        // the bracket in the string is skipped
+        let palette = theme::syntax::BRACKET_PALETTE;
+        assert_eq!(
+            bracket_colors(tree.root_node()),
+            [
+                (1, palette[0]),
+                (3, palette[1]),
+                (7, palette[1]),
+                (10, palette[1]),
+                (12, palette[1]),
+                (13, palette[0]),
+            ]
+        );
+    }
+}
@@ -11,7 +11,7 @@ use crate::{
     block_editor::{
         blocks::Block,
         rope_ext::{RopeExt, RopeSliceExt},
-        source::{Source, UndoStopCondition},
+        source::{IndentStyle, Source, UndoStopCondition},
         text_range::{
             movement::{HDir, HUnit, TextMovement},
             TextPoint, TextRange,
@@ -39,7 +39,7 @@ impl TextEditor {
                 "editor-block-drag",
                 HashMap::from([
                     ("type", block.syntax_type.as_str()),
-                    ("lang", source.lang.config.name),
+                    ("lang", source.lang.config.name.as_str()),
                 ]),
             );
 
@@ -62,6 +62,7 @@ impl TextEditor {
                     line: block.line,
                 },
                 self.blocks.padding(),
+                source.text(),
                 font,
             );
             let relative_pos =
@@ -91,7 +92,12 @@ impl TextEditor {
     ) -> bool {
         // note: using take() also sets to None
         if let Some(drag_block) = drag_block.take() {
-            let mut indented_text = set_indent(&drag_block.text, drop_point.col);
+            // match the indentation of the file (blocks from the palette or other files might not)
+            let indent_style = source.indent_style();
+            let block_text = IndentStyle::detect(&Rope::from_str(&drag_block.text))
+                .reindent(&drag_block.text, indent_style);
+            let mut indented_text =
+                set_indent(&block_text, &indent_style.whitespace(drop_point.col));
 
             // if at the end of the file, and the last line doesn't have a newline, add one
             if drop_point.line == source.text().len_lines() {
@@ -123,14 +129,19 @@ impl TextEditor {
         drop_point: TextPoint,
         viewport_width: f32,
         offset: Vec2,
+        indent_style: IndentStyle,
         font: &MonospaceFont,
         painter: &Painter,
     ) {
         const THICKNESS: f32 = 4.0;
 
+        // the drop point's column is the number of indent characters, which are wider if they are tabs
+        let visual_col =
+            drop_point.col * indent_style.unit_width(font.tab_width) / indent_style.unit_chars();
+
         let line_padding_above = self.blocks.padding().cumulative(drop_point.line);
         let y = (drop_point.line as f32) * font.size.y + OUTER_PAD + line_padding_above;
-        let x = (drop_point.col as f32) * font.size.x + OUTER_PAD + GUTTER_WIDTH;
+        let x = (visual_col as f32) * font.size.x + OUTER_PAD + GUTTER_WIDTH;
 
         let origin = Pos2::new(x, y);
         let size = Vec2::new(viewport_width - origin.x - 10.0, THICKNESS);
@@ -153,6 +164,7 @@ impl TextEditor {
 
         // limit to the first non-empty above line's level
         // (or 1 more if it ends in a new scope character)
+        let indent_style = source.indent_style();
         let mut relative_whitespace_line = line;
         let allowed_indent = loop {
             if relative_whitespace_line == 0 {
@@ -169,21 +181,24 @@ impl TextEditor {
             }
 
             // if the line above ends in a new scope character, allow one more indent
-            if line_above
-                .excluding_linebreak()
-                .ends_with(source.lang.config.new_scope_char.char())
+            let new_scope_char = source.lang.config.new_scope_char;
+            if new_scope_char.is_some_and(|c| line_above.excluding_linebreak().ends_with(c.char()))
             {
-                break above_indent + 4;
+                break above_indent + indent_style.unit_chars();
             }
 
             // otherwise, allow up to the same indent as the line above
             break above_indent;
         };
         let indent = match source.lang.config.new_scope_char {
-            // when scope is indent based, allow reducing scope when dragging
-            NewScopeChar::Colon => ((coord.col / 4) * 4).min(allowed_indent),
+            // when scope is indent based (or there are no scopes), allow reducing scope when dragging
+            Some(NewScopeChar::Colon) | None => {
+                // the mouse column is where it is drawn, so find how many levels of indent it is past
+                let levels = coord.col / indent_style.unit_width(font.tab_width);
+                (levels * indent_style.unit_chars()).min(allowed_indent)
+            }
             // when scope is brace based, only allow the maximum indent
-            NewScopeChar::Brace => allowed_indent,
+            Some(NewScopeChar::Brace) => allowed_indent,
         };
 
         TextPoint::new(line, indent)
@@ -191,7 +206,11 @@ impl TextEditor {
 }
 
 /* ---------------------------- Helper Functions ---------------------------- */
-fn block_for_point<'a>(blocks: &'a [Block], point: TextPoint, source: &Rope) -> Option<&'a Block> {
+pub(super) fn block_for_point<'a>(
+    blocks: &'a [Block],
+    point: TextPoint,
+    source: &Rope,
+) -> Option<&'a Block> {
     let mut curr_block: Option<&Block> = None;
     let mut curr_level = blocks;
     'outer: while !curr_level.is_empty() {
@@ -225,8 +244,7 @@ fn block_for_point<'a>(blocks: &'a [Block], point: TextPoint, source: &Rope) ->
 fn normalize_indent(mut block: String) -> String {
     // set indent of string to the insertion point row
     // all lines after the first are indented relative to the first
-    let existing_indent_count = block.chars().take_while(|c| c.is_whitespace()).count();
-    let existing_indent = " ".repeat(existing_indent_count);
+    let existing_indent: String = block.chars().take_while(|c| c.is_whitespace()).collect();
 
     // replace the indent at the start (which is not after a newline)
     block = block.replacen(&existing_indent, "", 1);
@@ -238,11 +256,10 @@ fn normalize_indent(mut block: String) -> String {
     block
 }
 
-/// Increases the indent of a *normalized* block so the first line is indented to new_indent_count
+/// Increases the indent of a *normalized* block so the first line is indented with new_indent
 /// Assumes the indents of all lines are aligned.
-fn set_indent(block: &str, new_indent_count: usize) -> String {
+fn set_indent(block: &str, new_indent: &str) -> String {
     // get the new indent
-    let new_indent = " ".repeat(new_indent_count);
     let new_linebreak_indent = format!("\n{new_indent}");
 
     // add indent to start of string
@@ -0,0 +1,42 @@
+use egui::{Response, Ui, Vec2};
+
+use super::{block_dragging::block_for_point, coord_conversions::pt_to_text_coord, TextEditor};
+use crate::block_editor::{source::Source, MonospaceFont, GUTTER_WIDTH};
+
+impl TextEditor {
+    /// Show a menu of actions for a block when it is right-clicked
+    pub fn show_block_menu(
+        &mut self,
+        ui: &Ui,
+        offset: Vec2,
+        response: &Response,
+        source: &mut Source,
+        font: &MonospaceFont,
+    ) {
+        if response.secondary_clicked() {
+            self.block_menu_range = ui
+                .ctx()
+                .pointer_interact_pos()
+                .map(|pointer_pos| pointer_pos - offset)
+                .filter(|pos| pos.x >= GUTTER_WIDTH)
+                .and_then(|pos| {
+                    let point = pt_to_text_coord(pos, self.blocks.padding(), source.text(), font);
+                    block_for_point(self.blocks.trees(), point, source.text())
+                })
+                .map(|block| block.text_range());
+        }
+
+        // only open the menu over blocks
+        let Some(block_range) = self.block_menu_range else {
+            return;
+        };
+        response.context_menu(|ui| {
+            if ui.button("Comment out block").clicked() {
+                self.selections.set_selection(block_range, source);
+                source.toggle_comment(&mut self.selections);
+                self.completion_popup.clear();
+                ui.close_menu();
+            }
+        });
+    }
+}
@@ -0,0 +1,315 @@
+use egui::{Painter, Stroke, Vec2};
+use ropey::Rope;
+use tree_sitter::Node;
+
+use super::TextEditor;
+use crate::{
+    block_editor::{
+        source::Source,
+        text_range::{TextPoint, TextRange},
+        MonospaceFont,
+    },
+    theme,
+};
+
+/// The opening and closing characters of the brackets that are matched
+pub const BRACKET_PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// How far to look for a matching bracket when the syntax tree can't be used
+const MAX_SCAN_CHARS: usize = 20_000;
+
+/// The character indices of a pair of matching brackets
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BracketPair {
+    pub open: usize,
+    pub close: usize,
+}
+
+impl BracketPair {
+    /// Find the pair of brackets next to the cursor (at the char index),
+    /// or the innermost pair around it if it isn't next to one.
+    ///
+    /// The syntax tree is used so brackets in strings and comments aren't matched,
+    /// but the text is scanned instead in error regions (like while a pair is being typed).
+    pub fn around(cursor: usize, source: &Source) -> Option<BracketPair> {
+        let text = source.text();
+
+        // prefer the bracket after the cursor, like other editors
+        let next_to = [Some(cursor), cursor.checked_sub(1)]
+            .into_iter()
+            .flatten()
+            .filter(|idx| *idx < text.len_chars());
+        for idx in next_to {
+            if let Some(other) = matching_bracket(idx, source) {
+                return Some(BracketPair {
+                    open: idx.min(other),
+                    close: idx.max(other),
+                });
+            }
+        }
+
+        enclosing_pair(cursor, source)
+    }
+
+    /// The ranges of the opening and closing brackets
+    pub fn ranges(&self, text: &Rope) -> [TextRange; 2] {
+        [self.open, self.close].map(|idx| TextRange::from_char_range_in(text, idx..idx + 1))
+    }
+}
+
+/// The brackets a character is part of, and if it is the opening one
+fn bracket_kind(c: char) -> Option<((char, char), bool)> {
+    BRACKET_PAIRS.iter().find_map(|&(open, close)| {
+        if c == open {
+            Some(((open, close), true))
+        } else if c == close {
+            Some(((open, close), false))
+        } else {
+            None
+        }
+    })
+}
+
+/// The char index of the bracket matching the bracket at `idx`, if it is one
+fn matching_bracket(idx: usize, source: &Source) -> Option<usize> {
+    let text = source.text();
+    let (pair, is_open) = bracket_kind(text.char(idx))?;
+
+    let Some(cursor) = source.get_tree_cursor() else {
+        return scan_for_match(idx, pair, is_open, text);
+    };
+
+    let byte = text.char_to_byte(idx);
+    let node = cursor.node().descendant_for_byte_range(byte, byte + 1)?;
+    let is_bracket_token = node.start_byte() == byte && node.end_byte() == byte + 1;
+
+    let found = is_bracket_token
+        .then(|| sibling_match(node, is_open))
+        .flatten();
+    match found {
+        Some(other) => Some(text.byte_to_char(other.start_byte())),
+        None if in_error(node) => scan_for_match(idx, pair, is_open, text),
+        // like a bracket in a string
+        None => None,
+    }
+}
+
+/// Find the bracket that matches a bracket token among the nodes next to it
+fn sibling_match<'tree>(node: Node<'tree>, is_open: bool) -> Option<Node<'tree>> {
+    let parent = node.parent()?;
+    let mut cursor = parent.walk();
+    let siblings: Vec<Node> = parent.children(&mut cursor).collect();
+    let index = siblings
+        .iter()
+        .position(|sibling| sibling.id() == node.id())?;
+
+    let (own, _) = bracket_kind(node.kind().chars().next()?)?;
+    let (same, other) = if is_open {
+        (own.0, own.1)
+    } else {
+        (own.1, own.0)
+    };
+    let is_kind = |node: &Node, c: char| node.kind().chars().eq([c]);
+
+    let candidates: Box<dyn Iterator<Item = &Node>> = if is_open {
+        Box::new(siblings[index..].iter())
+    } else {
+        Box::new(siblings[..=index].iter().rev())
+    };
+    let mut depth = 0;
+    for sibling in candidates.filter(|sibling| !sibling.is_missing()) {
+        if is_kind(sibling, same) {
+            depth += 1;
+        } else if is_kind(sibling, other) {
+            depth -= 1;
+            if depth == 0 {
+                return Some(*sibling);
+            }
+        }
+    }
+    None
+}
+
+/// If the node is in a part of the tree that couldn't be parsed
+fn in_error(node: Node) -> bool {
+    node.is_error()
+        || node
+            .parent()
+            .is_some_and(|parent| parent.is_error() || parent.has_error())
+}
+
+/// Find the bracket matching the one at `idx` by counting brackets in the text
+fn scan_for_match(idx: usize, pair: (char, char), is_open: bool, text: &Rope) -> Option<usize> {
+    let (same, other) = if is_open {
+        (pair.0, pair.1)
+    } else {
+        (pair.1, pair.0)
+    };
+
+    let mut depth = 0;
+    for offset in 0..MAX_SCAN_CHARS {
+        let scan_idx = if is_open {
+            idx + offset
+        } else {
+            idx.checked_sub(offset)?
+        };
+        let c = text.get_char(scan_idx)?;
+        if c == same {
+            depth += 1;
+        } else if c == other {
+            depth -= 1;
+            if depth == 0 {
+                return Some(scan_idx);
+            }
+        }
+    }
+    None
+}
+
+/// Find the innermost pair of brackets around the cursor (at the char index)
+fn enclosing_pair(cursor: usize, source: &Source) -> Option<BracketPair> {
+    let text = source.text();
+    let Some(tree_cursor) = source.get_tree_cursor() else {
+        return scan_for_enclosing(cursor, text);
+    };
+
+    let byte = text.char_to_byte(cursor);
+    let start = tree_cursor.node().descendant_for_byte_range(byte, byte)?;
+    let mut node = start;
+    loop {
+        // the last opening bracket before the cursor that closes after it
+        let mut walker = node.walk();
+        let children: Vec<Node> = node.children(&mut walker).collect();
+        let pair = children.iter().rev().find_map(|child| {
+            let (_, is_open) = bracket_kind(child.kind().chars().next()?)?;
+            if !is_open || child.kind().len() != 1 || child.end_byte() > byte {
+                return None;
+            }
+            let close = sibling_match(*child, true)?;
+            (close.start_byte() >= byte).then(|| BracketPair {
+                open: text.byte_to_char(child.start_byte()),
+                close: text.byte_to_char(close.start_byte()),
+            })
+        });
+        if pair.is_some() {
+            return pair;
+        }
+
+        match node.parent() {
+            Some(parent) => node = parent,
+            None if start.has_error() || in_error(start) => {
+                return scan_for_enclosing(cursor, text)
+            }
+            None => return None,
+        }
+    }
+}
+
+/// Find the innermost pair of brackets around the cursor by counting brackets in the text
+fn scan_for_enclosing(cursor: usize, text: &Rope) -> Option<BracketPair> {
+    // the closing brackets that have been passed, which need their opening brackets skipped
+    let mut closed: Vec<char> = vec![];
+    for offset in 1..MAX_SCAN_CHARS {
+        let idx = cursor.checked_sub(offset)?;
+        let Some(((open, close), is_open)) = bracket_kind(text.char(idx)) else {
+            continue;
+        };
+        if !is_open {
+            closed.push(close);
+        } else if closed.last().is_some_and(|c| *c == close) {
+            closed.pop();
+        } else if closed.is_empty() {
+            let close = scan_for_match(idx, (open, close), true, text)?;
+            return Some(BracketPair { open: idx, close });
+        }
+    }
+    None
+}
+
+impl TextEditor {
+    /// Highlight the pair of brackets around the primary cursor
+    pub fn draw_matching_brackets(
+        &self,
+        offset: Vec2,
+        source: &Source,
+        font: &MonospaceFont,
+        painter: &Painter,
+    ) {
+        let selection = self.selections.selection();
+        if !selection.is_cursor() {
+            return;
+        }
+
+        let cursor = selection.end.char_idx_in(source.text());
+        if let Some(pair) = BracketPair::around(cursor, source) {
+            for range in pair.ranges(source.text()) {
+                range.draw_selection_blocks(
+                    theme::MATCHING_BRACKET,
+                    Stroke::new(1.0_f32, theme::MATCHING_BRACKET_BORDER),
+                    offset,
+                    self.blocks.padding(),
+                    source.text(),
+                    font,
+                    painter,
+                );
+            }
+        }
+    }
+
+    /// Move the cursor to the other bracket of the pair around it,
+    /// or select the pair (including the brackets) if `selecting`
+    pub fn jump_to_matching_bracket(&mut self, selecting: bool, source: &mut Source) {
+        let text = source.text();
+        let cursor = self.selections.selection().end.char_idx_in(text);
+        let Some(pair) = BracketPair::around(cursor, source) else {
+            return;
+        };
+
+        let new_range = if selecting {
+            TextRange::from_char_range_in(text, pair.open..pair.close + 1)
+        } else if cursor == pair.close || cursor == pair.close + 1 {
+            // at the closing bracket, so go before the opening one
+            TextRange::new_cursor(char_point(text, pair.open))
+        } else {
+            // at the opening bracket (or inside the pair), so go after the closing one
+            TextRange::new_cursor(char_point(text, pair.close + 1))
+        };
+        self.selections.set_selection(new_range, source);
+        source.external_cursor_move();
+    }
+}
+
+fn char_point(text: &Rope, idx: usize) -> TextPoint {
+    TextRange::from_char_range_in(text, idx..idx).start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::{config::LanguageConfig, Language};
+
+    /// Find the pair around the `|` in the code
+    fn pair_around(code: &str) -> Option<(usize, usize)> {
+        let cursor = code.find('|').unwrap();
+        let code = code.replace('|', "");
+        let lang = Language::new(LanguageConfig::for_file("test.py")).unwrap();
+        let source = Source::new(Rope::from_str(&code), lang);
+        BracketPair::around(cursor, &source).map(|pair| (pair.open, pair.close))
+    }
+
+    #[test]
+    fn matches_brackets() {
+        // next to the cursor, preferring the one after it
+        assert_eq!(pair_around("f(|a[1])"), Some((1, 6)));
+        assert_eq!(pair_around("f(a|[1])"), Some((3, 5)));
+        assert_eq!(pair_around("f(a[1]|)"), Some((1, 6)));
+
+        // around the cursor, skipping ones in strings
+        assert_eq!(pair_around("f(a, \")\", |b)"), Some((1, 11)));
+        assert_eq!(pair_around("a = |1"), None);
+
+        // and in code that doesn't parse
+        assert_eq!(pair_around("x = |(1 +)\n"), Some((4, 8)));
+        assert_eq!(pair_around("x = |[f(a)\n"), None);
+    }
+}
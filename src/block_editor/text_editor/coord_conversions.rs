@@ -3,8 +3,9 @@ use ropey::Rope;
 
 use super::TextPoint;
 use crate::block_editor::{
-    blocks::Padding, rope_ext::RopeExt, MonospaceFont, GUTTER_WIDTH, OUTER_PAD, TEXT_L_PAD,
-    TOTAL_TEXT_X_OFFSET,
+    blocks::Padding,
+    rope_ext::{RopeExt, RopeSliceExt},
+    MonospaceFont, GUTTER_WIDTH, OUTER_PAD, TEXT_L_PAD, TOTAL_TEXT_X_OFFSET,
 };
 
 pub fn pt_to_text_coord(
@@ -34,14 +35,16 @@ pub fn pt_to_text_coord(
 
     // TODO: if past last line, move to end of last line
 
-    let col_raw =
+    let visual_col =
         ((point.x - OUTER_PAD - GUTTER_WIDTH - TEXT_L_PAD) / font.size.x).round() as usize;
+    let col_raw = source.line(line).col_at_visual(visual_col, font.tab_width);
     let col_bound = source.clamp_col(line, col_raw);
 
     TextPoint::new(line, col_bound)
 }
 
-/// Finds the text coordinate that the mouse is over, without clamping to a valid position within the text
+/// Finds the text coordinate that the mouse is over, without clamping to a valid position within the text.
+/// Since the line might not exist, the column is where it is drawn (tabs are not accounted for).
 pub fn pt_to_unbounded_text_coord(
     point: Pos2,
     padding: &Padding,
@@ -68,9 +71,17 @@ pub fn pt_to_unbounded_text_coord(
     TextPoint::new(line, col)
 }
 
-pub fn text_coord_to_pt(coord: TextPoint, padding: &Padding, font: &MonospaceFont) -> Pos2 {
+pub fn text_coord_to_pt(
+    coord: TextPoint,
+    padding: &Padding,
+    source: &Rope,
+    font: &MonospaceFont,
+) -> Pos2 {
+    let visual_col = source
+        .get_line(coord.line)
+        .map_or(coord.col, |line| line.visual_col(coord.col, font.tab_width));
     let y = OUTER_PAD + (coord.line as f32 * font.size.y) + padding.cumulative(coord.line);
-    let x = TOTAL_TEXT_X_OFFSET + (coord.col as f32 * font.size.x);
+    let x = TOTAL_TEXT_X_OFFSET + (visual_col as f32 * font.size.x);
 
     Pos2::new(x, y)
 }
@@ -4,7 +4,9 @@ use egui::{Align2, Painter, Pos2, Rect, Response, Stroke, Ui, Vec2, Widget};
 use ropey::Rope;
 
 use crate::{
-    block_editor::{blocks::Padding, MonospaceFont, OUTER_PAD, TOTAL_TEXT_X_OFFSET},
+    block_editor::{
+        blocks::Padding, rope_ext::RopeSliceExt, MonospaceFont, OUTER_PAD, TOTAL_TEXT_X_OFFSET,
+    },
     lsp::diagnostics::{Diagnostic, VSCodeCodeAction},
     theme,
     util_widgets::SelectableRow,
@@ -152,9 +154,12 @@ impl Diagnostic {
             let y =
                 padding.cumulative(line_num) + ((line_num + 1) as f32 * font.size.y) + OUTER_PAD;
 
-            // find the start and end of the line
-            let x = TOTAL_TEXT_X_OFFSET + (line_range.start.col as f32 * font.size.x);
-            let width = (line_range.end.col - line_range.start.col) as f32 * font.size.x;
+            // find the start and end of the line (accounting for tabs)
+            let line = source.line(line_num);
+            let start_col = line.visual_col(line_range.start.col, font.tab_width);
+            let end_col = line.visual_col(line_range.end.col, font.tab_width);
+            let x = TOTAL_TEXT_X_OFFSET + (start_col as f32 * font.size.x);
+            let width = (end_col - start_col) as f32 * font.size.x;
 
             // draw line
             painter.line_segment(
@@ -1,6 +1,8 @@
 use std::collections::HashSet;
 
 mod block_dragging;
+mod block_menu;
+pub mod bracket_matching;
 mod completion_popup;
 mod coord_conversions;
 mod diagnostics_popup;
@@ -45,6 +47,9 @@ pub struct TextEditor {
     /// blocks to draw
     blocks: BlockTrees,
 
+    /// the text of the block that was right-clicked, if its menu is open
+    block_menu_range: Option<TextRange>,
+
     /// line numbers that have breakpoints
     breakpoints: HashSet<usize>,
 
@@ -59,6 +64,12 @@ pub struct TextEditor {
 
     /// overlay view for hover
     documentation_popup: DocumentationPopup,
+
+    /// if the editor should take the keyboard focus on the next frame
+    focus_requested: bool,
+
+    /// if the editor should scroll to the selection on the next frame
+    scroll_to_selection: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -87,11 +98,14 @@ impl TextEditor {
             diagnostic_selection: Option::None,
             text_drawer: TextDrawer::new(),
             blocks: BlockTrees::default(),
+            block_menu_range: None,
             breakpoints: HashSet::new(),
             stack_frame: StackFrameLines::empty(),
             diagnostic_popup: DiagnosticPopup::new(),
             completion_popup: CompletionPopup::new(),
             documentation_popup: DocumentationPopup::new(),
+            focus_requested: false,
+            scroll_to_selection: false,
         }
     }
 }
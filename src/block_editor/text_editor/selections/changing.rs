@@ -1,16 +1,66 @@
 use egui::Pos2;
-use tree_sitter::TreeCursor;
+use ropey::Rope;
+use tree_sitter::{Node, TreeCursor};
 
-use super::Selections;
+use super::{ColumnSelection, Selections};
 use crate::block_editor::{
-    blocks::Padding, rope_ext::RopeExt, source::Source,
-    text_editor::coord_conversions::pt_to_text_coord, text_range::movement::TextMovement,
-    MonospaceFont, TextRange,
+    blocks::{Block, Padding},
+    rope_ext::{RopeExt, RopeSliceExt},
+    search,
+    source::Source,
+    text_editor::coord_conversions::{pt_to_text_coord, pt_to_unbounded_text_coord},
+    text_range::{
+        movement::{TextMovement, VDir},
+        TextPoint,
+    },
+    BlockType, MonospaceFont, TextRange,
 };
 
 impl Selections {
+    /// Set a single selection, removing any other cursors
     pub fn set_selection(&mut self, new_selection: TextRange, source: &Source) {
-        self.selection = new_selection;
+        self.set_all(vec![new_selection], 0, source);
+    }
+
+    /// Set every selection, where `primary` is the index of the primary one.
+    /// Selections that overlap are merged together.
+    pub fn set_all(&mut self, mut ranges: Vec<TextRange>, primary: usize, source: &Source) {
+        if ranges.is_empty() {
+            ranges.push(TextRange::ZERO);
+        }
+
+        // sort them by where they start, keeping track of the primary
+        let mut indexed: Vec<(usize, TextRange)> = ranges.into_iter().enumerate().collect();
+        indexed.sort_by(|(_, a), (_, b)| {
+            let (a, b) = (a.ordered(), b.ordered());
+            (a.start.line, a.start.col).cmp(&(b.start.line, b.start.col))
+        });
+
+        self.ranges = Vec::with_capacity(indexed.len());
+        self.primary = 0;
+        self.line_columns = None;
+        self.column = None;
+        self.grow_history.clear();
+        for (index, range) in indexed {
+            let merged = self.ranges.last_mut().and_then(|last| {
+                let (prev, next) = (last.ordered(), range.ordered());
+                let touching = next.start == prev.end && (prev.is_cursor() || next.is_cursor());
+                (next.start < prev.end || touching).then(|| {
+                    let end = if next.end > prev.end {
+                        next.end
+                    } else {
+                        prev.end
+                    };
+                    *last = TextRange::new(prev.start, end);
+                })
+            });
+            if merged.is_none() {
+                self.ranges.push(range);
+            }
+            if index == primary {
+                self.primary = self.ranges.len() - 1;
+            }
+        }
 
         // find pseudo selection for new position
         self.find_pseudo_selection(source);
@@ -19,26 +69,79 @@ impl Selections {
         self.last_selection_time = self.frame_start_time;
     }
 
+    /// Set the selections after they were edited.
+    /// A column selection is kept (moved to the cursors' new column) if the edit left
+    /// a cursor on each of its lines and they still line up.
+    pub fn set_edited(&mut self, ranges: Vec<TextRange>, source: &Source) {
+        let primary = self.primary;
+        let Some(column) = self.column else {
+            self.set_all(ranges, primary, source);
+            return;
+        };
+
+        let text = source.text();
+        let first_line = *column.lines().start();
+        let same_lines = ranges.len() == column.lines().count()
+            && ranges
+                .iter()
+                .enumerate()
+                .all(|(index, range)| range.is_cursor() && range.start.line == first_line + index);
+
+        // cursors at the end of short lines line up with any column past them
+        let line_len = |range: &TextRange| text.line(range.start.line).len_chars_no_linebreak();
+        let visual_col = |range: &TextRange| {
+            text.line(range.start.line)
+                .visual_col(range.start.col, column.tab_width)
+        };
+        let col = ranges.iter().map(visual_col).max().unwrap_or(0);
+        let lined_up = ranges
+            .iter()
+            .all(|range| visual_col(range) == col || range.start.col == line_len(range));
+
+        if same_lines && lined_up {
+            let column = ColumnSelection {
+                anchor: TextPoint::new(column.anchor.line, col),
+                head: TextPoint::new(column.head.line, col),
+                tab_width: column.tab_width,
+            };
+            self.set_column(column, source);
+        } else {
+            self.set_all(ranges, primary, source);
+        }
+    }
+
     // Set the selection as a result of non-edit user input
     fn set_selection_user(&mut self, selection: TextRange, source: &mut Source) {
         self.set_selection(selection, source);
         source.external_cursor_move();
     }
 
+    // Set all the selections as a result of non-edit user input
+    fn set_all_user(&mut self, ranges: Vec<TextRange>, primary: usize, source: &mut Source) {
+        self.set_all(ranges, primary, source);
+        source.external_cursor_move();
+    }
+
+    /// Remove every selection other than the primary one
+    pub fn remove_extra(&mut self, source: &mut Source) {
+        if self.is_multiple() {
+            self.set_selection_user(self.selection(), source);
+        }
+    }
+
     pub fn find_pseudo_selection(&mut self, source: &Source) {
         self.pseudo_selection = None;
-        if self.selection.is_cursor() {
+        if !self.is_multiple() && self.selection().is_cursor() {
             // find if the cursor is after a quote
-            let cursor_loc = self.selection.start;
+            let cursor_loc = self.selection().start;
             let cursor_offset = cursor_loc.char_idx_in(source.text());
             let (prev_char, _) = source.text().surrounding_chars(cursor_offset);
 
             if prev_char == '"' || prev_char == '\'' {
-                self.pseudo_selection = self.string_pseudo_selection_range(
-                    source.get_tree_cursor(),
-                    cursor_loc.into(),
-                    source,
-                );
+                if let Some(tree_cursor) = source.get_tree_cursor() {
+                    self.pseudo_selection =
+                        self.string_pseudo_selection_range(tree_cursor, cursor_loc.into(), source);
+                }
             }
         }
     }
@@ -59,12 +162,12 @@ impl Selections {
 
         // verify that our current point is the start or end of a string (not an escape sequence)
         let current_kind = cursor.node().kind_id();
-        let kinds = source.lang.config.string_node_ids;
+        let kinds = source.lang.syntax.as_ref()?.string_node_ids.as_ref()?;
         if !kinds.string_bounds.contains(&current_kind) {
             return None;
         }
 
-        // go up until we hit the string (node of id 230)
+        // go up until we hit the string node
         while cursor.goto_parent() {
             let node = cursor.node();
             if node.kind_id() == kinds.string {
@@ -80,15 +183,135 @@ impl Selections {
 
     /* ----------------------------- Cursor Movement ---------------------------- */
     pub fn move_cursor(&mut self, movement: TextMovement, source: &mut Source) {
-        let new_cursor = self
-            .selection
-            .find_movement_result(movement, source.text(), false);
-        self.set_selection_user(TextRange::new_cursor(new_cursor), source);
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|range| {
+                TextRange::new_cursor(range.find_movement_result(movement, source.text(), false))
+            })
+            .collect();
+        self.set_all_user(ranges, self.primary, source);
     }
 
     pub fn move_selecting(&mut self, movement: TextMovement, source: &mut Source) {
-        let new_sel = self.selection.expanded_by(movement, source.text());
-        self.set_selection_user(new_sel, source);
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|range| range.expanded_by(movement, source.text()))
+            .collect();
+        self.set_all_user(ranges, self.primary, source);
+    }
+
+    /* ----------------------------- Adding Cursors ----------------------------- */
+    /// Select the next occurrence of the primary selection's text as well.
+    /// If the primary selection is a cursor, this selects the word around it instead.
+    pub fn add_next_occurrence(&mut self, source: &mut Source) {
+        let primary = self.selection().ordered();
+        if primary.is_cursor() {
+            if let Some(word) = TextRange::word_around(primary.start, source.text()) {
+                let mut ranges = self.ranges.clone();
+                ranges[self.primary] = word;
+                self.set_all_user(ranges, self.primary, source);
+            }
+            return;
+        }
+
+        let text = source
+            .text()
+            .slice(primary.char_range_in(source.text()))
+            .to_string();
+        let matches = search::find_all(source.text(), &text);
+
+        // the first match after the primary selection that isn't selected yet,
+        // wrapping around to the start
+        let after = matches
+            .iter()
+            .position(|found| found.start >= primary.end)
+            .unwrap_or(0);
+        let next = matches[after..]
+            .iter()
+            .chain(&matches[..after])
+            .find(|found| !self.ranges.iter().any(|range| range.ordered() == **found));
+
+        if let Some(next) = next {
+            let mut ranges = self.ranges.clone();
+            ranges.push(*next);
+            let primary = ranges.len() - 1;
+            self.set_all_user(ranges, primary, source);
+        }
+    }
+
+    /// Add a selection on the line above or below the primary one, with the same columns.
+    /// If there already is one (from adding in the other direction), the primary is removed instead.
+    pub fn add_line_selection(&mut self, direction: VDir, source: &mut Source) {
+        let primary = self.selection();
+        let line = match direction {
+            VDir::Up if primary.start.line > 0 && primary.end.line > 0 => primary.start.line - 1,
+            VDir::Down if primary.end.line + 1 < source.text().len_lines() => primary.end.line + 1,
+            _ => return,
+        };
+
+        // only single line selections line up in a column
+        if primary.start.line != primary.end.line {
+            return;
+        }
+
+        // keep the columns the same when going past shorter lines
+        let (start_col, end_col) = self
+            .line_columns
+            .unwrap_or((primary.start.col, primary.end.col));
+
+        let mut ranges = self.ranges.clone();
+        if let Some(existing) = ranges.iter().position(|range| range.start.line == line) {
+            ranges.remove(self.primary);
+            let primary = if existing > self.primary {
+                existing - 1
+            } else {
+                existing
+            };
+            self.set_all_user(ranges, primary, source);
+        } else {
+            ranges.push(TextRange::new(
+                TextPoint::new(line, source.text().clamp_col(line, start_col)),
+                TextPoint::new(line, source.text().clamp_col(line, end_col)),
+            ));
+            let primary = ranges.len() - 1;
+            self.set_all_user(ranges, primary, source);
+        }
+        self.line_columns = Some((start_col, end_col));
+    }
+
+    /* ---------------------------- Syntax Selection ---------------------------- */
+    /// Grow the primary selection to the smallest syntax node around it,
+    /// or to the block around it once the nodes get bigger than the block.
+    pub fn grow_selection(&mut self, blocks: &[Block], source: &mut Source) {
+        let selection = self.selection().ordered();
+        let node = source
+            .get_tree_cursor()
+            .and_then(|cursor| node_around(cursor.node(), selection, source.text()));
+        let block = block_around(blocks, selection, source.text());
+
+        let grown = match (node, block) {
+            (Some(node), Some(block)) if !range_contains(block, node) => block,
+            (Some(node), _) => node,
+            (None, Some(block)) => block,
+            (None, None) => return,
+        };
+
+        // setting the selection clears the history, so keep it around
+        let mut history = std::mem::take(&mut self.grow_history);
+        history.push(self.selection());
+        self.set_selection_user(grown, source);
+        self.grow_history = history;
+    }
+
+    /// Go back to the selection before the last `grow_selection`
+    pub fn shrink_selection(&mut self, source: &mut Source) {
+        let mut history = std::mem::take(&mut self.grow_history);
+        if let Some(previous) = history.pop() {
+            self.set_selection_user(previous, source);
+            self.grow_history = history;
+        }
     }
 
     /* ------------------------------ Mouse Clicks ------------------------------ */
@@ -103,6 +326,127 @@ impl Selections {
         self.set_selection_user(TextRange::new_cursor(text_pos), source);
     }
 
+    /// Add a cursor where the mouse was clicked, or remove the cursor that is already there
+    pub fn mouse_clicked_adding(
+        &mut self,
+        pos: Pos2,
+        padding: &Padding,
+        source: &mut Source,
+        font: &MonospaceFont,
+    ) {
+        let text_pos = pt_to_text_coord(pos, padding, source.text(), font);
+        let mut ranges = self.ranges.clone();
+
+        let existing = ranges
+            .iter()
+            .position(|range| *range == TextRange::new_cursor(text_pos));
+        match existing {
+            Some(existing) if self.is_multiple() => {
+                ranges.remove(existing);
+                let primary = ranges.len() - 1;
+                self.set_all_user(ranges, primary, source);
+            }
+            _ => {
+                ranges.push(TextRange::new_cursor(text_pos));
+                let primary = ranges.len() - 1;
+                self.set_all_user(ranges, primary, source);
+            }
+        }
+    }
+
+    /// Start a column selection where the mouse was pressed
+    pub fn start_column_selection(
+        &mut self,
+        pos: Pos2,
+        padding: &Padding,
+        source: &mut Source,
+        font: &MonospaceFont,
+    ) {
+        let point = Self::column_point(pos, padding, source, font);
+        let column = ColumnSelection {
+            anchor: point,
+            head: point,
+            tab_width: font.tab_width,
+        };
+        self.set_column(column, source);
+        source.external_cursor_move();
+    }
+
+    /// Drag the corner of the column selection to the mouse position
+    pub fn expand_column_selection(
+        &mut self,
+        pos: Pos2,
+        padding: &Padding,
+        source: &Source,
+        font: &MonospaceFont,
+    ) {
+        let Some(column) = self.column else {
+            return;
+        };
+        let head = Self::column_point(pos, padding, source, font);
+        if head != column.head {
+            self.set_column(ColumnSelection { head, ..column }, source);
+        }
+    }
+
+    /// Update the selections of the column selection for the current text
+    pub fn refresh_column(&mut self, source: &Source) {
+        if let Some(column) = self.column {
+            self.set_column(column, source);
+        }
+    }
+
+    /// The line and visual column under the mouse (which can be past the end of the line)
+    fn column_point(
+        pos: Pos2,
+        padding: &Padding,
+        source: &Source,
+        font: &MonospaceFont,
+    ) -> TextPoint {
+        let line = pt_to_text_coord(pos, padding, source.text(), font).line;
+        let col = pt_to_unbounded_text_coord(pos, padding, font).col;
+        TextPoint::new(line, col)
+    }
+
+    /// Select each line of the column selection, with the primary selection on the head's line
+    pub fn set_column(&mut self, column: ColumnSelection, source: &Source) {
+        let primary = column.head.line - column.lines().start();
+        self.set_all(column.ranges(source.text()), primary, source);
+        self.column = Some(column);
+    }
+
+    /// Select the word that was double clicked
+    pub fn mouse_double_clicked(
+        &mut self,
+        pos: Pos2,
+        padding: &Padding,
+        source: &mut Source,
+        font: &MonospaceFont,
+    ) {
+        let text_pos = pt_to_text_coord(pos, padding, source.text(), font);
+        if let Some(word) = TextRange::word_around(text_pos, source.text()) {
+            self.set_selection_user(word, source);
+        }
+    }
+
+    /// Select the line that was triple clicked (including its newline)
+    pub fn mouse_triple_clicked(
+        &mut self,
+        pos: Pos2,
+        padding: &Padding,
+        source: &mut Source,
+        font: &MonospaceFont,
+    ) {
+        let line = pt_to_text_coord(pos, padding, source.text(), font).line;
+        let text = source.text();
+        let end = if line + 1 < text.len_lines() {
+            TextPoint::new(line + 1, 0)
+        } else {
+            TextPoint::new(line, text.line(line).len_chars())
+        };
+        self.set_selection_user(TextRange::new(TextPoint::new(line, 0), end), source);
+    }
+
     pub fn expand_selection(
         &mut self,
         pos: Pos2,
@@ -111,10 +455,13 @@ impl Selections {
         font: &MonospaceFont,
     ) {
         // set selection end to dragged position
-        self.selection.end = pt_to_text_coord(pos, padding, source.text(), font);
+        self.column = None;
+        self.grow_history.clear();
+        let primary = &mut self.ranges[self.primary];
+        primary.end = pt_to_text_coord(pos, padding, source.text(), font);
 
         // clear pseudo selection if making a selection
-        if !self.selection.is_cursor() {
+        if !primary.is_cursor() {
             self.pseudo_selection = None;
         }
 
@@ -122,3 +469,158 @@ impl Selections {
         self.last_selection_time = self.frame_start_time;
     }
 }
+
+/// If the range `outer` contains all of `inner`
+fn range_contains(outer: TextRange, inner: TextRange) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+/// The smallest named node that contains the selection and is bigger than it
+fn node_around(root: Node, selection: TextRange, source: &Rope) -> Option<TextRange> {
+    let bytes = selection.byte_range_in(source);
+    let mut node = root.named_descendant_for_byte_range(bytes.start, bytes.end)?;
+    loop {
+        let range = TextRange::from_char_range_in(
+            source,
+            source.byte_to_char(node.start_byte())..source.byte_to_char(node.end_byte()),
+        );
+        if range != selection {
+            return Some(range);
+        }
+        node = node.parent()?;
+    }
+}
+
+/// The smallest block that contains the selection and is bigger than it
+fn block_around(blocks: &[Block], selection: TextRange, source: &Rope) -> Option<TextRange> {
+    let mut around = None;
+    let mut level = blocks;
+    'outer: loop {
+        for block in level {
+            // the last block in the file can end past its last line
+            let mut range = block.text_range();
+            if range.end.line >= source.len_lines() {
+                let last_line = source.len_lines() - 1;
+                range.end = TextPoint::new(last_line, source.line(last_line).len_chars());
+            }
+
+            if range_contains(range, selection) {
+                // dividers are walked through but not selected
+                if block.syntax_type != BlockType::Divider && range != selection {
+                    around = Some(range);
+                }
+                level = &block.children;
+                continue 'outer;
+            }
+        }
+        return around;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+
+    use super::*;
+    use crate::{
+        block_editor::blocks::BlockTrees,
+        lang::{config::LanguageConfig, Language},
+    };
+
+    fn range(start: (usize, usize), end: (usize, usize)) -> TextRange {
+        TextRange::new(
+            TextPoint::new(start.0, start.1),
+            TextPoint::new(end.0, end.1),
+        )
+    }
+
+    fn source(text: &str) -> Source {
+        let lang = Language::new(LanguageConfig::for_file("test.py")).unwrap();
+        Source::new(Rope::from_str(text), lang)
+    }
+
+    #[test]
+    fn adds_next_occurrences() {
+        let mut source = source("ab = ab + 1\nab\n");
+        let mut selections = Selections::new();
+        selections.set_selection(range((0, 6), (0, 6)), &source);
+
+        // the first selects the word, the rest add the next match (wrapping around)
+        selections.add_next_occurrence(&mut source);
+        assert_eq!(selections.all(), [range((0, 5), (0, 7))]);
+        selections.add_next_occurrence(&mut source);
+        selections.add_next_occurrence(&mut source);
+        assert_eq!(
+            selections.all(),
+            [
+                range((0, 0), (0, 2)),
+                range((0, 5), (0, 7)),
+                range((1, 0), (1, 2))
+            ]
+        );
+        assert_eq!(selections.selection(), range((0, 0), (0, 2)));
+    }
+
+    #[test]
+    fn grows_and_shrinks_selections() {
+        let mut source = source("def f():\n    return a + b\n");
+        let mut selections = Selections::new();
+        selections.set_selection(range((1, 11), (1, 11)), &source);
+
+        selections.grow_selection(&[], &mut source);
+        assert_eq!(selections.selection(), range((1, 11), (1, 12)));
+        selections.grow_selection(&[], &mut source);
+        assert_eq!(selections.selection(), range((1, 11), (1, 16)));
+
+        selections.shrink_selection(&mut source);
+        selections.shrink_selection(&mut source);
+        assert_eq!(selections.selection(), range((1, 11), (1, 11)));
+    }
+
+    #[test]
+    fn grows_to_blocks() {
+        let mut source = source("def f():\n    if a:\n        b = 1\n    c = 2\n");
+        let blocks = {
+            let mut cursor = source.get_tree_cursor().unwrap();
+            BlockTrees::for_ts_tree(&mut cursor, source.text(), source.lang.config)
+        };
+        let mut selections = Selections::new();
+        selections.set_selection(range((2, 8), (2, 13)), &source);
+
+        // from the statement to its block, then the enclosing if block
+        selections.grow_selection(blocks.trees(), &mut source);
+        assert_eq!(selections.selection(), range((2, 8), (3, 0)));
+        selections.grow_selection(blocks.trees(), &mut source);
+        assert_eq!(selections.selection(), range((1, 4), (3, 0)));
+
+        selections.shrink_selection(&mut source);
+        selections.shrink_selection(&mut source);
+        assert_eq!(selections.selection(), range((2, 8), (2, 13)));
+    }
+
+    #[test]
+    fn adds_line_selections() {
+        let mut source = source("abc\na\nabc\n");
+        let mut selections = Selections::new();
+        selections.set_selection(range((0, 1), (0, 3)), &source);
+
+        // columns are clamped to short lines
+        selections.add_line_selection(VDir::Down, &mut source);
+        selections.add_line_selection(VDir::Down, &mut source);
+        assert_eq!(
+            selections.all(),
+            [
+                range((0, 1), (0, 3)),
+                range((1, 1), (1, 1)),
+                range((2, 1), (2, 3))
+            ]
+        );
+
+        // going back removes them
+        selections.add_line_selection(VDir::Up, &mut source);
+        assert_eq!(
+            selections.all(),
+            [range((0, 1), (0, 3)), range((1, 1), (1, 1))]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::ops::RangeInclusive;
+
+use ropey::Rope;
+
+use crate::block_editor::{
+    rope_ext::RopeSliceExt,
+    text_range::{TextPoint, TextRange},
+};
+
+/// A rectangle of text, selected by dragging with shift and option held.
+/// The columns are where the text is drawn (with tabs expanded), so they line up visually
+/// and can go past the end of shorter lines.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ColumnSelection {
+    /// the line and visual column where the selection started
+    pub anchor: TextPoint,
+
+    /// the line and visual column where the selection is being dragged to
+    pub head: TextPoint,
+
+    /// the width of a tab when the selection was made
+    pub tab_width: usize,
+}
+
+impl ColumnSelection {
+    pub fn lines(&self) -> RangeInclusive<usize> {
+        self.anchor.line.min(self.head.line)..=self.anchor.line.max(self.head.line)
+    }
+
+    /// The visual column of the left side
+    pub fn left(&self) -> usize {
+        self.anchor.col.min(self.head.col)
+    }
+
+    /// The visual column of the right side
+    pub fn right(&self) -> usize {
+        self.anchor.col.max(self.head.col)
+    }
+
+    /// The visual column a line ends at
+    fn visual_len(&self, line: usize, source: &Rope) -> usize {
+        let line = source.line(line);
+        line.visual_col(line.len_chars_no_linebreak(), self.tab_width)
+    }
+
+    /// If the line ends before the rectangle, so nothing on it is selected.
+    /// (When the rectangle has no width, a line ending right at it still has a cursor.)
+    pub fn is_past_end(&self, line: usize, source: &Rope) -> bool {
+        let visual_len = self.visual_len(line, source);
+        if self.left() == self.right() {
+            visual_len < self.left()
+        } else {
+            visual_len <= self.left()
+        }
+    }
+
+    /// How many spaces the line needs to reach the left side of the rectangle
+    pub fn padding_needed(&self, line: usize, source: &Rope) -> usize {
+        self.left().saturating_sub(self.visual_len(line, source))
+    }
+
+    /// The text selected on each line (clamped to the end of the line),
+    /// ending on the same side as the head
+    pub fn ranges(&self, source: &Rope) -> Vec<TextRange> {
+        self.lines()
+            .map(|line_num| {
+                let line = source.line(line_num);
+                let len = line.len_chars_no_linebreak();
+                let col_at = |visual: usize| line.col_at_visual(visual, self.tab_width).min(len);
+
+                let start = TextPoint::new(line_num, col_at(self.anchor.col));
+                let end = TextPoint::new(line_num, col_at(self.head.col));
+                TextRange::new(start, end)
+            })
+            .collect()
+    }
+}
@@ -3,48 +3,80 @@ use std::cmp::max;
 use egui::{Color32, Painter, Pos2, Rect, Stroke, Ui, Vec2};
 use ropey::Rope;
 
-use super::{Selections, CURSOR_OFF_DURATION, CURSOR_ON_DURATION};
+use super::{ColumnSelection, Selections, CURSOR_OFF_DURATION, CURSOR_ON_DURATION};
 use crate::{
     block_editor::{
-        blocks::Padding, text_editor::TextPoint, text_range::TextRange, MonospaceFont, OUTER_PAD,
-        TOTAL_TEXT_X_OFFSET,
+        blocks::Padding, rope_ext::RopeSliceExt, text_editor::TextPoint, text_range::TextRange,
+        MonospaceFont, OUTER_PAD, TOTAL_TEXT_X_OFFSET,
     },
     theme,
 };
 
 impl Selections {
-    /// Draws the cursor at the current position and returns the rect of the cursor.
+    /// Draws a cursor at the end of every selection and returns the rect of the primary cursor.
     pub fn draw_cursor(
         &self,
         offset: Vec2,
         padding: &Padding,
+        source: &Rope,
         font: &MonospaceFont,
         ui: &Ui,
     ) -> Rect {
         // we want to draw the cursor where the mouse has last been (selection end)
-        let total_pad: f32 = padding.cumulative(self.selection.end.line);
-        let block = Rect::from_min_size(
-            Pos2::new(
-                TOTAL_TEXT_X_OFFSET + (self.selection.end.col as f32) * font.size.x,
-                OUTER_PAD + (self.selection.end.line as f32) * font.size.y + total_pad,
-            ) + offset,
-            Vec2::new(2.0, font.size.y),
-        );
+        let visual_cursor_rect = |line: usize, visual_col: usize| {
+            let total_pad: f32 = padding.cumulative(line);
+            Rect::from_min_size(
+                Pos2::new(
+                    TOTAL_TEXT_X_OFFSET + (visual_col as f32) * font.size.x,
+                    OUTER_PAD + (line as f32) * font.size.y + total_pad,
+                ) + offset,
+                Vec2::new(2.0, font.size.y),
+            )
+        };
+        let cursor_rect = |point: TextPoint| {
+            let visual_col = source
+                .line(point.line)
+                .visual_col(point.col, font.tab_width);
+            visual_cursor_rect(point.line, visual_col)
+        };
+
+        // the cursors of a column selection line up, even past the end of short lines
+        if let Some(column) = self.column {
+            let rect = |line: usize| visual_cursor_rect(line, column.head.col);
+            return self.draw_blinking_cursors(
+                column.lines().map(rect),
+                rect(column.head.line),
+                ui,
+            );
+        }
 
+        let cursors = self.ranges.iter().map(|range| cursor_rect(range.end));
+        self.draw_blinking_cursors(cursors, cursor_rect(self.selection().end), ui)
+    }
+
+    /// Draws the cursors if they are in the visible part of their blink, and returns the primary rect
+    fn draw_blinking_cursors(
+        &self,
+        cursors: impl Iterator<Item = Rect>,
+        primary: Rect,
+        ui: &Ui,
+    ) -> Rect {
         let time_since_last_selection = self.frame_start_time - self.last_selection_time;
         let total_duration = CURSOR_ON_DURATION + CURSOR_OFF_DURATION;
         let time_in_cycle = time_since_last_selection % total_duration;
         let wake_in = if time_in_cycle < CURSOR_ON_DURATION {
-            // cursor is visible
-            ui.painter().rect_filled(block, 0.0, theme::CURSOR);
+            // cursors are visible (and blink together)
+            for cursor in cursors {
+                ui.painter().rect_filled(cursor, 0.0, theme::CURSOR);
+            }
             CURSOR_ON_DURATION - time_in_cycle
         } else {
-            // cursor is not visible
+            // cursors are not visible
             total_duration - time_in_cycle
         };
         ui.ctx().request_repaint_after_secs(wake_in as f32);
 
-        block
+        primary
     }
 
     pub fn draw_selection(
@@ -55,8 +87,13 @@ impl Selections {
         font: &MonospaceFont,
         painter: &Painter,
     ) {
-        if !self.selection.is_cursor() {
-            self.selection.draw_selection_blocks(
+        if let Some(column) = self.column {
+            Self::draw_column_selection(column, offset, padding, font, painter);
+            return;
+        }
+
+        for range in self.ranges.iter().filter(|range| !range.is_cursor()) {
+            range.draw_selection_blocks(
                 theme::SELECTION,
                 Stroke::NONE,
                 offset,
@@ -68,6 +105,37 @@ impl Selections {
         }
     }
 
+    /// Draw the rectangle of a column selection, including past the end of short lines
+    /// and across the padding between lines (so it stays a rectangle around blocks)
+    fn draw_column_selection(
+        column: ColumnSelection,
+        offset: Vec2,
+        padding: &Padding,
+        font: &MonospaceFont,
+        painter: &Painter,
+    ) {
+        let width = column.right() - column.left();
+        if width == 0 {
+            return;
+        }
+
+        let top = *column.lines().start();
+        for line in column.lines() {
+            TextRange::draw_selection_block(
+                TextPoint::new(line, column.left()),
+                width,
+                padding.cumulative(max(top, line.saturating_sub(1))),
+                line != top,
+                theme::SELECTION,
+                Stroke::NONE,
+                offset,
+                padding,
+                font,
+                painter,
+            );
+        }
+    }
+
     pub fn draw_pseudo_selection(
         &self,
         offset: Vec2,
@@ -108,17 +176,21 @@ impl TextRange {
             // one line per range so the line number is the start of the range
             let line_num = line_range.start.line;
 
-            // find width of selection block in chars
-            let width = line_range.end.col - line_range.start.col
-                + if line_num != selection.end.line { 1 } else { 0 }; // 1 is added to the width to include the newline
+            // find where the selection block is drawn (accounting for tabs)
+            let line = source.line(line_num);
+            let start_col = line.visual_col(line_range.start.col, font.tab_width);
+            let end_col = line.visual_col(line_range.end.col, font.tab_width);
+
+            // find width of selection block in columns
+            let width = end_col - start_col + if line_num != selection.end.line { 1 } else { 0 }; // 1 is added to the width to include the newline
 
             // start the padding through the first line so the selection
             // block is placed on the text of the first line (instead of the padding above it)
             let padding_above: f32 =
                 padding.cumulative(max(selection.start.line, line_num.saturating_sub(1)));
 
-            self.draw_selection_block(
-                TextPoint::new(line_num, line_range.start.col),
+            Self::draw_selection_block(
+                TextPoint::new(line_num, start_col),
                 width,
                 padding_above,
                 line_num != selection.start.line,
@@ -132,8 +204,9 @@ impl TextRange {
         }
     }
 
+    /// Draw the selection for a single line,
+    /// where the start column and width are where the text is drawn (with tabs expanded)
     fn draw_selection_block(
-        &self,
         start: TextPoint,
         width: usize,
         padding_above: f32,
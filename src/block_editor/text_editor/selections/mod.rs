@@ -1,18 +1,34 @@
 use crate::block_editor::text_range::TextRange;
 
 mod changing;
+mod column;
 mod drawer;
 
+pub use column::ColumnSelection;
+
 /// the interval during which the cursor is on during the blink cycle (in seconds)
 const CURSOR_ON_DURATION: f64 = 0.8;
 
 /// the interval during which the cursor is on during the blink cycle (in seconds)
 const CURSOR_OFF_DURATION: f64 = 0.4;
 
-/// The actual text selection and the associated pseudo selection
+/// The actual text selections and the associated pseudo selection
 pub struct Selections {
-    /// the currently selected text
-    selection: TextRange,
+    /// the currently selected text, one range per cursor, in the order they are in the text
+    ranges: Vec<TextRange>,
+
+    /// the index of the selection that was added last. Actions that only use one
+    /// selection (like completions and search) use it
+    primary: usize,
+
+    /// the columns of the selection that selections on the lines above or below were added from
+    line_columns: Option<(usize, usize)>,
+
+    /// the rectangle being selected, if the selections are a column selection
+    column: Option<ColumnSelection>,
+
+    /// the selections that the primary selection was grown from by `grow_selection`
+    grow_history: Vec<TextRange>,
 
     /// the frame that hitting backspace would delete
     pseudo_selection: Option<TextRange>,
@@ -27,15 +43,40 @@ pub struct Selections {
 impl Selections {
     pub fn new() -> Self {
         Selections {
-            selection: TextRange::ZERO,
+            ranges: vec![TextRange::ZERO],
+            primary: 0,
+            line_columns: None,
+            column: None,
+            grow_history: vec![],
             pseudo_selection: None,
             frame_start_time: 0.0,
             last_selection_time: 0.0,
         }
     }
 
+    /// The primary selection
     pub fn selection(&self) -> TextRange {
-        self.selection
+        self.ranges[self.primary]
+    }
+
+    /// Every selection, in the order they are in the text
+    pub fn all(&self) -> &[TextRange] {
+        &self.ranges
+    }
+
+    /// The index of the primary selection in `all`
+    pub fn primary_index(&self) -> usize {
+        self.primary
+    }
+
+    /// If there are multiple cursors
+    pub fn is_multiple(&self) -> bool {
+        self.ranges.len() > 1
+    }
+
+    /// The rectangle that is selected, if the selections are a column selection
+    pub fn column_selection(&self) -> Option<ColumnSelection> {
+        self.column
     }
 
     pub fn pseudo_selection(&self) -> Option<TextRange> {
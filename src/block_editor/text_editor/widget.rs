@@ -1,6 +1,6 @@
 use egui::{
     output::IMEOutput, scroll_area::ScrollBarVisibility, style::ScrollAnimation, CursorIcon, Event,
-    EventFilter, ImeEvent, Key, Modifiers, Rect, Response, ScrollArea, Sense, Ui, Vec2, Widget,
+    EventFilter, ImeEvent, Key, Rect, Response, ScrollArea, Sense, Ui, Vec2, Widget,
 };
 use std::{collections::HashSet, ops::RangeInclusive};
 
@@ -12,6 +12,9 @@ use super::{
 use crate::{
     block_editor::{
         blocks::BlockTrees,
+        commands::Command,
+        keymap,
+        rope_ext::RopeSliceExt,
         search::SearchResults,
         source::{Source, UndoStopCondition},
         text_range::{
@@ -57,6 +60,9 @@ impl TextEditor {
                     let sense = Sense::click_and_drag();
                     let mut response = ui.interact(rect, auto_id, sense);
                     ui.memory_mut(|mem| mem.set_focus_lock_filter(auto_id, EVENT_FILTER));
+                    if std::mem::take(&mut self.focus_requested) {
+                        response.request_focus();
+                    }
 
                     // find the offset to the content
                     let offset = rect.min.to_vec2();
@@ -67,6 +73,7 @@ impl TextEditor {
                     // handle interactions
                     let drop_point =
                         self.handle_pointer(ui, offset, &mut response, drag_block, source, font);
+                    self.show_block_menu(ui, offset, &response, source, font);
                     self.handle_external_commands(external_commands, source);
                     if response.has_focus() {
                         self.handle_input_events(source, ui);
@@ -74,6 +81,19 @@ impl TextEditor {
                     self.update_text_if_needed(source, &mut response);
                     // TODO: if the selection moved out of view, scroll to it
 
+                    // scroll to the selection if it was moved from outside (like going to a line)
+                    if std::mem::take(&mut self.scroll_to_selection) {
+                        let head = self.selections.selection().end;
+                        let pos =
+                            text_coord_to_pt(head, self.blocks.padding(), source.text(), font);
+                        let rect = Rect::from_min_size(pos + offset, font.size);
+                        ui.scroll_to_rect_animation(
+                            rect,
+                            Some(egui::Align::Center),
+                            ScrollAnimation::none(),
+                        );
+                    }
+
                     // set the selection to the current find result if it closed
                     if let Some(results) = search_results {
                         if results.will_clear_and_select() {
@@ -86,7 +106,12 @@ impl TextEditor {
                     if let Some(results) = search_results {
                         if results.check_and_clear_scroll_to_current() {
                             let current = results.current();
-                            let pos = text_coord_to_pt(current.start, self.blocks.padding(), font);
+                            let pos = text_coord_to_pt(
+                                current.start,
+                                self.blocks.padding(),
+                                source.text(),
+                                font,
+                            );
                             let rect = Rect::from_min_size(
                                 pos + offset,
                                 Vec2::new((current.end.col - current.start.col) as f32, 1.0)
@@ -254,6 +279,9 @@ impl TextEditor {
         );
         self.selections
             .draw_selection(offset, self.blocks.padding(), source.text(), font, painter);
+        if has_focus {
+            self.draw_matching_brackets(offset, source, font, painter);
+        }
 
         // find which lines are visible in the viewport
         let visible_lines = self.visible_lines(viewport, font);
@@ -265,6 +293,7 @@ impl TextEditor {
             block_offset,
             content_width - (block_padding.x + OUTER_PAD),
             Some(visible_lines.clone()),
+            source.text(),
             blocks_theme,
             font,
             painter,
@@ -281,7 +310,14 @@ impl TextEditor {
 
         // draw drag & drop insertion line
         if let Some(drop_point) = block_drop_point {
-            self.draw_dropping_line(drop_point, content_width, offset, font, painter);
+            self.draw_dropping_line(
+                drop_point,
+                content_width,
+                offset,
+                source.indent_style(),
+                font,
+                painter,
+            );
         }
 
         // draw diagnostic underlines
@@ -292,10 +328,13 @@ impl TextEditor {
 
         // draw cursor
         if has_focus {
-            Some(
-                self.selections
-                    .draw_cursor(offset, self.blocks.padding(), font, ui),
-            )
+            Some(self.selections.draw_cursor(
+                offset,
+                self.blocks.padding(),
+                source.text(),
+                font,
+                ui,
+            ))
         } else {
             None
         }
@@ -306,12 +345,16 @@ impl TextEditor {
             // mark response and changed
             response.mark_changed();
 
+            // the block that was right-clicked might not be there anymore
+            self.block_menu_range = None;
+
             // get blocks
-            {
-                let mut cursor = source.get_tree_cursor();
-                self.blocks =
-                    BlockTrees::for_ts_tree(&mut cursor, source.text(), source.lang.config);
-            }
+            self.blocks = match source.get_tree_cursor() {
+                Some(mut cursor) => {
+                    BlockTrees::for_ts_tree(&mut cursor, source.text(), source.lang.config)
+                }
+                None => BlockTrees::without_tree(source.text()),
+            };
 
             // highlight text
             self.text_drawer.highlight_source(source);
@@ -360,7 +403,9 @@ impl TextEditor {
         let mods = ui.input(|i| i.modifiers);
 
         if response.hovered() {
-            if mods.alt {
+            if mods.alt && mods.shift {
+                ui.ctx().set_cursor_icon(CursorIcon::Crosshair);
+            } else if mods.alt {
                 ui.ctx().set_cursor_icon(CursorIcon::Grab);
             } else {
                 ui.ctx().set_cursor_icon(CursorIcon::Text);
@@ -371,32 +416,66 @@ impl TextEditor {
             if let Some(pointer_pos) = ui.ctx().pointer_interact_pos() {
                 let pos = pointer_pos - offset;
 
+                // if option is held when dragging starts, remove the block where the drag
+                // started from the source and place it in drag_block
+                if response.drag_started() && mods.alt && !mods.shift && dragged_block.is_none() {
+                    if let Some(press_origin) = ui.input(|i| i.pointer.press_origin()) {
+                        let press_pos = press_origin - offset;
+                        if press_pos.x >= GUTTER_WIDTH {
+                            self.start_block_drag(press_pos, dragged_block, source, font);
+                        }
+                    }
+                }
+
                 // handle mouse down
                 let is_being_dragged = ui.ctx().is_being_dragged(response.id);
                 if is_being_dragged {
-                    if dragged_block.is_none() {
+                    if self.selections.column_selection().is_some() {
+                        self.selections.expand_column_selection(
+                            pos,
+                            self.blocks.padding(),
+                            source,
+                            font,
+                        );
+                    } else if dragged_block.is_none() && !mods.alt {
                         self.selections
                             .expand_selection(pos, self.blocks.padding(), source, font);
                     }
                 } else if ui.input(|i| i.pointer.primary_pressed()) && pos.x >= GUTTER_WIDTH {
-                    if mods.shift {
+                    if mods.shift && mods.alt {
+                        // shift and option select a column of text
+                        self.selections.start_column_selection(
+                            pos,
+                            self.blocks.padding(),
+                            source,
+                            font,
+                        );
+                    } else if mods.shift {
                         self.selections
                             .expand_selection(pos, self.blocks.padding(), source, font);
-                    } else {
-                        // if option is held, remove the current block from the source and place it in drag_block
-                        if mods.alt {
-                            if dragged_block.is_none() {
-                                self.start_block_drag(pos, dragged_block, source, font);
-                            }
-                        } else {
-                            self.selections
-                                .mouse_clicked(pos, self.blocks.padding(), source, font);
-                        }
+                    } else if !mods.alt {
+                        self.selections
+                            .mouse_clicked(pos, self.blocks.padding(), source, font);
                     }
                     self.completion_popup.clear();
                     response.request_focus();
                 }
 
+                // double clicking selects a word, and triple clicking selects a line
+                if response.double_clicked() && !mods.alt {
+                    self.selections
+                        .mouse_double_clicked(pos, self.blocks.padding(), source, font);
+                } else if response.triple_clicked() && !mods.alt {
+                    self.selections
+                        .mouse_triple_clicked(pos, self.blocks.padding(), source, font);
+                }
+
+                // if option is held and the mouse is released without dragging, add a cursor
+                if response.clicked() && mods.alt && !mods.shift && pos.x >= GUTTER_WIDTH {
+                    self.selections
+                        .mouse_clicked_adding(pos, self.blocks.padding(), source, font);
+                }
+
                 // handle mouse up
                 if dragged_block.is_some() {
                     let mouse_released = ui.input(|i| i.pointer.primary_released());
@@ -488,23 +567,11 @@ impl TextEditor {
         for event in &events {
             match event {
                 Event::Copy => {
-                    let char_range = self
-                        .selections
-                        .selection()
-                        .ordered()
-                        .char_range_in(source.text());
-                    let selected_text = source.text().slice(char_range).to_string();
-                    ui.ctx().copy_text(selected_text);
+                    ui.ctx().copy_text(self.selected_text(source));
                 }
 
                 Event::Cut => {
-                    let char_range = self
-                        .selections
-                        .selection()
-                        .ordered()
-                        .char_range_in(source.text());
-                    let selected_text = source.text().slice(char_range).to_string();
-                    ui.ctx().copy_text(selected_text);
+                    ui.ctx().copy_text(self.selected_text(source));
 
                     source.insert_str("", &mut self.selections);
                 }
@@ -525,8 +592,8 @@ impl TextEditor {
                     pressed: true,
                     ..
                 } => {
-                    if !self.handle_selection_modifying_keypress(modifiers, *key, source) {
-                        self.handle_text_modifying_keypress(modifiers, *key, source);
+                    if let Some(command) = keymap::command_for(*key, *modifiers) {
+                        self.run_command(command, source);
                     }
                 }
 
@@ -549,6 +616,10 @@ impl TextEditor {
                 ExternalCommand::ApplyEdit(edit) => {
                     source.apply_edit(edit, UndoStopCondition::Always, true, &mut self.selections);
                 }
+                ExternalCommand::SetBracketColorization(enabled) => {
+                    self.text_drawer.set_bracket_colorization(*enabled);
+                    self.text_drawer.highlight_source(source);
+                }
                 ExternalCommand::SetDiagnostics(new_diagnostics) => {
                     self.diagnostics = new_diagnostics.clone();
                     self.diagnostic_popup.clear_fixes();
@@ -606,103 +677,6 @@ impl TextEditor {
         });
     }
 
-    fn handle_text_modifying_keypress(
-        &mut self,
-        modifiers: &Modifiers,
-        key: Key,
-        source: &mut Source,
-    ) {
-        match key {
-            // Basic actions
-            Key::Enter => {
-                if self.completion_popup.has_completions() {
-                    self.completion_popup.trigger_completion();
-                } else {
-                    source.insert_newline(&mut self.selections);
-                    self.completion_popup.clear();
-                }
-            }
-            Key::Tab => {
-                if self.completion_popup.has_completions() {
-                    self.completion_popup.trigger_completion();
-                } else {
-                    if modifiers.shift {
-                        source.unindent(&mut self.selections);
-                    } else {
-                        source.indent(&mut self.selections)
-                    };
-                    self.completion_popup.clear();
-                }
-            }
-            Key::Backspace => {
-                let movement = if modifiers.mac_cmd {
-                    TextMovement::horizontal(HUnit::Line, HDir::Left)
-                } else if modifiers.alt || modifiers.ctrl {
-                    // alt on mac, ctrl on windows
-                    TextMovement::horizontal(HUnit::Word, HDir::Left)
-                } else {
-                    TextMovement::horizontal(HUnit::Grapheme, HDir::Left)
-                };
-                source.delete(movement, &mut self.selections);
-
-                self.completion_popup
-                    .request_completions(source.text(), self.selections.selection())
-            }
-            Key::Delete => {
-                let movement = if modifiers.mac_cmd {
-                    TextMovement::horizontal(HUnit::Line, HDir::Right)
-                } else if modifiers.alt || modifiers.ctrl {
-                    // alt on mac, ctrl on windows
-                    TextMovement::horizontal(HUnit::Word, HDir::Right)
-                } else {
-                    TextMovement::horizontal(HUnit::Grapheme, HDir::Right)
-                };
-                source.delete(movement, &mut self.selections);
-                self.completion_popup
-                    .request_completions(source.text(), self.selections.selection())
-            }
-
-            // Undo/Redo
-            Key::Z if modifiers.matches_logically(Modifiers::COMMAND) => {
-                if modifiers.shift {
-                    source.redo(&mut self.selections);
-                } else {
-                    source.undo(&mut self.selections);
-                }
-                self.completion_popup.clear();
-            }
-            Key::Y if modifiers.matches_logically(Modifiers::COMMAND) => {
-                source.redo(&mut self.selections);
-                self.completion_popup.clear();
-            }
-
-            // Control hotkeys
-            Key::H if modifiers.ctrl => {
-                source.delete(
-                    TextMovement::horizontal(HUnit::Grapheme, HDir::Left),
-                    &mut self.selections,
-                );
-                self.completion_popup.clear();
-            }
-            Key::D if modifiers.ctrl => {
-                source.delete(
-                    TextMovement::horizontal(HUnit::Grapheme, HDir::Right),
-                    &mut self.selections,
-                );
-                self.completion_popup.clear();
-            }
-            Key::K if modifiers.ctrl => {
-                source.delete(
-                    TextMovement::horizontal(HUnit::Line, HDir::Right),
-                    &mut self.selections,
-                );
-                self.completion_popup.clear();
-            }
-
-            _ => {}
-        }
-    }
-
     fn handle_ime(&mut self, ime_event: &ImeEvent, source: &mut Source) {
         match ime_event {
             ImeEvent::Enabled => {
@@ -747,14 +721,93 @@ impl TextEditor {
         }
     }
 
-    pub fn handle_selection_modifying_keypress(
-        &mut self,
-        modifiers: &Modifiers,
-        key: Key,
-        source: &mut Source,
-    ) -> bool {
-        match key {
-            Key::A if modifiers.command => {
+    /// Run a command from the keymap
+    pub fn run_command(&mut self, command: Command, source: &mut Source) {
+        let has_completions = self.completion_popup.has_completions();
+        match command {
+            // handled by the block editor
+            Command::Find | Command::ShowAllCommands | Command::GoToLine => {}
+
+            // editing
+            Command::Undo => source.undo(&mut self.selections),
+            Command::Redo => source.redo(&mut self.selections),
+            Command::Newline | Command::Indent if has_completions => {
+                self.completion_popup.trigger_completion();
+                return;
+            }
+            Command::Newline => source.insert_newline(&mut self.selections),
+            Command::Indent => source.indent(&mut self.selections),
+            Command::Unindent => source.unindent(&mut self.selections),
+            Command::DeleteLeft
+            | Command::DeleteRight
+            | Command::DeleteWordLeft
+            | Command::DeleteWordRight
+            | Command::DeleteLineLeft
+            | Command::DeleteLineRight => {
+                let (unit, direction) = match command {
+                    Command::DeleteLeft => (HUnit::Grapheme, HDir::Left),
+                    Command::DeleteRight => (HUnit::Grapheme, HDir::Right),
+                    Command::DeleteWordLeft => (HUnit::Word, HDir::Left),
+                    Command::DeleteWordRight => (HUnit::Word, HDir::Right),
+                    Command::DeleteLineLeft => (HUnit::Line, HDir::Left),
+                    _ => (HUnit::Line, HDir::Right),
+                };
+                source.delete(
+                    TextMovement::horizontal(unit, direction),
+                    &mut self.selections,
+                );
+
+                // keep completing the word being typed
+                self.completion_popup
+                    .request_completions(source.text(), self.selections.selection());
+                return;
+            }
+            Command::ToggleComment => source.toggle_comment(&mut self.selections),
+
+            // cursor movement and selection
+            Command::CursorUp | Command::SelectUp if has_completions => {
+                self.completion_popup.select_prev();
+                return;
+            }
+            Command::CursorDown | Command::SelectDown if has_completions => {
+                self.completion_popup.select_next();
+                return;
+            }
+            Command::CursorDocumentStart
+            | Command::CursorDocumentEnd
+            | Command::SelectDocumentStart
+            | Command::SelectDocumentEnd
+                if has_completions =>
+            {
+                return;
+            }
+            Command::CursorLeft
+            | Command::CursorRight
+            | Command::CursorUp
+            | Command::CursorDown
+            | Command::CursorWordLeft
+            | Command::CursorWordRight
+            | Command::CursorLineStart
+            | Command::CursorLineEnd
+            | Command::CursorDocumentStart
+            | Command::CursorDocumentEnd => {
+                let movement = Self::command_movement(command);
+                self.selections.move_cursor(movement, source);
+            }
+            Command::SelectLeft
+            | Command::SelectRight
+            | Command::SelectUp
+            | Command::SelectDown
+            | Command::SelectWordLeft
+            | Command::SelectWordRight
+            | Command::SelectLineStart
+            | Command::SelectLineEnd
+            | Command::SelectDocumentStart
+            | Command::SelectDocumentEnd => {
+                let movement = Self::command_movement(command);
+                self.selections.move_selecting(movement, source);
+            }
+            Command::SelectAll => {
                 self.selections.set_selection(
                     TextRange::ZERO.expanded_by(
                         TextMovement::vertical(VUnit::Document, VDir::Down),
@@ -762,90 +815,78 @@ impl TextEditor {
                     ),
                     source,
                 );
-                self.completion_popup.clear();
-                true
             }
-
-            Key::ArrowLeft | Key::ArrowRight => {
-                let direction = match key {
-                    Key::ArrowLeft => HDir::Left,
-                    Key::ArrowRight => HDir::Right,
-                    _ => unreachable!(),
-                };
-
-                let unit = if modifiers.command {
-                    HUnit::Line
-                } else if modifiers.alt {
-                    HUnit::Word
-                } else {
-                    HUnit::Grapheme
-                };
-
-                let movement = TextMovement::horizontal(unit, direction);
-
-                if modifiers.shift {
-                    self.selections.move_selecting(movement, source);
-                } else {
-                    self.selections.move_cursor(movement, source);
+            Command::GrowSelection => self.selections.grow_selection(self.blocks.trees(), source),
+            Command::ShrinkSelection => self.selections.shrink_selection(source),
+            Command::JumpToBracket => self.jump_to_matching_bracket(false, source),
+            Command::SelectToBracket => self.jump_to_matching_bracket(true, source),
+
+            // multiple cursors
+            Command::AddNextOccurrence => self.selections.add_next_occurrence(source),
+            Command::AddCursorAbove => self.selections.add_line_selection(VDir::Up, source),
+            Command::AddCursorBelow => self.selections.add_line_selection(VDir::Down, source),
+            Command::Cancel => {
+                if !has_completions {
+                    self.selections.remove_extra(source);
                 }
-
-                self.completion_popup.clear();
-
-                true
             }
+        }
+        self.completion_popup.clear();
+    }
 
-            Key::ArrowUp | Key::ArrowDown | Key::Home | Key::End => {
-                if self.completion_popup.has_completions() {
-                    if key == Key::ArrowUp {
-                        self.completion_popup.select_prev();
-                    } else if key == Key::ArrowDown {
-                        self.completion_popup.select_next();
-                    }
-                } else {
-                    let direction = match key {
-                        Key::ArrowUp => VDir::Up,
-                        Key::ArrowDown => VDir::Down,
-                        Key::Home => VDir::Up,
-                        Key::End => VDir::Down,
-                        _ => unreachable!(),
-                    };
-
-                    let unit = if modifiers.command || Key::Home == key || Key::End == key {
-                        VUnit::Document
-                    } else {
-                        VUnit::Line
-                    };
-
-                    let movement = TextMovement::vertical(unit, direction);
-
-                    if modifiers.shift {
-                        self.selections.move_selecting(movement, source);
-                    } else {
-                        self.selections.move_cursor(movement, source);
-                    }
-
-                    self.completion_popup.clear();
-                }
-
-                true
+    /// The movement of a cursor movement or selection command
+    fn command_movement(command: Command) -> TextMovement {
+        match command {
+            Command::CursorLeft | Command::SelectLeft => {
+                TextMovement::horizontal(HUnit::Grapheme, HDir::Left)
             }
-
-            Key::Escape => {
-                self.completion_popup.clear();
-                true
+            Command::CursorRight | Command::SelectRight => {
+                TextMovement::horizontal(HUnit::Grapheme, HDir::Right)
             }
-
-            _ => false,
+            Command::CursorWordLeft | Command::SelectWordLeft => {
+                TextMovement::horizontal(HUnit::Word, HDir::Left)
+            }
+            Command::CursorWordRight | Command::SelectWordRight => {
+                TextMovement::horizontal(HUnit::Word, HDir::Right)
+            }
+            Command::CursorLineStart | Command::SelectLineStart => {
+                TextMovement::horizontal(HUnit::Line, HDir::Left)
+            }
+            Command::CursorLineEnd | Command::SelectLineEnd => {
+                TextMovement::horizontal(HUnit::Line, HDir::Right)
+            }
+            Command::CursorUp | Command::SelectUp => TextMovement::vertical(VUnit::Line, VDir::Up),
+            Command::CursorDown | Command::SelectDown => {
+                TextMovement::vertical(VUnit::Line, VDir::Down)
+            }
+            Command::CursorDocumentStart | Command::SelectDocumentStart => {
+                TextMovement::vertical(VUnit::Document, VDir::Up)
+            }
+            _ => TextMovement::vertical(VUnit::Document, VDir::Down),
         }
     }
 
+    /// Take the keyboard focus on the next frame (like after using the command palette)
+    pub fn request_focus(&mut self) {
+        self.focus_requested = true;
+    }
+
+    /// Move the cursor to the start of a line (counting from 1), and scroll to it
+    pub fn go_to_line(&mut self, line: usize, source: &mut Source) {
+        let line = line.clamp(1, source.text().len_lines()) - 1;
+        self.selections
+            .set_selection(TextRange::new_cursor(TextPoint::new(line, 0)), source);
+        source.external_cursor_move();
+        self.scroll_to_selection = true;
+    }
+
     /* --------------------------------- helpers -------------------------------- */
     fn content_size(&self, source: &Source, viewport: Rect, font: &MonospaceFont) -> Vec2 {
         // width is max between text and window
         let max_chars = source
             .text()
             .lines()
-            .map(|l| l.len_chars())
+            .map(|l| l.visual_col(l.len_chars(), font.tab_width))
             .max()
             .unwrap_or(0);
         let max_line_len =
@@ -861,6 +902,19 @@ impl TextEditor {
         Vec2::new(width, height)
     }
 
+    /// The text of the selections, on separate lines if there are multiple
+    fn selected_text(&self, source: &Source) -> String {
+        self.selections
+            .all()
+            .iter()
+            .map(|range| {
+                let char_range = range.ordered().char_range_in(source.text());
+                source.text().slice(char_range).to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     /// Detects if the mouse has been in the same spot for a certain amount of time (in seconds)
     fn mouse_still_for(duration: f32, ui: &Ui) -> bool {
         let time_since_last_move = ui.input(|i| i.pointer.time_since_last_movement());
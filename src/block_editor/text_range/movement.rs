@@ -119,6 +119,25 @@ impl TextRange {
         }
     }
 
+    /// The word (letters, numbers, and underscores) around a point, if it is next to one
+    pub fn word_around(point: TextPoint, source: &Rope) -> Option<TextRange> {
+        let is_word_char = |c: &char| c.is_alphanumeric() || *c == '_';
+        let line = source.line(point.line);
+        let before = line
+            .chars_at(point.col)
+            .reversed()
+            .take_while(is_word_char)
+            .count();
+        let after = line.chars_at(point.col).take_while(is_word_char).count();
+
+        (before + after > 0).then(|| {
+            TextRange::new(
+                TextPoint::new(point.line, point.col - before),
+                TextPoint::new(point.line, point.col + after),
+            )
+        })
+    }
+
     fn cursor_at_line_above(&self, source: &Rope) -> TextPoint {
         // when moving up, use top of selection
         let cursor_pos = self.ordered().start;
@@ -163,6 +163,13 @@ pub unsafe extern "C" fn iswalpha(c: c_int) -> bool {
     char::from_u32(c as u32).map_or(false, |c| c.is_alphabetic())
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn towupper(c: c_int) -> c_int {
+    char::from_u32(c as u32)
+        .and_then(|c| c.to_uppercase().next())
+        .map_or(c, |c| c as c_int)
+}
+
 /* --------------------------------- time.h --------------------------------- */
 
 #[no_mangle]
@@ -1,66 +1,427 @@
-use egui::Color32;
+use std::{collections::HashMap, fmt};
 
+use egui::Color32;
+use serde::Deserialize;
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Node, Query, QueryCursor, QueryError, QueryMatch, QueryPredicateArg};
+
+use super::{
+    grammar::Grammar,
+    highlighter::RopeProvider,
+    indents::{IndentsQuery, NewlineIndent},
+    registry,
+};
 use crate::{block_editor::BlockType, theme::syntax::*};
 
+/// A loaded language definition (see `src/lang/definitions`)
 pub struct LanguageConfig {
     /// Name of the language. Used as an ID and potentially for UI
-    pub name: &'static str,
+    pub name: String,
+
+    /// The compiled-in tree-sitter grammar to parse with.
+    /// Languages without one (like plain text) get no blocks or highlighting.
+    grammar: Option<Grammar>,
 
-    /// Tree-sitter language
-    ts_lang: tree_sitter_language::LanguageFn,
+    /// File extensions (without the dot) that open in this language
+    pub(super) extensions: Vec<String>,
 
-    /// Tree-sitter highlight query
-    pub(super) highlight_query: &'static str,
+    /// The character that starts a new scope (so should increase the indent), if any
+    pub new_scope_char: Option<NewScopeChar>,
 
-    /// The character that starts a new scope (so should increase the indent)
-    pub new_scope_char: NewScopeChar,
+    /// Query whose captures decide which nodes are drawn as blocks
+    blocks_query: Option<Query>,
 
-    /// Assigns a node a block type to draw
-    node_categorizer: fn(&tree_sitter::Node) -> Option<BlockType>,
+    /// What each capture of the blocks query does, indexed by capture index
+    block_captures: Vec<BlockCapture>,
 
-    /// The IDs for a string, and the start and end. Used for pseudo-selections
-    pub string_node_ids: StringNodeIDs,
+    /// Query whose captures decide how new lines are indented
+    indents_query: Option<IndentsQuery>,
+
+    /// The node kinds for a string, and the start and end. Used for pseudo-selections
+    pub string_nodes: Option<StringNodeKinds>,
+
+    /// Text that is closed automatically when typed, like brackets and quotes
+    pub auto_pairs: Vec<AutoPair>,
+
+    /// The syntax for comments, used for toggling them
+    pub comments: CommentTokens,
 
     /// Snippets to use for the palette. Must end with a newline.
-    pub palettes: &'static [Palette],
+    pub palettes: Vec<Palette>,
 
     /// The highlight names to recognize and their associated colors
     pub highlight: &'static [(&'static str, Color32)],
+
+    /// The definition, blocks query, and indents query the config was loaded from
+    /// (`None` for plain text), to tell if a definition is loaded again unchanged
+    source: Option<[String; 3]>,
+}
+
+/// The JSON part of a language definition
+#[derive(Deserialize)]
+struct Definition {
+    name: String,
+    #[serde(default)]
+    grammar: Option<Grammar>,
+    extensions: Vec<String>,
+    #[serde(default)]
+    new_scope_char: Option<NewScopeChar>,
+    #[serde(default)]
+    string_nodes: Option<StringNodeKinds>,
+    #[serde(default = "default_auto_pairs")]
+    auto_pairs: Vec<AutoPair>,
+    #[serde(default)]
+    comments: CommentTokens,
+    #[serde(default)]
+    palettes: Vec<Palette>,
+}
+
+/// The meaning of a capture name in a blocks query
+#[derive(Clone, Copy)]
+enum BlockCapture {
+    /// `@block.<type>`: draw the node as a block of that type
+    Block(BlockType),
+
+    /// `@block.none`: never draw the node as a block
+    Skip,
+
+    /// `@_<name>`: only used by predicates
+    Ignore,
 }
 
+/// Predicates in a blocks query other than the built-in tree-sitter ones
+const SINGLE_LINE_PREDICATE: &str = "single-line?";
+const NOT_SINGLE_LINE_PREDICATE: &str = "not-single-line?";
+
+/// The name of the language used for files that don't match any other language
+pub const PLAIN_TEXT: &str = "plain text";
+
 impl LanguageConfig {
     pub fn for_file(file_name: &str) -> &'static LanguageConfig {
-        match file_name.split('.').next_back() {
-            Some("py") => &PYTHON_LANGUAGE,
-            Some("java") => &JAVA_LANGUAGE,
-            Some("cpp") | Some("h") | Some("hpp") => &CPP_LANGUAGE,
-            Some("cs") => &CS_LANGUAGE,
-            Some("rs") => &RUST_LANGUAGE,
-            _ => &PYTHON_LANGUAGE, // TODO: plain text mode?
+        registry::for_file(file_name)
+    }
+
+    /// Load a language from its JSON definition and the sources of its blocks and indents queries.
+    /// The queries are ignored if the definition doesn't have a grammar.
+    pub fn load(
+        definition: &str,
+        blocks_query: &str,
+        indents_query: &str,
+    ) -> Result<Self, LoadError> {
+        let source = [definition, blocks_query, indents_query].map(str::to_string);
+        let definition: Definition = serde_json::from_str(definition).map_err(LoadError::Json)?;
+
+        // an empty opening would match everything typed
+        if definition
+            .auto_pairs
+            .iter()
+            .any(|pair| pair.open.is_empty())
+        {
+            return Err(LoadError::EmptyAutoPair);
+        }
+        if definition.comments.has_empty_token() {
+            return Err(LoadError::EmptyCommentToken);
+        }
+
+        let (blocks_query, block_captures) = match definition.grammar {
+            Some(grammar) => {
+                let (query, captures) = compile_blocks_query(grammar, blocks_query)?;
+                (Some(query), captures)
+            }
+            None => (None, vec![]),
+        };
+
+        let indents_query = match definition.grammar {
+            Some(grammar) => {
+                let source = resolve_inherits(indents_query, registry::built_in_indents_query)?;
+                let ts_lang = tree_sitter::Language::new(grammar.ts_lang());
+                Some(IndentsQuery::new(&ts_lang, &source)?)
+            }
+            None => None,
+        };
+
+        Ok(LanguageConfig {
+            name: definition.name,
+            grammar: definition.grammar,
+            extensions: definition.extensions,
+            new_scope_char: definition.new_scope_char,
+            blocks_query,
+            block_captures,
+            indents_query,
+            string_nodes: definition.string_nodes,
+            auto_pairs: definition.auto_pairs,
+            comments: definition.comments,
+            palettes: definition.palettes,
+            highlight: STANDARD_HIGHLIGHT,
+            source: Some(source),
+        })
+    }
+
+    /// The language for files that don't match any other language:
+    /// no grammar, no palettes, and no scopes
+    pub fn plain_text() -> Self {
+        LanguageConfig {
+            name: PLAIN_TEXT.to_string(),
+            grammar: None,
+            extensions: vec!["txt".to_string()],
+            new_scope_char: None,
+            blocks_query: None,
+            block_captures: vec![],
+            indents_query: None,
+            string_nodes: None,
+            auto_pairs: default_auto_pairs(),
+            comments: CommentTokens::default(),
+            palettes: vec![],
+            highlight: &[],
+            source: None,
+        }
+    }
+
+    /// If the config was loaded from the same definition and queries as another one
+    pub(super) fn is_same_definition(&self, other: &LanguageConfig) -> bool {
+        self.name == other.name && self.source.is_some() && self.source == other.source
+    }
+
+    pub fn tree_sitter(&self) -> Option<tree_sitter::Language> {
+        self.grammar
+            .map(|grammar| tree_sitter::Language::new(grammar.ts_lang()))
+    }
+
+    pub(super) fn highlight_query(&self) -> Option<&'static str> {
+        self.grammar.map(|grammar| grammar.highlight_query())
+    }
+
+    /// The query for finding other languages embedded in this one (empty if there are none)
+    pub(super) fn injection_query(&self) -> &'static str {
+        self.grammar.map_or("", |grammar| grammar.injection_query())
+    }
+
+    /// Find the block type of every node under `root` that should be drawn as a block,
+    /// keyed by node ID.
+    ///
+    /// If a node is captured by multiple patterns, the first pattern in the query wins.
+    pub fn categorize_tree(&self, root: Node, source: &ropey::Rope) -> HashMap<usize, BlockType> {
+        let Some(blocks_query) = &self.blocks_query else {
+            return HashMap::new();
+        };
+
+        // node ID -> (pattern index, block type)
+        let mut categories: HashMap<usize, (usize, Option<BlockType>)> = HashMap::new();
+
+        let mut cursor = QueryCursor::new();
+        let mut matches = cursor.matches(blocks_query, root, RopeProvider(source.slice(..)));
+        while let Some(query_match) = matches.next() {
+            if !satisfies_general_predicates(blocks_query, query_match) {
+                continue;
+            }
+
+            for capture in query_match.captures {
+                let block = match self.block_captures[capture.index as usize] {
+                    BlockCapture::Block(block) => Some(block),
+                    BlockCapture::Skip => None,
+                    BlockCapture::Ignore => continue,
+                };
+                let pattern = query_match.pattern_index;
+                categories
+                    .entry(capture.node.id())
+                    .and_modify(|existing| {
+                        if pattern < existing.0 {
+                            *existing = (pattern, block);
+                        }
+                    })
+                    .or_insert((pattern, block));
+            }
         }
+
+        categories
+            .into_iter()
+            .filter_map(|(id, (_, block))| Some((id, block?)))
+            .collect()
     }
 
-    pub fn tree_sitter(&self) -> tree_sitter::Language {
-        tree_sitter::Language::new(self.ts_lang)
+    /// Find how to indent a newline inserted at `byte`.
+    /// Languages without an indents query don't get anything from the tree.
+    pub fn newline_indent(
+        &'static self,
+        root: Node,
+        source: &ropey::Rope,
+        byte: usize,
+    ) -> NewlineIndent {
+        let Some(query) = &self.indents_query else {
+            return NewlineIndent::default();
+        };
+
+        let mut indent = query.newline_at(root, source, byte);
+        if indent.missing_close.is_none() {
+            if let Some(close) = self.unclosed_pair(root, source, byte) {
+                indent.opens_scope = true;
+                indent.missing_close = Some(close);
+            }
+        }
+        indent
     }
 
-    pub fn categorize_node(&self, node: &tree_sitter::Node) -> Option<BlockType> {
-        (self.node_categorizer)(node)
+    /// Find the closing text for a bracket right before `byte` that the parser couldn't
+    /// match because the rest of the file is an error (like `fn a() {` at the end of a file).
+    /// Grammars don't always insert a missing node for these.
+    fn unclosed_pair(
+        &'static self,
+        root: Node,
+        source: &ropey::Rope,
+        byte: usize,
+    ) -> Option<&'static str> {
+        let open = root.descendant_for_byte_range(byte.checked_sub(1)?, byte)?;
+        let error = open.parent().filter(|parent| parent.is_error())?;
+
+        let after_error = source.slice(source.byte_to_char(error.end_byte())..);
+        if !after_error.chars().all(char::is_whitespace) {
+            return None;
+        }
+
+        self.auto_pairs
+            .iter()
+            .find(|pair| !pair.quote && pair.open == open.kind())
+            .map(|pair| pair.close.as_str())
+    }
+
+    /// If the line starts with a branch (like `else` or `}`),
+    /// find the line of the scope it should be indented to match
+    pub fn branch_scope_line(
+        &self,
+        root: Node,
+        source: &ropey::Rope,
+        line: usize,
+    ) -> Option<usize> {
+        self.indents_query
+            .as_ref()?
+            .branch_scope_row(root, source, line)
     }
 }
 
-pub struct Palette {
-    pub name: &'static str,
-    pub snippets: &'static [Snippet],
+/// Check the predicates that tree-sitter doesn't handle itself (`#single-line?`)
+fn satisfies_general_predicates(query: &Query, query_match: &QueryMatch) -> bool {
+    query
+        .general_predicates(query_match.pattern_index)
+        .iter()
+        .all(|predicate| {
+            let [QueryPredicateArg::Capture(index)] = *predicate.args else {
+                return false;
+            };
+            query_match.nodes_for_capture_index(index).all(|node| {
+                let single_line = node.start_position().row == node.end_position().row;
+                single_line == (&*predicate.operator == SINGLE_LINE_PREDICATE)
+            })
+        })
+}
+
+/// Compile a blocks query for a grammar, and find what each of its captures means
+fn compile_blocks_query(
+    grammar: Grammar,
+    source: &str,
+) -> Result<(Query, Vec<BlockCapture>), LoadError> {
+    let source = resolve_inherits(source, registry::built_in_blocks_query)?;
+    let ts_lang = tree_sitter::Language::new(grammar.ts_lang());
+    let blocks_query = Query::new(&ts_lang, &source).map_err(LoadError::Query)?;
+
+    let block_captures = blocks_query
+        .capture_names()
+        .iter()
+        .map(|name| {
+            if name.starts_with('_') {
+                Ok(BlockCapture::Ignore)
+            } else if *name == "block.none" {
+                Ok(BlockCapture::Skip)
+            } else {
+                name.strip_prefix("block.")
+                    .and_then(BlockType::from_capture_name)
+                    .map(BlockCapture::Block)
+                    .ok_or_else(|| LoadError::UnknownCapture(name.to_string()))
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    for pattern in 0..blocks_query.pattern_count() {
+        for predicate in blocks_query.general_predicates(pattern) {
+            let operator = &*predicate.operator;
+            let valid = (operator == SINGLE_LINE_PREDICATE
+                || operator == NOT_SINGLE_LINE_PREDICATE)
+                && matches!(*predicate.args, [QueryPredicateArg::Capture(_)]);
+            if !valid {
+                return Err(LoadError::UnknownPredicate(operator.to_string()));
+            }
+        }
+    }
+
+    Ok((blocks_query, block_captures))
+}
+
+/// Expand `; inherits: <name>, ...` lines by appending the queries of those
+/// built-in definitions (found with `built_in`), so that the inheriting query's patterns take priority
+fn resolve_inherits(
+    query: &str,
+    built_in: fn(&str) -> Option<&'static str>,
+) -> Result<String, LoadError> {
+    let mut resolved = query.to_string();
+    for line in query.lines() {
+        let Some(names) = line.trim().strip_prefix("; inherits:") else {
+            continue;
+        };
+        for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let inherited =
+                built_in(name).ok_or_else(|| LoadError::UnknownInherit(name.to_string()))?;
+            resolved.push('\n');
+            resolved.push_str(&resolve_inherits(inherited, built_in)?);
+        }
+    }
+    Ok(resolved)
+}
+
+/// Why a language definition could not be loaded
+#[derive(Debug)]
+pub enum LoadError {
+    Json(serde_json::Error),
+    Query(QueryError),
+    UnknownCapture(String),
+    UnknownPredicate(String),
+    UnknownInherit(String),
+    IndentsQuery(QueryError),
+    UnknownIndentCapture(String),
+    EmptyAutoPair,
+    EmptyCommentToken,
 }
 
-impl Palette {
-    pub const fn new(name: &'static str, snippets: &'static [Snippet]) -> Palette {
-        Palette { name, snippets }
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Json(err) => write!(f, "invalid definition: {err}"),
+            LoadError::Query(err) => write!(f, "invalid blocks query: {err}"),
+            LoadError::UnknownCapture(name) => {
+                write!(f, "unknown capture in blocks query: @{name}")
+            }
+            LoadError::UnknownPredicate(name) => {
+                write!(f, "unknown predicate in blocks query: #{name}")
+            }
+            LoadError::UnknownInherit(name) => {
+                write!(f, "query inherits unknown language: {name}")
+            }
+            LoadError::IndentsQuery(err) => write!(f, "invalid indents query: {err}"),
+            LoadError::UnknownIndentCapture(name) => {
+                write!(f, "unknown capture in indents query: @{name}")
+            }
+            LoadError::EmptyAutoPair => write!(f, "auto pair has an empty opening"),
+            LoadError::EmptyCommentToken => write!(f, "comment token is empty"),
+        }
     }
 }
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Deserialize)]
+pub struct Palette {
+    pub name: String,
+    pub snippets: Vec<Snippet>,
+}
+
+#[derive(PartialEq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum NewScopeChar {
     Colon,
     Brace,
@@ -73,565 +434,98 @@ impl NewScopeChar {
             NewScopeChar::Brace => '{',
         }
     }
-}
 
-#[derive(Clone, Copy)]
-pub struct StringNodeIDs {
-    pub string: u16,
-    pub string_bounds: &'static [u16],
-}
-
-pub struct Snippet {
-    pub id: &'static str,
-    pub source: &'static str,
-}
-
-impl Snippet {
-    pub const fn new(id: &'static str, source: &'static str) -> Snippet {
-        Snippet { id, source }
+    /// The character that finishes a branch (like `else:` or `}`),
+    /// so typing it lines the branch up with its scope
+    pub const fn reindent_char(&self) -> char {
+        match self {
+            NewScopeChar::Colon => ':',
+            NewScopeChar::Brace => '}',
+        }
     }
 }
 
-const PYTHON_LANGUAGE: LanguageConfig = LanguageConfig {
-    name: "python",
-    ts_lang: tree_sitter_python::LANGUAGE,
-    highlight_query: tree_sitter_python::HIGHLIGHTS_QUERY,
-    new_scope_char: NewScopeChar::Colon,
-    node_categorizer: |node| {
-        use BlockType::*;
-
-        match node.kind() {
-            // scopes
-            "class_definition" => Some(Object),
-            "function_definition" => Some(FunctionDef),
-            "while_statement" => Some(While),
-            "if_statement" => Some(If),
-            "for_statement" => Some(For),
-            "try_statement" => Some(Try),
-
-            // normal expressions
-            // TODO: check exhaustiveness
-            "import_statement" => Some(Generic),
-            "import_from_statement" => Some(Generic),
-            "expression_statement" => Some(Generic),
-            "continue_statement" => Some(Generic),
-            "break_statement" => Some(Generic),
-            "pass_statement" => Some(Generic),
-            "return_statement" => Some(Generic),
-
-            // comments
-            "comment" => Some(Comment),
-
-            // dividers to keep generics from merging
-            "else_clause" => Some(Divider),
-            "elif_clause" => Some(Divider),
-            "except_clause" => Some(Divider),
-
-            // do not handle the rest
-            _ => None,
-        }
-    },
-    string_node_ids: StringNodeIDs {
-        string: 232,
-        string_bounds: &[104, 107], // 104 is string start, 107 is string end
-    },
-    palettes: &[
-        Palette::new(
-            "General",
-            &[
-                Snippet::new("import_module", "import module\n"),
-                Snippet::new("import_from", "from module import thing\n"),
-                Snippet::new("import_as", "import module as name\n"),
-                Snippet::new("var_assign", "val = 0\n"),
-                Snippet::new("var_assign_string", "val = \"Hello world\"\n"),
-                Snippet::new("var_assign_list", "val = [1, 2, 3]\n"),
-                Snippet::new("var_assign_dict", "val = {'a': 1, 'b': 'tw0'}\n"),
-                Snippet::new("var_assign_tuple", "val = (False, 1, 2.0, '3')\n"),
-                Snippet::new("var_assign_set", "val = {1, 2, 3}\n"),
-            ],
-        ),
-        Palette::new(
-            "Classes",
-            &[
-                Snippet::new(
-                    "class_declaration",
-                    "class ClassName:\n    def __init__(self, param):\n        pass\n",
-                ),
-                Snippet::new("instance_method", "def method(self, param):\n    pass\n"),
-                Snippet::new(
-                    "static_method",
-                    "@staticmethod\ndef method(param):\n    pass\n",
-                ),
-                Snippet::new("class_instance", "instance = ClassName()\n"),
-            ],
-        ),
-        Palette::new(
-            "Control",
-            &[
-                Snippet::new("for", "for item in range(0, 10):\n    pass\n"),
-                Snippet::new("while", "while 0 == 0:\n    pass\n"),
-                Snippet::new("break", "break\n"),
-                Snippet::new("continue", "continue\n"),
-                Snippet::new("if", "if 0 < 0:\n    pass\n"),
-                Snippet::new("if_else", "if 0 < 0:\n    pass\nelse:\n    pass\n"),
-                Snippet::new(
-                    "if_elif_else",
-                    "if 0 < 0:\n    pass\nelif 0 > 0:\n    pass\nelse:\n    pass\n",
-                ),
-                Snippet::new(
-                    "try",
-                    "try:\n    pass\nexcept:\n    pass\nelse:\n    pass\nfinally:\n    pass\n",
-                ),
-            ],
-        ),
-        Palette::new(
-            "Functions",
-            &[
-                Snippet::new("function_def", "def function(args):\n    return\n"),
-                Snippet::new("function_call", "function(args) \n"),
-                Snippet::new("return_val", "return value\n"),
-                Snippet::new("return", "return\n"),
-            ],
-        ),
-        Palette::new(
-            "Logic",
-            &[
-                Snippet::new("equals", "a == b\n"),
-                Snippet::new("not_equals", "a != b\n"),
-                Snippet::new("greater_than", "a > b\n"),
-                Snippet::new("less_than", "a < b\n"),
-                Snippet::new("greater_than_or_equal", "a >= b\n"),
-                Snippet::new("less_than_or_equal", "a <= b\n"),
-                Snippet::new("and", "a and b\n"),
-                Snippet::new("or", "a or b\n"),
-                Snippet::new("not", "not a\n"),
-                Snippet::new("in", "a in b\n"),
-                Snippet::new("is", "a is b\n"),
-            ],
-        ),
-        Palette::new(
-            "Arithmetic",
-            &[
-                Snippet::new("add", "a + b\n"),
-                Snippet::new("subtract", "a - b\n"),
-                Snippet::new("multiply", "a * b\n"),
-                Snippet::new("divide", "a / b\n"),
-                Snippet::new("modulo", "a % b\n"),
-                Snippet::new("exponent", "a ** b\n"),
-                Snippet::new("floor_divide", "a // b\n"),
-            ],
-        ),
-    ],
-    highlight: STANDARD_HIGHLIGHT,
-};
+/// Node kinds by name, checked against the grammar when creating a `Language`
+#[derive(Deserialize)]
+pub struct StringNodeKinds {
+    pub string: String,
+    pub string_bounds: Vec<String>,
+}
 
-const JAVA_LANGUAGE: LanguageConfig = LanguageConfig {
-    name: "java",
-    ts_lang: tree_sitter_java::LANGUAGE,
-    highlight_query: tree_sitter_java::HIGHLIGHTS_QUERY,
-    new_scope_char: NewScopeChar::Brace,
-    node_categorizer: |node| {
-        use BlockType::*;
-
-        match node.kind() {
-            // scopes
-            "class_declaration" => Some(Object),
-            "interface_declaration" => Some(Object),
-            "method_declaration" => Some(FunctionDef),
-            "while_statement" => Some(While),
-            "if_statement" => {
-                // the java grammar treats else if as else, if_statement
-                // so check that is isn't that
-                if node.prev_sibling().map_or("", |s| s.kind()) == "else" {
-                    None
-                } else {
-                    Some(If)
-                }
-            }
-            "for_statement" => Some(For),
-            "try_statement" => Some(Try),
-
-            // normal expressions (incomplete)
-            "import_declaration" => Some(Generic),
-            "expression_statement" => Some(Generic),
-            "local_variable_declaration" => {
-                // don't create a block for a for loop's variable declaration
-                if node.parent().map_or("", |p| p.kind()) == "for_statement" {
-                    None
-                } else {
-                    Some(Generic)
-                }
-            }
-            "field_declaration" => Some(Generic),
-            "return_statement" => Some(Generic),
-            "assert_statement" => Some(Generic),
+/// Text that gets its closing text inserted after the cursor when typed
+#[derive(Deserialize)]
+pub struct AutoPair {
+    /// What is typed to insert the pair. If it is longer than one character,
+    /// the rest must already be before the cursor (like `""` before the last `"` of `"""`)
+    pub open: String,
 
-            // comments
-            "line_comment" => Some(Comment),
-            "block_comment" => Some(Comment),
+    /// What is inserted after the cursor
+    pub close: String,
 
-            // dividers to keep generics from merging
-            "block" => Some(Divider),
+    /// Quotes are only paired away from words, since they could be closing a string
+    #[serde(default)]
+    pub quote: bool,
 
-            // do not handle the rest
-            _ => None,
-        }
-    },
-    string_node_ids: StringNodeIDs {
-        string: 141,
-        string_bounds: &[11, 12], // 11 is single quote, 12 is double quote
-    },
-    palettes: &[Palette::new(
-        "General",
-        &[
-            Snippet::new(
-                "if",
-                "if (condition) {\n    \n} else if (condition) {\n    \n} else {\n    \n}\n",
-            ),
-            Snippet::new(
-                "class",
-                "public class MyClass {\n    public MyClass() {\n        \n    }\n}\n",
-            ),
-            Snippet::new("while", "while (condition) {\n    \n}\n"),
-            Snippet::new("method", "public void myMethod() {\n    \n}\n"),
-            Snippet::new(
-                "try",
-                "try {\n    \n} catch (Exception e) {\n    \n} finally {\n    \n}\n",
-            ),
-        ],
-    )],
-    highlight: STANDARD_HIGHLIGHT,
-};
+    /// Words that can come right before a quote, like the `f` of a Python f-string
+    #[serde(default)]
+    pub prefixes: Vec<String>,
 
-const CS_LANGUAGE: LanguageConfig = LanguageConfig {
-    name: "c#",
-    ts_lang: tree_sitter_c_sharp::LANGUAGE,
-    highlight_query: tree_sitter_c_sharp::HIGHLIGHTS_QUERY,
-    new_scope_char: NewScopeChar::Brace,
-    node_categorizer: |node| {
-        use BlockType::*;
-
-        match node.kind() {
-            // scopes
-            "class_declaration" => Some(Object),
-            "method_declaration" => Some(FunctionDef),
-            "while_statement" => Some(While),
-            "if_statement" => {
-                if node.prev_sibling().map_or("", |s| s.kind()) == "else" {
-                    None
-                } else {
-                    Some(If)
-                }
-            }
-            "for_statement" => Some(For),
-            "try_statement" => Some(Try),
-            "switch_statement" => Some(Switch),
-            "switch_section" => Some(Divider),
-            "import_declaration" => Some(Generic),
-            "local_decleration_statement" => {
-                // don't create a block for a for loop's variable declaration
-                if node.parent().map_or("", |p| p.kind()) == "for_statement" {
-                    None
-                } else {
-                    Some(Generic)
-                }
-            }
-            "field_declaration" => Some(Generic),
-            "break_statement" => Some(Generic),
-            "return_statement" => Some(Generic),
-            "assert_statement" => Some(Generic),
-            "local_function_statement" => Some(Generic),
-            "expression_statement" => Some(Generic),
-
-            "using_directive" => Some(Generic),
-            // comments
-            "line_comment" => Some(Comment),
-            "block_comment" => Some(Comment),
-
-            // dividers to keep generics from merging
-            "block" => Some(Divider),
-
-            // do not handle the rest
-            _ => None,
-        }
-    },
-    string_node_ids: StringNodeIDs {
-        string: 141,
-        string_bounds: &[11, 12], // 11 is single quote, 12 is double quote
-    },
-    palettes: &[Palette::new(
-        "General",
-        &[
-            Snippet::new(
-                "if",
-                "if (condition) {\n    \n} else if (condition) {\n    \n} else {\n    \n}\n",
-            ),
-            Snippet::new(
-                "class",
-                "public class MyClass {\n    public MyClass() {\n        \n    }\n}\n",
-            ),
-            Snippet::new("while", "while (condition) {\n    \n}\n"),
-            Snippet::new("func", "public void myFunction() {\n    \n}\n"),
-            Snippet::new(
-                "try",
-                "try {\n    \n} catch (Exception e) {\n    \n} finally {\n    \n}\n",
-            ),
-        ],
-    )],
-    highlight: STANDARD_HIGHLIGHT,
-};
+    /// Only pair right after a word, like `<` for generics (`Vec<`) but not comparisons
+    #[serde(default)]
+    pub after_word: bool,
 
-const CPP_LANGUAGE: LanguageConfig = LanguageConfig {
-    name: "cpp",
-    ts_lang: tree_sitter_cpp::LANGUAGE,
-    highlight_query: tree_sitter_cpp::HIGHLIGHT_QUERY,
-    new_scope_char: NewScopeChar::Brace,
-    node_categorizer: |node| {
-        use BlockType::*;
-
-        match node.kind() {
-            // scopes
-            "class_specifier" => Some(Object),
-            "struct_specifier" => Some(Object),
-            "abstract_function_declarator" => Some(Object),
-            "function_definition" => {
-                // create one box around a template function
-                if node.parent().map_or("", |s| s.kind()) == "template_declaration" {
-                    None
-                } else {
-                    Some(FunctionDef)
-                }
-            }
-            "while_statement" => Some(While),
-            "if_statement" => {
-                if node.prev_sibling().map_or("", |s| s.kind()) == "else" {
-                    None
-                } else {
-                    Some(If)
-                }
-            }
-            "for_statement" => Some(For),
-            "try_statement" => Some(Try),
-            "template_declaration" => Some(FunctionDef),
-
-            // normal expressions (incomplete)
-            "preproc_include" => Some(Generic),
-            "expression_statement" => Some(Generic),
-            "continue_statement" => Some(Generic),
-            "break_statement" => Some(Generic),
-            "pass_statement" => Some(Generic),
-            "local_variable_declaration" => {
-                // don't create a block for a for loop's variable declaration
-                if node.parent().map_or("", |p| p.kind()) == "for_statement" {
-                    None
-                } else {
-                    Some(Generic)
-                }
-            }
+    /// Characters that stop the pair if they are right before it, like `&` for Rust lifetimes
+    #[serde(default)]
+    pub not_after: String,
+}
 
-            // comments
-            "comment" => Some(Comment),
+/// The pairs for definitions that don't list their own
+fn default_auto_pairs() -> Vec<AutoPair> {
+    let pair = |open: &str, close: &str, quote: bool| AutoPair {
+        open: open.to_string(),
+        close: close.to_string(),
+        quote,
+        prefixes: vec![],
+        after_word: false,
+        not_after: String::new(),
+    };
+    vec![
+        pair("(", ")", false),
+        pair("[", "]", false),
+        pair("{", "}", false),
+        pair("'", "'", true),
+        pair("\"", "\"", true),
+    ]
+}
 
-            // dividers to keep generics from merging
-            "else_clause" => Some(Divider),
-            "except_clause" => Some(Divider),
+/// The text that starts (and ends) comments in a language
+#[derive(Deserialize, Default)]
+pub struct CommentTokens {
+    /// Starts a comment that goes to the end of the line, like `//`
+    #[serde(default)]
+    pub line: Option<String>,
 
-            // do not handle the rest
-            _ => None,
-        }
-    },
-    string_node_ids: StringNodeIDs {
-        string: 360,
-        string_bounds: &[162],
-    },
-    palettes: &[Palette::new("General", &[])],
-    highlight: STANDARD_HIGHLIGHT,
-};
+    /// Starts and ends a comment that can cover multiple lines, like `/*` and `*/`
+    #[serde(default)]
+    pub block: Option<(String, String)>,
+}
 
-const RUST_LANGUAGE: LanguageConfig = LanguageConfig {
-    name: "rust",
-    ts_lang: tree_sitter_rust::LANGUAGE,
-    highlight_query: tree_sitter_rust::HIGHLIGHTS_QUERY,
-    new_scope_char: NewScopeChar::Brace,
-    node_categorizer: |node| {
-        use BlockType::*;
-
-        match node.kind() {
-            // scopes
-            "struct_item" => Some(Object),
-            "union_item" => Some(Object),
-            "enum_item" => Some(Object),
-            "impl_item" => Some(Object),
-            "trait_item" => Some(Object),
-            "function_item" => Some(FunctionDef),
-            "type_item" => Some(Object),
-            "block" => {
-                if node.parent().map_or("", |s| s.kind()) == "function_item"
-                    || node.parent().map_or("", |s| s.kind()) == "if_expression"
-                    || node.parent().map_or("", |s| s.kind()) == "else_clause"
-                    || node.parent().map_or("", |s| s.kind()) == "match_arm"
-                    || node.parent().map_or("", |s| s.kind()) == "match_block"
-                {
-                    None
-                } else {
-                    Some(Object)
-                }
-            }
-            "enum_variant" => Some(Generic),
-            "field_declaration" => Some(Generic),
-            "while_expression" => {
-                if node.parent().map_or("", |s| s.kind()) == "expression_statement" {
-                    None
-                } else {
-                    Some(Generic)
-                }
-            }
-            "match_block" => Some(Switch),
-            "binary_expression" => {
-                if node.parent().map_or("", |s| s.kind()) == "block" {
-                    None //some(generic) for this and idenitifer enabled multiline let, but caused other issues
-                } else {
-                    None
-                }
-            }
-            "identifier" => {
-                if node.parent().map_or("", |s| s.kind()) == "block" {
-                    None
-                } else {
-                    None
-                }
-            }
-            //Some(Switch),
-            "match_arm" => Some(Generic),
-            /*            "let_declaration" => {
-                            if (node.named_child_count() == 2 || node.named_child_count() == 3)
-                                && node
-                                    .named_child(1)
-                                    .map_or(false, |child| child.named_child_count() == 0)
-                            {
-                                Some(Generic)
-                            } else {
-                                Some(Object)
-                            }
-                        }
-            */
-            "let_declaration" => {
-                if node.start_position().row == node.end_position().row {
-                    Some(Generic)
-                } else {
-                    Some(Divider)
-                }
-            }
-            "if_expression" => {
-                if node.parent().map_or("", |s| s.kind()) == "else_clause" {
-                    None
-                } else {
-                    Some(If)
-                }
-            }
-            "for_expression" => {
-                if node.parent().map_or("", |s| s.kind()) == "expression_statement" {
-                    None
-                } else {
-                    Some(For)
-                }
-            }
-            "struct_expression" => Some(Generic),
-            "continue_expression" => Some(Generic),
-            "call_expression" => {
-                if let Some(parent) = node.parent() {
-                    let value_contexts = [
-                        "let_declaration",
-                        "match_expression",
-                        "binary_expression",
-                        "argument_list",
-                        "match_arm",
-                        "if_expression",
-                        "for_expression",
-                        "while_expression",
-                        "arguments",
-                    ];
-                    if value_contexts.contains(&parent.kind()) {
-                        None
-                    } else {
-                        Some(Generic)
-                    }
-                } else {
-                    None
-                }
-            }
+impl CommentTokens {
+    fn has_empty_token(&self) -> bool {
+        let empty_line = self.line.as_ref().is_some_and(String::is_empty);
+        let empty_block = self
+            .block
+            .as_ref()
+            .is_some_and(|(start, end)| start.is_empty() || end.is_empty());
+        empty_line || empty_block
+    }
+}
 
-            "macro_invocation" => {
-                if let Some(parent) = node.parent() {
-                    let value_contexts = [
-                        "let_declaration",
-                        "match_expression",
-                        "binary_expression",
-                        "argument_list",
-                        "match_arm",
-                        "if_expression",
-                        "for_expression",
-                        "while_expression",
-                    ];
-                    if value_contexts.contains(&parent.kind()) {
-                        None
-                    } else {
-                        Some(Generic)
-                    }
-                } else {
-                    Some(Generic)
-                }
-            }
-            // normal expressions (incomplete)
-            "use_item" => Some(Generic),
-            "else_clause" => Some(Divider),
-            //"break_expressionex" => Some(Generic),
-            "return_expression" => Some(Generic),
-            "assignment_expression" => Some(Generic),
-            // comments
-            "line_comment" => Some(Comment),
-            "block_comment" => Some(Comment),
-
-            // dividers to keep generics from merging
-
-            // do not handle the rest
-            _ => None,
-        }
-    },
-    string_node_ids: StringNodeIDs {
-        string: 360,
-        string_bounds: &[162],
-    },
-    palettes : &[Palette::new(
-            "General",
-            &[
-                Snippet::new(
-                    "if",
-                    "if condition {\n    // code\n} else if condition {\n    // code\n} else {\n    // code\n}",
-                ),
-                Snippet::new(
-                    "loop",
-                    "loop {\n    // code\n}",
-                ),
-                Snippet::new(
-                    "while",
-                    "while condition {\n    // code\n}",
-                ),
-                Snippet::new(
-                    "fn",
-                    "fn my_function() {\n    // code\n}",
-                ),
-                Snippet::new(
-                    "match",
-                    "match value {\n    Pattern1 => {None}\n    Pattern2 => {None}\n    _ => {}\n}",
-                ),
-                Snippet::new(
-                    "result",
-                    "fn divide(a: i32, b: i32) -> Result<i32, String> {\n    if b == 0 {\n        Err(String::from(\"Cannot divide by zero\"))\n    } else {\n        Ok(a / b)\n    }\n}",
-                ),
-            ])],
-    highlight: STANDARD_HIGHLIGHT,
-};
+#[derive(Deserialize)]
+pub struct Snippet {
+    pub id: String,
+    pub source: String,
+}
 
 const STANDARD_HIGHLIGHT: &[(&str, Color32)] = &[
     ("function", FUNCTION),
@@ -649,5 +543,7 @@ const STANDARD_HIGHLIGHT: &[(&str, Color32)] = &[
     ("number", LITERAL),
     ("escape", ESCAPE_SEQUENCE),
     ("comment", COMMENT),
+    ("tag", TAG),
+    ("attribute", ATTRIBUTE),
     ("embedded", DEFAULT), // treat inside of interpolation like top level
 ];
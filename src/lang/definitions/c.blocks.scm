@@ -0,0 +1,78 @@
+; the grammar puts else if in an else clause,
+; so keep it in the block of the first if
+(else_clause
+  (if_statement) @block.none)
+
+; a for loop's variable declaration is part of the loop
+(for_statement
+  initializer: (declaration) @block.none)
+
+; typedefs of a struct, union, or enum get the block of the type
+(type_definition
+  type: [
+    (struct_specifier body: (_))
+    (union_specifier body: (_))
+    (enum_specifier body: (_))
+  ] @block.none)
+(type_definition
+  type: [
+    (struct_specifier body: (_))
+    (union_specifier body: (_))
+    (enum_specifier body: (_))
+  ]) @block.object
+
+; scopes
+[
+  (struct_specifier body: (_))
+  (union_specifier body: (_))
+  (enum_specifier body: (_))
+] @block.object
+(function_definition) @block.function_def
+[
+  (while_statement)
+  (do_statement)
+] @block.while
+[
+  (if_statement)
+  (preproc_if)
+  (preproc_ifdef)
+] @block.if
+(for_statement) @block.for
+(switch_statement) @block.switch
+
+; declarations can span multiple lines (like initializer lists),
+; so use a divider to keep them from merging with the generics around them
+([
+  (declaration)
+  (expression_statement)
+  (type_definition)
+] @block.generic
+  (#single-line? @block.generic))
+[
+  (declaration)
+  (expression_statement)
+  (type_definition)
+] @block.divider
+
+; normal expressions
+[
+  (preproc_include)
+  (preproc_def)
+  (preproc_function_def)
+  (field_declaration)
+  (return_statement)
+  (continue_statement)
+  (break_statement)
+  (goto_statement)
+] @block.generic
+
+; comments
+(comment) @block.comment
+
+; dividers to keep generics from merging
+[
+  (else_clause)
+  (case_statement)
+  (preproc_else)
+  (preproc_elif)
+] @block.divider
@@ -0,0 +1,21 @@
+; braces indent what is inside them
+[
+  (compound_statement)
+  (field_declaration_list)
+  (enumerator_list)
+  (initializer_list)
+] @indent.begin
+
+; brackets continue their contents on the next level
+[
+  (argument_list)
+  (parameter_list)
+  (parenthesized_expression)
+] @indent.begin
+
+; closing brackets line up with the line that opened them
+[
+  "}"
+  ")"
+  "]"
+] @indent.branch
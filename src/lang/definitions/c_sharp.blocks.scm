@@ -0,0 +1,106 @@
+; the grammar treats else if as an if statement in the else,
+; so keep it in the block of the first if
+(if_statement
+  alternative: (if_statement) @block.none)
+
+; don't create a block for a for loop's variable declaration
+(for_statement
+  (variable_declaration) @block.none)
+
+; file scoped namespaces (namespace A;) hold the rest of the file,
+; so they only get a block when they are a single line
+((file_scoped_namespace_declaration) @block.generic
+  (#single-line? @block.generic))
+
+; scopes
+[
+  (namespace_declaration)
+  (class_declaration)
+  (struct_declaration)
+  (record_declaration)
+  (interface_declaration)
+  (enum_declaration)
+] @block.object
+[
+  (method_declaration)
+  (constructor_declaration)
+  (destructor_declaration)
+  (operator_declaration)
+  (local_function_statement)
+] @block.function_def
+[
+  (while_statement)
+  (do_statement)
+] @block.while
+(if_statement) @block.if
+[
+  (for_statement)
+  (foreach_statement)
+] @block.for
+(try_statement) @block.try
+(switch_statement) @block.switch
+[
+  (using_statement)
+  (lock_statement)
+] @block.with
+
+; properties with accessors on their own lines work like methods
+([
+  (property_declaration)
+  (indexer_declaration)
+] @block.generic
+  (#single-line? @block.generic))
+[
+  (property_declaration)
+  (indexer_declaration)
+] @block.function_def
+
+; lambdas and switch expressions only get a block if they have multiple lines
+((lambda_expression
+  body: (block)) @block.function_def
+  (#not-single-line? @block.function_def))
+((anonymous_method_expression) @block.function_def
+  (#not-single-line? @block.function_def))
+((switch_expression) @block.switch
+  (#not-single-line? @block.switch))
+
+; statements that hold a lambda or switch expression span multiple lines,
+; so use a divider to keep them from merging with the generics around them
+([
+  (expression_statement)
+  (local_declaration_statement)
+  (field_declaration)
+  (return_statement)
+  (throw_statement)
+  (yield_statement)
+] @block.generic
+  (#single-line? @block.generic))
+[
+  (expression_statement)
+  (local_declaration_statement)
+  (field_declaration)
+  (return_statement)
+  (throw_statement)
+  (yield_statement)
+] @block.divider
+
+; normal expressions
+[
+  (using_directive)
+  (delegate_declaration)
+  (event_field_declaration)
+  (break_statement)
+  (continue_statement)
+  (goto_statement)
+] @block.generic
+
+; comments
+(comment) @block.comment
+
+; dividers to keep generics from merging
+(if_statement (block) @block.divider)
+[
+  (switch_section)
+  (catch_clause)
+  (finally_clause)
+] @block.divider
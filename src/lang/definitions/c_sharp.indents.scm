@@ -0,0 +1,25 @@
+; braces indent what is inside them
+[
+  (block)
+  (declaration_list)
+  (enum_member_declaration_list)
+  (accessor_list)
+  (switch_body)
+  (switch_expression)
+  (initializer_expression)
+  (anonymous_object_creation_expression)
+] @indent.begin
+
+; brackets continue their contents on the next level
+[
+  (argument_list)
+  (parameter_list)
+  (parenthesized_expression)
+] @indent.begin
+
+; closing brackets line up with the line that opened them
+[
+  "}"
+  ")"
+  "]"
+] @indent.branch
@@ -0,0 +1,48 @@
+; inherits: c
+
+; create one box around a template function or class
+(template_declaration
+  [
+    (function_definition)
+    (class_specifier body: (_))
+    (struct_specifier body: (_))
+  ] @block.none)
+(template_declaration
+  [
+    (class_specifier body: (_))
+    (struct_specifier body: (_))
+  ]) @block.object
+
+; a declaration in an if or switch header is part of the statement
+(condition_clause
+  (declaration) @block.none)
+
+; scopes
+[
+  (namespace_definition)
+  (class_specifier body: (_))
+] @block.object
+(template_declaration) @block.function_def
+(for_range_loop) @block.for
+(try_statement) @block.try
+
+; lambdas only get a block if their body has multiple lines
+; (the statements holding them are dividers from the c rules)
+((lambda_expression
+  body: (compound_statement)) @block.function_def
+  (#not-single-line? @block.function_def))
+
+; normal expressions
+[
+  (using_declaration)
+  (alias_declaration)
+  (namespace_alias_definition)
+  (static_assert_declaration)
+  (throw_statement)
+] @block.generic
+
+; dividers to keep generics from merging
+[
+  (catch_clause)
+  (access_specifier)
+] @block.divider
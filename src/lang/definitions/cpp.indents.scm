@@ -0,0 +1,8 @@
+; inherits: c
+
+[
+  (declaration_list)
+  (condition_clause)
+  (template_argument_list)
+  (template_parameter_list)
+] @indent.begin
@@ -0,0 +1,20 @@
+; scopes
+[
+  (rule_set)
+  (media_statement)
+  (supports_statement)
+  (keyframes_statement)
+  (keyframe_block)
+] @block.object
+
+; normal expressions
+[
+  (declaration)
+  (import_statement)
+  (charset_statement)
+  (namespace_statement)
+  (at_rule)
+] @block.generic
+
+; comments
+(comment) @block.comment
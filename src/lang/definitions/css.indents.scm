@@ -0,0 +1,14 @@
+; braces indent what is inside them
+[
+  (block)
+  (keyframe_block_list)
+] @indent.begin
+
+; brackets continue their contents on the next level
+(arguments) @indent.begin
+
+; closing brackets line up with the line that opened them
+[
+  "}"
+  ")"
+] @indent.branch
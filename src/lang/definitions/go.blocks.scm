@@ -0,0 +1,91 @@
+; the grammar treats else if as an if statement in the else,
+; so keep it in the block of the first if
+(if_statement
+  alternative: (if_statement) @block.none)
+
+; the simple statements at the start of a header are part of the statement
+(if_statement
+  initializer: (_) @block.none)
+(expression_switch_statement
+  initializer: (_) @block.none)
+(type_switch_statement
+  initializer: (_) @block.none)
+(for_clause (_) @block.none)
+(communication_case
+  communication: (_) @block.none)
+
+; scopes
+(type_declaration
+  (type_spec
+    type: [
+      (struct_type)
+      (interface_type)
+    ])) @block.object
+[
+  (function_declaration)
+  (method_declaration)
+] @block.function_def
+(if_statement) @block.if
+(for_statement) @block.for
+[
+  (expression_switch_statement)
+  (type_switch_statement)
+  (select_statement)
+] @block.switch
+
+; function values (callbacks, goroutines) only get a block if they have multiple lines
+((func_literal) @block.function_def
+  (#not-single-line? @block.function_def))
+
+; statements that hold a function value span multiple lines,
+; so use a divider to keep them from merging with the generics around them
+([
+  (expression_statement)
+  (short_var_declaration)
+  (assignment_statement)
+  (var_declaration)
+  (const_declaration)
+  (type_declaration)
+  (return_statement)
+  (defer_statement)
+  (go_statement)
+] @block.generic
+  (#single-line? @block.generic))
+[
+  (expression_statement)
+  (short_var_declaration)
+  (assignment_statement)
+  (var_declaration)
+  (const_declaration)
+  (type_declaration)
+  (return_statement)
+  (defer_statement)
+  (go_statement)
+] @block.divider
+
+; normal expressions
+[
+  (package_clause)
+  (import_declaration)
+  (field_declaration)
+  (method_elem)
+  (inc_statement)
+  (dec_statement)
+  (send_statement)
+  (break_statement)
+  (continue_statement)
+  (goto_statement)
+  (fallthrough_statement)
+] @block.generic
+
+; comments
+(comment) @block.comment
+
+; dividers to keep generics from merging
+(if_statement (block) @block.divider)
+[
+  (expression_case)
+  (type_case)
+  (default_case)
+  (communication_case)
+] @block.divider
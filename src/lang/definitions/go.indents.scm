@@ -0,0 +1,26 @@
+; braces indent what is inside them
+[
+  (block)
+  (field_declaration_list)
+  (interface_type)
+  (literal_value)
+  (expression_switch_statement)
+  (type_switch_statement)
+  (select_statement)
+] @indent.begin
+
+; brackets continue their contents on the next level
+[
+  (argument_list)
+  (parameter_list)
+  (import_spec_list)
+  (const_declaration)
+  (var_declaration)
+] @indent.begin
+
+; closing brackets line up with the line that opened them
+[
+  "}"
+  ")"
+  "]"
+] @indent.branch
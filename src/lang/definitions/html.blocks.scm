@@ -0,0 +1,18 @@
+; scripts and styles hold other languages, so they're scopes even on one line
+[
+  (script_element)
+  (style_element)
+] @block.object
+
+; elements with content on multiple lines are scopes
+((element) @block.object
+  (#not-single-line? @block.object))
+
+; normal expressions
+[
+  (element)
+  (doctype)
+] @block.generic
+
+; comments
+(comment) @block.comment
@@ -0,0 +1,9 @@
+; the contents of an element are indented inside it
+[
+  (element)
+  (script_element)
+  (style_element)
+] @indent.begin
+
+; closing tags line up with the opening tag
+(end_tag) @indent.branch
@@ -0,0 +1,88 @@
+; the grammar treats else if as an if statement in the else,
+; so keep it in the block of the first if
+(if_statement
+  alternative: (if_statement) @block.none)
+
+; don't create a block for a for loop's variable declaration
+(for_statement
+  (local_variable_declaration) @block.none)
+
+; scopes
+[
+  (class_declaration)
+  (interface_declaration)
+  (enum_declaration)
+  (record_declaration)
+  (annotation_type_declaration)
+] @block.object
+[
+  (method_declaration)
+  (constructor_declaration)
+  (compact_constructor_declaration)
+] @block.function_def
+[
+  (while_statement)
+  (do_statement)
+] @block.while
+(if_statement) @block.if
+[
+  (for_statement)
+  (enhanced_for_statement)
+] @block.for
+[
+  (try_statement)
+  (try_with_resources_statement)
+] @block.try
+(switch_expression) @block.switch
+(synchronized_statement) @block.with
+
+; lambdas only get a block if their body has multiple lines
+((lambda_expression
+  body: (block)) @block.function_def
+  (#not-single-line? @block.function_def))
+
+; statements that hold a lambda or anonymous class span multiple lines,
+; so use a divider to keep them from merging with the generics around them
+([
+  (expression_statement)
+  (local_variable_declaration)
+  (field_declaration)
+  (return_statement)
+  (throw_statement)
+  (yield_statement)
+] @block.generic
+  (#single-line? @block.generic))
+[
+  (expression_statement)
+  (local_variable_declaration)
+  (field_declaration)
+  (return_statement)
+  (throw_statement)
+  (yield_statement)
+] @block.divider
+
+; normal expressions
+[
+  (package_declaration)
+  (import_declaration)
+  (constant_declaration)
+  (explicit_constructor_invocation)
+  (assert_statement)
+  (break_statement)
+  (continue_statement)
+] @block.generic
+
+; comments
+[
+  (line_comment)
+  (block_comment)
+] @block.comment
+
+; dividers to keep generics from merging
+(if_statement (block) @block.divider)
+[
+  (catch_clause)
+  (finally_clause)
+  (switch_block_statement_group)
+  (switch_rule)
+] @block.divider
@@ -0,0 +1,27 @@
+; braces indent what is inside them
+[
+  (block)
+  (class_body)
+  (interface_body)
+  (enum_body)
+  (constructor_body)
+  (annotation_type_body)
+  (switch_block)
+  (array_initializer)
+  (element_value_array_initializer)
+] @indent.begin
+
+; brackets continue their contents on the next level
+[
+  (argument_list)
+  (formal_parameters)
+  (parenthesized_expression)
+  (resource_specification)
+] @indent.begin
+
+; closing brackets line up with the line that opened them
+[
+  "}"
+  ")"
+  "]"
+] @indent.branch
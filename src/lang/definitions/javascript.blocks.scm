@@ -0,0 +1,88 @@
+; else if is part of the first if's block
+(else_clause
+  (if_statement) @block.none)
+
+; a for loop's header is part of the loop
+(for_statement
+  initializer: (_) @block.none)
+(for_statement
+  condition: (_) @block.none)
+
+; exported declarations get the block of the declaration
+(export_statement
+  declaration: (_) @block.none)
+(export_statement
+  declaration: [
+    (function_declaration)
+    (generator_function_declaration)
+  ]) @block.function_def
+(export_statement
+  declaration: (class_declaration)) @block.object
+
+; scopes
+[
+  (class_declaration)
+  (class)
+] @block.object
+[
+  (function_declaration)
+  (generator_function_declaration)
+  (method_definition)
+] @block.function_def
+(while_statement) @block.while
+(do_statement) @block.while
+(if_statement) @block.if
+[
+  (for_statement)
+  (for_in_statement)
+] @block.for
+(try_statement) @block.try
+(switch_statement) @block.switch
+
+; function values (callbacks, arrow functions) only get a block if they have a body
+((arrow_function
+  body: (statement_block)) @block.function_def
+  (#not-single-line? @block.function_def))
+((function_expression) @block.function_def
+  (#not-single-line? @block.function_def))
+
+; statements that hold a function value span multiple lines,
+; so use a divider to keep them from merging with the generics around them
+([
+  (expression_statement)
+  (lexical_declaration)
+  (variable_declaration)
+  (export_statement)
+  (import_statement)
+  (return_statement)
+  (throw_statement)
+] @block.generic
+  (#single-line? @block.generic))
+[
+  (expression_statement)
+  (lexical_declaration)
+  (variable_declaration)
+  (export_statement)
+  (import_statement)
+  (return_statement)
+  (throw_statement)
+] @block.divider
+
+; normal expressions
+[
+  (break_statement)
+  (continue_statement)
+  (debugger_statement)
+] @block.generic
+
+; comments
+(comment) @block.comment
+
+; dividers to keep generics from merging
+[
+  (else_clause)
+  (switch_case)
+  (switch_default)
+  (catch_clause)
+  (finally_clause)
+] @block.divider
@@ -0,0 +1,31 @@
+; braces indent what is inside them
+[
+  (statement_block)
+  (class_body)
+  (switch_body)
+  (object)
+  (object_pattern)
+  (named_imports)
+  (export_clause)
+] @indent.begin
+
+; brackets continue their contents on the next level
+[
+  (arguments)
+  (formal_parameters)
+  (array)
+  (array_pattern)
+  (parenthesized_expression)
+  (template_substitution)
+] @indent.begin
+
+; jsx children are indented inside their element
+(jsx_element) @indent.begin
+
+; closing brackets and tags line up with the line that opened them
+[
+  "}"
+  ")"
+  "]"
+  (jsx_closing_element)
+] @indent.branch
@@ -0,0 +1,52 @@
+; decorators are a part of the definition they decorate
+(decorated_definition
+  definition: (_) @block.none)
+
+; scopes
+[
+  (class_definition)
+  (decorated_definition
+    definition: (class_definition))
+] @block.object
+[
+  (function_definition)
+  (decorated_definition
+    definition: (function_definition))
+] @block.function_def
+(while_statement) @block.while
+(if_statement) @block.if
+(for_statement) @block.for
+(try_statement) @block.try
+(with_statement) @block.with
+(match_statement) @block.match
+
+; normal expressions
+[
+  (import_statement)
+  (import_from_statement)
+  (future_import_statement)
+  (expression_statement)
+  (continue_statement)
+  (break_statement)
+  (pass_statement)
+  (return_statement)
+  (global_statement)
+  (nonlocal_statement)
+  (delete_statement)
+  (raise_statement)
+  (assert_statement)
+  (type_alias_statement)
+] @block.generic
+
+; comments
+(comment) @block.comment
+
+; dividers to keep generics from merging
+[
+  (else_clause)
+  (elif_clause)
+  (except_clause)
+  (except_group_clause)
+  (finally_clause)
+  (case_clause)
+] @block.divider
@@ -0,0 +1,57 @@
+; compound statements indent their bodies
+[
+  (if_statement)
+  (elif_clause)
+  (else_clause)
+  (for_statement)
+  (while_statement)
+  (try_statement)
+  (except_clause)
+  (finally_clause)
+  (with_statement)
+  (function_definition)
+  (class_definition)
+  (match_statement)
+  (case_clause)
+] @indent.begin
+
+; brackets continue their contents on the next level
+[
+  (argument_list)
+  (parameters)
+  (list)
+  (tuple)
+  (dictionary)
+  (set)
+  (parenthesized_expression)
+  (list_comprehension)
+  (dictionary_comprehension)
+  (set_comprehension)
+  (generator_expression)
+] @indent.begin
+
+; clauses and closing brackets line up with the statement they belong to
+[
+  "elif"
+  "else"
+  "except"
+  "finally"
+  ")"
+  "]"
+  "}"
+] @indent.branch
+
+; while a clause is being typed at the indent of the block before it, it is an error
+((ERROR
+  .
+  (identifier) @indent.branch)
+  (#any-of? @indent.branch "elif" "else" "except" "finally"))
+
+; nothing after these runs in the same block
+[
+  (return_statement)
+  (pass_statement)
+  (break_statement)
+  (continue_statement)
+  (raise_statement)
+] @indent.dedent
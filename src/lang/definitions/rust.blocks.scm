@@ -0,0 +1,96 @@
+; else if is part of the first if's block
+(else_clause
+  (if_expression) @block.none)
+
+; statements that are a block-like expression get the block of the expression
+(expression_statement
+  [
+    (if_expression)
+    (match_expression)
+    (for_expression)
+    (while_expression)
+    (loop_expression)
+    (unsafe_block)
+    (async_block)
+    (block)
+  ]) @block.none
+
+; scopes
+[
+  (struct_item)
+  (union_item)
+  (enum_item)
+  (impl_item)
+  (trait_item)
+  (mod_item body: (_))
+] @block.object
+[
+  (function_item)
+  (macro_definition)
+] @block.function_def
+[
+  (while_expression)
+  (loop_expression)
+] @block.while
+(if_expression) @block.if
+(for_expression) @block.for
+(match_expression) @block.match
+
+; closures only get a block if their body has multiple lines
+((closure_expression
+  body: (block)) @block.function_def
+  (#not-single-line? @block.function_def))
+
+; match arms with a multi-line body are kept apart with dividers
+((match_arm) @block.generic
+  (#single-line? @block.generic))
+(match_arm) @block.divider
+
+; statements can span multiple lines (like a closure or struct literal),
+; so use a divider to keep them from merging with the generics around them
+([
+  (let_declaration)
+  (expression_statement)
+  (const_item)
+  (static_item)
+  (type_item)
+] @block.generic
+  (#single-line? @block.generic))
+[
+  (let_declaration)
+  (expression_statement)
+  (const_item)
+  (static_item)
+  (type_item)
+] @block.divider
+
+; normal expressions
+[
+  (use_declaration)
+  (extern_crate_declaration)
+  (mod_item)
+  (function_signature_item)
+  (associated_type)
+  (enum_variant)
+  (field_declaration)
+] @block.generic
+
+; macros used as items, like lazy_static! { ... }
+(source_file
+  (macro_invocation) @block.generic)
+(declaration_list
+  (macro_invocation) @block.generic)
+
+; comments
+[
+  (line_comment)
+  (block_comment)
+] @block.comment
+
+; the value at the end of a block, like `a * b` or `Ok(value)`
+((block
+  (_) @block.generic .)
+  (#single-line? @block.generic))
+
+; dividers to keep generics from merging
+(else_clause) @block.divider
@@ -0,0 +1,28 @@
+; braces indent what is inside them
+[
+  (block)
+  (declaration_list)
+  (field_declaration_list)
+  (enum_variant_list)
+  (match_block)
+  (field_initializer_list)
+  (use_list)
+] @indent.begin
+
+; brackets continue their contents on the next level
+[
+  (arguments)
+  (parameters)
+  (array_expression)
+  (tuple_expression)
+  (token_tree)
+  (type_arguments)
+  (type_parameters)
+] @indent.begin
+
+; closing brackets line up with the line that opened them
+[
+  "}"
+  ")"
+  "]"
+] @indent.branch
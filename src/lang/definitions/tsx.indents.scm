@@ -0,0 +1,5 @@
+; inherits: typescript
+
+; jsx children are indented inside their element
+(jsx_element) @indent.begin
+(jsx_closing_element) @indent.branch
@@ -0,0 +1,43 @@
+; inherits: javascript
+
+; exported declarations get the block of the declaration
+(export_statement
+  declaration: (_) @block.none)
+(export_statement
+  declaration: [
+    (interface_declaration)
+    (abstract_class_declaration)
+    (enum_declaration)
+    (internal_module)
+  ]) @block.object
+
+; type-only declarations inside an ambient declaration (declare ...) are part of it
+(ambient_declaration
+  (_) @block.none)
+
+; scopes
+[
+  (interface_declaration)
+  (abstract_class_declaration)
+  (enum_declaration)
+  (internal_module)
+  (module)
+] @block.object
+
+; normal expressions
+([
+  (type_alias_declaration)
+  (ambient_declaration)
+] @block.generic
+  (#single-line? @block.generic))
+[
+  (type_alias_declaration)
+  (ambient_declaration)
+] @block.divider
+[
+  (public_field_definition)
+  (method_signature)
+  (abstract_method_signature)
+  (function_signature)
+  (property_signature)
+] @block.generic
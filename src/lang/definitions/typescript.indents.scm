@@ -0,0 +1,32 @@
+; braces indent what is inside them
+[
+  (statement_block)
+  (class_body)
+  (switch_body)
+  (object)
+  (object_pattern)
+  (named_imports)
+  (export_clause)
+  (interface_body)
+  (object_type)
+  (enum_body)
+] @indent.begin
+
+; brackets continue their contents on the next level
+[
+  (arguments)
+  (formal_parameters)
+  (array)
+  (array_pattern)
+  (parenthesized_expression)
+  (template_substitution)
+  (type_arguments)
+  (type_parameters)
+] @indent.begin
+
+; closing brackets line up with the line that opened them
+[
+  "}"
+  ")"
+  "]"
+] @indent.branch
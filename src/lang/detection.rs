@@ -0,0 +1,249 @@
+use super::{
+    config::{LanguageConfig, PLAIN_TEXT},
+    registry,
+};
+
+/// Find the language for a file. In order, this uses:
+///   1. a Vim or Emacs modeline naming the language
+///   2. the file extension (checking the contents of `.h` files for C++)
+///   3. the interpreter in a `#!` line
+///   4. lines that are a strong sign of a language
+pub fn detect(file_name: &str, contents: &str) -> &'static LanguageConfig {
+    if let Some(config) = from_modeline(contents) {
+        return config;
+    }
+
+    let config = registry::for_file(file_name);
+    if config.name != PLAIN_TEXT {
+        // .h files are used by both C and C++, so check for C++ only features
+        let extension = file_name.split('.').next_back().unwrap_or("");
+        if extension == "h" && header_is_cpp(contents) {
+            if let Some(cpp) = registry::by_name("cpp") {
+                return cpp;
+            }
+        }
+        return config;
+    }
+
+    from_shebang(contents)
+        .or_else(|| from_content(contents))
+        .unwrap_or(config)
+}
+
+/// How many lines at the start and end of a file can have a modeline
+const MODELINE_LINES: usize = 5;
+
+/// Find the language in a Vim (`vim: set ft=python:`) or Emacs (`-*- mode: python -*-`) modeline
+fn from_modeline(contents: &str) -> Option<&'static LanguageConfig> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let start = &lines[..lines.len().min(MODELINE_LINES)];
+    let end = &lines[lines.len().saturating_sub(MODELINE_LINES)..];
+
+    start
+        .iter()
+        .chain(end)
+        .find_map(|line| vim_modeline(line).or_else(|| emacs_modeline(line)))
+        .and_then(registry::for_name)
+}
+
+/// Get the file type from a Vim modeline, like `# vim: set ft=python :` or `// vim: ts=4 ft=c`
+fn vim_modeline(line: &str) -> Option<&str> {
+    // like Vim, the marker has to start the line or follow whitespace (so `regex:` isn't one)
+    let options = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(idx, _)| {
+                line[..*idx]
+                    .chars()
+                    .next_back()
+                    .is_none_or(char::is_whitespace)
+            })
+            .map(|(idx, _)| &line[idx + marker.len()..])
+    })?;
+    options
+        .split([' ', ':', '\t'])
+        .find_map(|option| {
+            option
+                .strip_prefix("ft=")
+                .or_else(|| option.strip_prefix("filetype="))
+                .or_else(|| option.strip_prefix("syntax="))
+        })
+        .filter(|name| !name.is_empty())
+}
+
+/// Get the mode from an Emacs modeline, like `-*- mode: python -*-` or `-*- python -*-`
+fn emacs_modeline(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (variables, _) = rest.split_once("-*-")?;
+
+    // either a list of `name: value` pairs, or just the mode
+    if !variables.contains(':') {
+        return Some(variables.trim()).filter(|mode| !mode.is_empty());
+    }
+    variables.split(';').find_map(|variable| {
+        let (name, value) = variable.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("mode")
+            .then(|| value.trim())
+    })
+}
+
+/// Find the language for the interpreter in a `#!` line,
+/// like `#!/usr/bin/python3` or `#!/usr/bin/env -S node --flag`
+fn from_shebang(contents: &str) -> Option<&'static LanguageConfig> {
+    let shebang = contents.lines().next()?.strip_prefix("#!")?;
+
+    let mut words = shebang.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        // skip the options to env (like -S) to get to the program
+        interpreter = words.find(|word| !word.starts_with('-'))?;
+    }
+
+    // python3.12 -> python
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    registry::for_name(interpreter)
+}
+
+/// Lines that are a strong sign of a language when they start with the pattern.
+/// Used for files without an extension, so only languages with an obvious style are here.
+const CONTENT_MARKERS: &[(&str, &str)] = &[
+    ("def ", "python"),
+    ("elif ", "python"),
+    ("from __future__ import", "python"),
+    ("if __name__ == ", "python"),
+    ("package main", "go"),
+    ("func main() {", "go"),
+    ("fn main() {", "rust"),
+    ("use std::", "rust"),
+    ("let mut ", "rust"),
+    ("impl ", "rust"),
+    ("public static void main(String", "java"),
+    ("import java.", "java"),
+    ("using System", "c#"),
+    ("static void Main(", "c#"),
+    ("Console.WriteLine(", "c#"),
+    ("#include <iostream>", "cpp"),
+    ("std::cout", "cpp"),
+    ("#include <stdio.h>", "c"),
+    ("#include <stdlib.h>", "c"),
+    ("console.log(", "javascript"),
+    ("module.exports", "javascript"),
+    ("<!DOCTYPE html", "html"),
+    ("<html", "html"),
+];
+
+/// Guess the language from the lines of a file, picking the one with the most markers
+fn from_content(contents: &str) -> Option<&'static LanguageConfig> {
+    let mut counts: Vec<(&str, usize)> = vec![];
+    for line in contents.lines().map(str::trim_start) {
+        for (marker, name) in CONTENT_MARKERS {
+            if line.starts_with(marker) {
+                match counts.iter_mut().find(|(counted, _)| counted == name) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((*name, 1)),
+                }
+            }
+        }
+    }
+
+    // ties go to the language that was seen first
+    let (name, _) = counts.iter().rev().max_by_key(|(_, count)| *count)?;
+    registry::by_name(name)
+}
+
+/// Things that show up in C++ headers but not in C ones
+const CPP_MARKERS: &[&str] = &[
+    "::",
+    "class ",
+    "namespace ",
+    "template <",
+    "template<",
+    "public:",
+    "private:",
+    "protected:",
+    "virtual ",
+    "nullptr",
+    "constexpr ",
+    "#include <iostream>",
+    "#include <string>",
+    "#include <vector>",
+];
+
+/// Guess whether a header is C++ (instead of C) based on the code outside of comments
+fn header_is_cpp(contents: &str) -> bool {
+    contents
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or("").trim())
+        .filter(|line| !line.starts_with('*') && !line.starts_with("/*"))
+        .any(|line| CPP_MARKERS.iter().any(|marker| line.contains(marker)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_default_to_c() {
+        let c_header = "#ifndef POINT_H\n#define POINT_H\n\n// a class of points\nstruct point {\n    int x;\n};\n\n#endif\n";
+        assert_eq!(detect("point.h", c_header).name, "c");
+    }
+
+    #[test]
+    fn headers_with_cpp_features_are_cpp() {
+        let cpp_header = "#pragma once\n\nclass Point {\npublic:\n    int x;\n};\n";
+        assert_eq!(detect("point.h", cpp_header).name, "cpp");
+
+        let namespaced = "#pragma once\n\nnamespace geometry {\nint area();\n}\n";
+        assert_eq!(detect("geometry.h", namespaced).name, "cpp");
+    }
+
+    #[test]
+    fn shebangs_name_the_interpreter() {
+        assert_eq!(
+            detect("script", "#!/usr/bin/env python3\nprint(1)\n").name,
+            "python"
+        );
+        assert_eq!(detect("script", "#!/usr/bin/python3.12\n").name, "python");
+        assert_eq!(
+            detect("run", "#!/usr/bin/env -S node --no-warnings\n").name,
+            "javascript"
+        );
+        assert_eq!(detect("script", "#!/bin/sh\necho hi\n").name, PLAIN_TEXT);
+    }
+
+    #[test]
+    fn modelines_override_extensions() {
+        let vim = "x = 1\n# vim: set ts=4 ft=python :\n";
+        assert_eq!(detect("untitled", vim).name, "python");
+
+        let emacs = "// -*- mode: c++; indent-tabs-mode: nil -*-\nint x;\n";
+        assert_eq!(detect("notes.txt", emacs).name, "cpp");
+
+        let short_emacs = "/* -*- c -*- */\n";
+        assert_eq!(detect("header", short_emacs).name, "c");
+    }
+
+    #[test]
+    fn modeline_markers_must_start_a_word() {
+        let python = "# split the line with a regex: syntax=html, then\nx = 1\n";
+        assert_eq!(detect("main.py", python).name, "python");
+
+        let no_space = "//vim: ft=python\n";
+        assert_eq!(detect("notes.txt", no_space).name, PLAIN_TEXT);
+        assert_eq!(detect("notes.txt", "vim: ft=python\n").name, "python");
+    }
+
+    #[test]
+    fn content_is_used_without_an_extension() {
+        let rust = "use std::io;\n\nfn main() {\n    let mut x = 1;\n}\n";
+        assert_eq!(detect("untitled", rust).name, "rust");
+
+        let python = "def f():\n    pass\n\nif __name__ == \"__main__\":\n    f()\n";
+        assert_eq!(detect("untitled", python).name, "python");
+
+        assert_eq!(detect("untitled", "just some words\n").name, PLAIN_TEXT);
+
+        // extensions win over content
+        assert_eq!(detect("main.c", rust).name, "c");
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::LazyLock;
+
+use serde::Deserialize;
+
+/// The tree-sitter grammars compiled into Lilypad.
+/// Language definitions choose one of these by name.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Grammar {
+    Python,
+    Java,
+    CSharp,
+    C,
+    Cpp,
+    Rust,
+    Go,
+    #[serde(rename = "javascript")]
+    JavaScript,
+    #[serde(rename = "typescript")]
+    TypeScript,
+    Tsx,
+    Html,
+    Css,
+}
+
+impl Grammar {
+    pub fn ts_lang(&self) -> tree_sitter_language::LanguageFn {
+        match self {
+            Grammar::Python => tree_sitter_python::LANGUAGE,
+            Grammar::Java => tree_sitter_java::LANGUAGE,
+            Grammar::CSharp => tree_sitter_c_sharp::LANGUAGE,
+            Grammar::C => tree_sitter_c::LANGUAGE,
+            Grammar::Cpp => tree_sitter_cpp::LANGUAGE,
+            Grammar::Rust => tree_sitter_rust::LANGUAGE,
+            Grammar::Go => tree_sitter_go::LANGUAGE,
+            Grammar::JavaScript => tree_sitter_javascript::LANGUAGE,
+            Grammar::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+            Grammar::Tsx => tree_sitter_typescript::LANGUAGE_TSX,
+            Grammar::Html => tree_sitter_html::LANGUAGE,
+            Grammar::Css => tree_sitter_css::LANGUAGE,
+        }
+    }
+
+    pub fn highlight_query(&self) -> &'static str {
+        match self {
+            Grammar::Python => tree_sitter_python::HIGHLIGHTS_QUERY,
+            Grammar::Java => tree_sitter_java::HIGHLIGHTS_QUERY,
+            Grammar::CSharp => tree_sitter_c_sharp::HIGHLIGHTS_QUERY,
+            Grammar::C => tree_sitter_c::HIGHLIGHT_QUERY,
+            Grammar::Cpp => CPP_HIGHLIGHTS.as_str(),
+            Grammar::Rust => tree_sitter_rust::HIGHLIGHTS_QUERY,
+            Grammar::Go => tree_sitter_go::HIGHLIGHTS_QUERY,
+            Grammar::JavaScript => JAVASCRIPT_HIGHLIGHTS.as_str(),
+            Grammar::TypeScript => TYPESCRIPT_HIGHLIGHTS.as_str(),
+            Grammar::Tsx => TSX_HIGHLIGHTS.as_str(),
+            Grammar::Html => tree_sitter_html::HIGHLIGHTS_QUERY,
+            Grammar::Css => tree_sitter_css::HIGHLIGHTS_QUERY,
+        }
+    }
+
+    /// The query for languages embedded in this one, like `<script>` tags in HTML.
+    /// Injected languages are found by name with `injections::resolve`.
+    pub fn injection_query(&self) -> &'static str {
+        match self {
+            Grammar::Python => PYTHON_INJECTIONS,
+            Grammar::Rust => tree_sitter_rust::INJECTIONS_QUERY,
+            Grammar::JavaScript | Grammar::TypeScript | Grammar::Tsx => {
+                tree_sitter_javascript::INJECTIONS_QUERY
+            }
+            Grammar::Html => tree_sitter_html::INJECTIONS_QUERY,
+            _ => "",
+        }
+    }
+}
+
+// python has no injections query of its own, so strings are marked with a comment on the line
+// before them, like `# language=html`. only the languages that can be injected are listed.
+const PYTHON_INJECTIONS: &str = r#"
+((comment) @_marker
+  .
+  (expression_statement
+    [(assignment right: (string (string_content) @injection.content))
+     (string (string_content) @injection.content)])
+  (#match? @_marker "^#\\s*language\\s*=\\s*html\\s*$")
+  (#set! injection.language "html"))
+
+((comment) @_marker
+  .
+  (expression_statement
+    [(assignment right: (string (string_content) @injection.content))
+     (string (string_content) @injection.content)])
+  (#match? @_marker "^#\\s*language\\s*=\\s*css\\s*$")
+  (#set! injection.language "css"))
+
+((comment) @_marker
+  .
+  (expression_statement
+    [(assignment right: (string (string_content) @injection.content))
+     (string (string_content) @injection.content)])
+  (#match? @_marker "^#\\s*language\\s*=\\s*(javascript|js)\\s*$")
+  (#set! injection.language "javascript"))
+"#;
+
+// the c++ and typescript queries only cover what they add to c and javascript,
+// and jsx has its own query. the more specific queries go first so they take priority.
+static CPP_HIGHLIGHTS: LazyLock<String> = LazyLock::new(|| {
+    [
+        tree_sitter_cpp::HIGHLIGHT_QUERY,
+        tree_sitter_c::HIGHLIGHT_QUERY,
+    ]
+    .join("\n")
+});
+static JAVASCRIPT_HIGHLIGHTS: LazyLock<String> = LazyLock::new(|| {
+    [
+        tree_sitter_javascript::JSX_HIGHLIGHT_QUERY,
+        tree_sitter_javascript::HIGHLIGHT_QUERY,
+    ]
+    .join("\n")
+});
+static TYPESCRIPT_HIGHLIGHTS: LazyLock<String> = LazyLock::new(|| {
+    [
+        tree_sitter_typescript::HIGHLIGHTS_QUERY,
+        tree_sitter_javascript::HIGHLIGHT_QUERY,
+    ]
+    .join("\n")
+});
+static TSX_HIGHLIGHTS: LazyLock<String> = LazyLock::new(|| {
+    [
+        tree_sitter_javascript::JSX_HIGHLIGHT_QUERY,
+        tree_sitter_typescript::HIGHLIGHTS_QUERY,
+        tree_sitter_javascript::HIGHLIGHT_QUERY,
+    ]
+    .join("\n")
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use streaming_iterator::StreamingIterator;
+
+    #[test]
+    fn python_strings_are_injected_after_a_marker() {
+        let code = "# language=html\npage = \"<p>hi</p>\"\n# not a marker\nother = \"<p>no</p>\"\n";
+        let ts_lang: tree_sitter::Language = Grammar::Python.ts_lang().into();
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&ts_lang).unwrap();
+        let tree = parser.parse(code, None).unwrap();
+
+        let query = tree_sitter::Query::new(&ts_lang, Grammar::Python.injection_query()).unwrap();
+        let content = query.capture_index_for_name("injection.content").unwrap();
+        let mut cursor = tree_sitter::QueryCursor::new();
+        let mut matches = cursor.matches(&query, tree.root_node(), code.as_bytes());
+
+        let mut injected = vec![];
+        while let Some(query_match) = matches.next() {
+            let language = query.property_settings(query_match.pattern_index)[0]
+                .value
+                .clone();
+            for capture in query_match.nodes_for_capture_index(content) {
+                injected.push((language.clone().unwrap(), &code[capture.byte_range()]));
+            }
+        }
+        assert_eq!(injected, [("html".into(), "<p>hi</p>")]);
+    }
+}
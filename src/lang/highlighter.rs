@@ -2,8 +2,9 @@
 // https://github.com/tree-sitter/tree-sitter/blob/master/highlight/src/lib.rs
 //
 // main changes:
-//   - remove all parsing (take in tree directly)
-//   - removed injections
+//   - take in the tree directly (only injected ranges are parsed)
+//   - the injection callback is passed directly to `highlight_existing_tree`
+//   - removed cancellation
 //   - support for ropey
 
 use ropey::RopeSlice;
@@ -14,9 +15,10 @@ use std::{
     mem::{self, MaybeUninit},
     ops, slice, str,
 };
+use streaming_iterator::StreamingIterator;
 use tree_sitter::{
-    ffi, Language, Node, Point, Query, QueryCapture, QueryCaptures, QueryCursor, QueryError,
-    QueryMatch, Range, TextProvider, Tree,
+    ffi, Language, Node, Parser, Point, Query, QueryCapture, QueryCaptures, QueryCursor,
+    QueryError, QueryMatch, Range, TextProvider, Tree,
 };
 
 /// Indicates which highlight should be applied to a region of source code.
@@ -45,6 +47,8 @@ pub struct HighlightConfiguration {
     highlights_pattern_index: usize,
     highlight_indices: Vec<Option<Highlight>>,
     non_local_variable_patterns: Vec<bool>,
+    injection_content_capture_index: Option<u32>,
+    injection_language_capture_index: Option<u32>,
     local_scope_capture_index: Option<u32>,
     local_def_capture_index: Option<u32>,
     local_def_value_capture_index: Option<u32>,
@@ -57,6 +61,7 @@ pub struct HighlightConfiguration {
 /// syntax highlighting calls. A separate highlighter is needed for each thread that
 /// is performing highlighting.
 pub struct Highlighter {
+    parser: Parser,
     cursors: Vec<QueryCursor>,
 }
 
@@ -74,10 +79,15 @@ struct LocalScope<'a> {
     local_defs: Vec<LocalDef<'a>>,
 }
 
-struct HighlightIter<'a> {
+struct HighlightIter<'a, F>
+where
+    F: FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a,
+{
     source: RopeProvider<'a>,
+    language_name: &'a str,
     byte_offset: usize,
     highlighter: &'a mut Highlighter,
+    injection_callback: F,
     layers: Vec<HighlightIterLayer<'a>>,
     next_event: Option<HighlightEvent>,
     last_highlight_range: Option<(usize, usize, usize)>,
@@ -176,28 +186,58 @@ impl Highlighter {
     #[must_use]
     pub fn new() -> Self {
         Self {
+            parser: Parser::new(),
             cursors: Vec::new(),
         }
     }
 
     /// Iterate over the highlighted regions for a given node.
-    /// Does not parse anything (and therefore does not support injections).
+    ///
+    /// The node's tree is used as is, so only the ranges of injected languages are parsed.
+    /// `injection_callback` finds the configuration for an injected language by name,
+    /// and injections are skipped if it returns `None`.
     pub fn highlight_existing_tree<'a>(
         &'a mut self,
         source: RopeSlice<'a>,
         node: Node,
         config: &'a HighlightConfiguration,
+        mut injection_callback: impl FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a,
     ) -> impl Iterator<Item = HighlightEvent> + 'a {
         let rope_provider = RopeProvider(source);
-        let layers = vec![HighlightIterLayer::new_from_tree(
-            rope_provider,
-            node,
+        let root_layer = HighlightIterLayer::new_from_tree(rope_provider, node, config);
+
+        // the root layer isn't parsed here, so find its combined injections separately
+        let mut cursor = self.cursors.pop().unwrap_or_default();
+        let combined = combined_injections(
             config,
-        )];
+            None,
+            node,
+            rope_provider,
+            &root_layer.ranges,
+            &mut injection_callback,
+            &mut cursor,
+        );
+        self.cursors.push(cursor);
+
+        let mut layers = vec![root_layer];
+        for (injected_config, ranges) in combined {
+            layers.extend(HighlightIterLayer::new(
+                rope_provider,
+                Some(config.language_name.as_str()),
+                self,
+                &mut injection_callback,
+                injected_config,
+                1,
+                ranges,
+            ));
+        }
+
         let mut result = HighlightIter {
             source: rope_provider,
+            language_name: &config.language_name,
             byte_offset: 0,
             highlighter: self,
+            injection_callback,
             layers,
             next_event: None,
             last_highlight_range: None,
@@ -289,9 +329,13 @@ impl HighlightConfiguration {
         let mut local_def_value_capture_index = None;
         let mut local_ref_capture_index = None;
         let mut local_scope_capture_index = None;
+        let mut injection_content_capture_index = None;
+        let mut injection_language_capture_index = None;
         for (i, name) in query.capture_names().iter().enumerate() {
             let i = Some(i as u32);
             match *name {
+                "injection.content" => injection_content_capture_index = i,
+                "injection.language" => injection_language_capture_index = i,
                 "local.definition" => local_def_capture_index = i,
                 "local.definition-value" => local_def_value_capture_index = i,
                 "local.reference" => local_ref_capture_index = i,
@@ -310,6 +354,8 @@ impl HighlightConfiguration {
             highlights_pattern_index,
             highlight_indices,
             non_local_variable_patterns,
+            injection_content_capture_index,
+            injection_language_capture_index,
             local_def_capture_index,
             local_def_value_capture_index,
             local_ref_capture_index,
@@ -358,6 +404,81 @@ impl HighlightConfiguration {
 }
 
 impl<'a> HighlightIterLayer<'a> {
+    /// Parse the ranges of an injected language, creating a layer for it
+    /// and for any combined injections inside of it.
+    fn new<F: FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a>(
+        source: RopeProvider<'a>,
+        parent_name: Option<&'a str>,
+        highlighter: &mut Highlighter,
+        injection_callback: &mut F,
+        mut config: &'a HighlightConfiguration,
+        mut depth: usize,
+        mut ranges: Vec<Range>,
+    ) -> Vec<Self> {
+        let mut result = Vec::with_capacity(1);
+        let mut queue = Vec::new();
+        loop {
+            if highlighter.parser.set_included_ranges(&ranges).is_ok()
+                && highlighter.parser.set_language(&config.language).is_ok()
+            {
+                if let Some(tree) = parse_rope(&mut highlighter.parser, source.0) {
+                    let mut cursor = highlighter.cursors.pop().unwrap_or_default();
+
+                    queue.extend(
+                        combined_injections(
+                            config,
+                            parent_name,
+                            tree.root_node(),
+                            source,
+                            &ranges,
+                            injection_callback,
+                            &mut cursor,
+                        )
+                        .into_iter()
+                        .map(|(config, ranges)| (config, depth + 1, ranges)),
+                    );
+
+                    // The `captures` iterator borrows the `Tree` and the `QueryCursor`, which
+                    // prevents them from being moved. But both of these values are really just
+                    // pointers, so it's actually ok to move them.
+                    let tree_ref = unsafe { mem::transmute::<&Tree, &'static Tree>(&tree) };
+                    let cursor_ref = unsafe {
+                        mem::transmute::<&mut QueryCursor, &'static mut QueryCursor>(&mut cursor)
+                    };
+                    let captures = unsafe {
+                        std::mem::transmute::<QueryCaptures<_, _>, _QueryCaptures<_, _>>(
+                            cursor_ref.captures(&config.query, tree_ref.root_node(), source),
+                        )
+                    }
+                    .peekable();
+
+                    result.push(HighlightIterLayer {
+                        highlight_end_stack: Vec::new(),
+                        scope_stack: vec![LocalScope {
+                            inherits: false,
+                            range: 0..usize::MAX,
+                            local_defs: Vec::new(),
+                        }],
+                        cursor,
+                        depth,
+                        _tree: Some(tree),
+                        captures,
+                        config,
+                        ranges,
+                    });
+                }
+            }
+
+            if queue.is_empty() {
+                break;
+            }
+
+            (config, depth, ranges) = queue.remove(0);
+        }
+
+        result
+    }
+
     fn new_from_tree(
         source: RopeProvider<'a>,
         node: Node,
@@ -398,6 +519,100 @@ impl<'a> HighlightIterLayer<'a> {
         }
     }
 
+    // Compute the ranges that should be included when parsing an injection.
+    // This takes into account three things:
+    // * `parent_ranges` - The ranges must all fall within the *current* layer's ranges.
+    // * `nodes` - Every injection takes place within a set of nodes. The injection ranges
+    //   are the ranges of those nodes.
+    // * `includes_children` - For some injections, the content nodes' children should be
+    //   excluded from the nested document, so that only the content nodes' *own* content
+    //   is reparsed. For other injections, the content nodes' entire ranges should be
+    //   reparsed, including the ranges of their children.
+    fn intersect_ranges(
+        parent_ranges: &[Range],
+        nodes: &[Node],
+        includes_children: bool,
+    ) -> Vec<Range> {
+        let mut cursor = nodes[0].walk();
+        let mut result = Vec::new();
+        let mut parent_range_iter = parent_ranges.iter();
+        let mut parent_range = parent_range_iter
+            .next()
+            .expect("Layers should only be constructed with non-empty ranges vectors");
+        for node in nodes {
+            let mut preceding_range = Range {
+                start_byte: 0,
+                start_point: Point::new(0, 0),
+                end_byte: node.start_byte(),
+                end_point: node.start_position(),
+            };
+            let following_range = Range {
+                start_byte: node.end_byte(),
+                start_point: node.end_position(),
+                end_byte: usize::MAX,
+                end_point: Point::new(usize::MAX, usize::MAX),
+            };
+
+            for excluded_range in node
+                .children(&mut cursor)
+                .filter_map(|child| {
+                    if includes_children {
+                        None
+                    } else {
+                        Some(child.range())
+                    }
+                })
+                .chain(iter::once(following_range))
+            {
+                let mut range = Range {
+                    start_byte: preceding_range.end_byte,
+                    start_point: preceding_range.end_point,
+                    end_byte: excluded_range.start_byte,
+                    end_point: excluded_range.start_point,
+                };
+                preceding_range = excluded_range;
+
+                if range.end_byte < parent_range.start_byte {
+                    continue;
+                }
+
+                while parent_range.start_byte <= range.end_byte {
+                    if parent_range.end_byte > range.start_byte {
+                        if range.start_byte < parent_range.start_byte {
+                            range.start_byte = parent_range.start_byte;
+                            range.start_point = parent_range.start_point;
+                        }
+
+                        if parent_range.end_byte < range.end_byte {
+                            if range.start_byte < parent_range.end_byte {
+                                result.push(Range {
+                                    start_byte: range.start_byte,
+                                    start_point: range.start_point,
+                                    end_byte: parent_range.end_byte,
+                                    end_point: parent_range.end_point,
+                                });
+                            }
+                            range.start_byte = parent_range.end_byte;
+                            range.start_point = parent_range.end_point;
+                        } else {
+                            if range.start_byte < range.end_byte {
+                                result.push(range);
+                            }
+                            break;
+                        }
+                    }
+
+                    if let Some(next_range) = parent_range_iter.next() {
+                        parent_range = next_range;
+                    } else {
+                        return result;
+                    }
+                }
+            }
+        }
+        result
+    }
+
     // First, sort scope boundaries by their byte offset in the document. At a
     // given position, emit scope endings before scope beginnings. Finally, emit
     // scope boundaries from deeper layers first.
@@ -423,7 +638,10 @@ impl<'a> HighlightIterLayer<'a> {
     }
 }
 
-impl HighlightIter<'_> {
+impl<'a, F> HighlightIter<'a, F>
+where
+    F: FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a,
+{
     fn emit_event(
         &mut self,
         offset: usize,
@@ -466,9 +684,30 @@ impl HighlightIter<'_> {
             self.highlighter.cursors.push(layer.cursor);
         }
     }
+
+    fn insert_layer(&mut self, mut layer: HighlightIterLayer<'a>) {
+        if let Some(sort_key) = layer.sort_key() {
+            let mut i = 1;
+            while i < self.layers.len() {
+                if let Some(sort_key_i) = self.layers[i].sort_key() {
+                    if sort_key_i > sort_key {
+                        self.layers.insert(i, layer);
+                        return;
+                    }
+                    i += 1;
+                } else {
+                    self.layers.remove(i);
+                }
+            }
+            self.layers.push(layer);
+        }
+    }
 }
 
-impl Iterator for HighlightIter<'_> {
+impl<'a, F> Iterator for HighlightIter<'a, F>
+where
+    F: FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a,
+{
     type Item = HighlightEvent;
 
     #[allow(unused_assignments)]
@@ -523,6 +762,50 @@ impl Iterator for HighlightIter<'_> {
             let (mut match_, capture_index) = layer.captures.next().unwrap();
             let mut capture = match_.captures[capture_index];
 
+            // If this capture represents an injection, then process the injection.
+            if match_.pattern_index < layer.config.locals_pattern_index {
+                let (language_name, content_node, include_children) = injection_for_match(
+                    layer.config,
+                    Some(self.language_name),
+                    &layer.config.query,
+                    &match_,
+                    self.source,
+                );
+
+                // Explicitly remove this match so that none of its other captures will remain
+                // in the stream of captures.
+                match_.remove();
+
+                // If a language is found with the given name, then add a new language layer
+                // to the highlighted document.
+                if let (Some(language_name), Some(content_node)) = (language_name, content_node) {
+                    if let Some(config) = (self.injection_callback)(&language_name) {
+                        let ranges = HighlightIterLayer::intersect_ranges(
+                            &self.layers[0].ranges,
+                            &[content_node],
+                            include_children,
+                        );
+                        if !ranges.is_empty() {
+                            let layers = HighlightIterLayer::new(
+                                self.source,
+                                Some(self.language_name),
+                                self.highlighter,
+                                &mut self.injection_callback,
+                                config,
+                                self.layers[0].depth + 1,
+                                ranges,
+                            );
+                            for layer in layers {
+                                self.insert_layer(layer);
+                            }
+                        }
+                    }
+                }
+
+                self.sort_layers();
+                continue 'main;
+            }
+
             // Remove from the local scope stack any local scopes that have already ended.
             while range.start > layer.scope_stack.last().unwrap().range.end {
                 layer.scope_stack.pop();
@@ -666,9 +949,139 @@ impl Iterator for HighlightIter<'_> {
     }
 }
 
+/// Find the combined injections (`#set! injection.combined`) under a node,
+/// along with the ranges to parse for each one
+fn combined_injections<'a>(
+    config: &'a HighlightConfiguration,
+    parent_name: Option<&'a str>,
+    node: Node,
+    source: RopeProvider<'a>,
+    parent_ranges: &[Range],
+    injection_callback: &mut impl FnMut(&str) -> Option<&'a HighlightConfiguration>,
+    cursor: &mut QueryCursor,
+) -> Vec<(&'a HighlightConfiguration, Vec<Range>)> {
+    let Some(combined_injections_query) = &config.combined_injections_query else {
+        return vec![];
+    };
+
+    let mut injections_by_pattern_index =
+        vec![(None, Vec::new(), false); combined_injections_query.pattern_count()];
+    let mut matches = cursor.matches(combined_injections_query, node, source);
+    while let Some(mat) = matches.next() {
+        let entry = &mut injections_by_pattern_index[mat.pattern_index];
+        let (language_name, content_node, include_children) =
+            injection_for_match(config, parent_name, combined_injections_query, mat, source);
+        if language_name.is_some() {
+            entry.0 = language_name.map(Cow::into_owned);
+        }
+        if let Some(content_node) = content_node {
+            entry.1.push(content_node);
+        }
+        entry.2 = include_children;
+    }
+
+    let mut result = vec![];
+    for (lang_name, content_nodes, includes_children) in injections_by_pattern_index {
+        if let (Some(lang_name), false) = (lang_name, content_nodes.is_empty()) {
+            if let Some(next_config) = injection_callback(&lang_name) {
+                let ranges = HighlightIterLayer::intersect_ranges(
+                    parent_ranges,
+                    &content_nodes,
+                    includes_children,
+                );
+                if !ranges.is_empty() {
+                    result.push((next_config, ranges));
+                }
+            }
+        }
+    }
+    result
+}
+
+fn injection_for_match<'a>(
+    config: &'a HighlightConfiguration,
+    parent_name: Option<&'a str>,
+    query: &'a Query,
+    query_match: &QueryMatch<'a, 'a>,
+    source: RopeProvider<'a>,
+) -> (Option<Cow<'a, str>>, Option<Node<'a>>, bool) {
+    let content_capture_index = config.injection_content_capture_index;
+    let language_capture_index = config.injection_language_capture_index;
+
+    let mut language_name = None;
+    let mut content_node = None;
+
+    for capture in query_match.captures {
+        let index = Some(capture.index);
+        if index == language_capture_index {
+            language_name = Some(Cow::from(source.0.byte_slice(capture.node.byte_range())));
+        } else if index == content_capture_index {
+            content_node = Some(capture.node);
+        }
+    }
+
+    let mut include_children = false;
+    for prop in query.property_settings(query_match.pattern_index) {
+        match prop.key.as_ref() {
+            // In addition to specifying the language name via the text of a
+            // captured node, it can also be hard-coded via a `#set!` predicate
+            // that sets the injection.language key.
+            "injection.language" => {
+                if language_name.is_none() {
+                    language_name = prop.value.as_deref().map(Cow::Borrowed);
+                }
+            }
+
+            // Setting the `injection.self` key can be used to specify that the
+            // language name should be the same as the language of the current
+            // layer.
+            "injection.self" => {
+                if language_name.is_none() {
+                    language_name = Some(Cow::Borrowed(config.language_name.as_str()));
+                }
+            }
+
+            // Setting the `injection.parent` key can be used to specify that
+            // the language name should be the same as the language of the
+            // parent layer
+            "injection.parent" => {
+                if language_name.is_none() {
+                    language_name = parent_name.map(Cow::Borrowed);
+                }
+            }
+
+            // By default, injections do not include the *children* of an
+            // `injection.content` node - only the ranges that belong to the
+            // node itself. This can be changed using a `#set!` predicate that
+            // sets the `injection.include-children` key.
+            "injection.include-children" => include_children = true,
+            _ => {}
+        }
+    }
+
+    (language_name, content_node, include_children)
+}
+
+/// Parse the text of a rope, respecting the parser's included ranges
+fn parse_rope(parser: &mut Parser, source: RopeSlice) -> Option<Tree> {
+    parser.parse_with_options(
+        &mut |byte, _| {
+            if byte < source.len_bytes() {
+                let (chunk, start_byte, _, _) = source.chunk_at_byte(byte);
+                &chunk.as_bytes()[byte - start_byte..]
+            } else {
+                // out of range
+                &[]
+            }
+        },
+        None,
+        None,
+    )
+}
+
 /* ------- Rope + TS Text Provider  ------- */
 #[derive(Clone, Copy)]
-struct RopeProvider<'a>(pub RopeSlice<'a>);
+pub(crate) struct RopeProvider<'a>(pub RopeSlice<'a>);
 
 impl<'a> TextProvider<&'a [u8]> for RopeProvider<'a> {
     type I = ChunksBytes<'a>;
@@ -682,7 +1095,7 @@ impl<'a> TextProvider<&'a [u8]> for RopeProvider<'a> {
 }
 
 /// shim to convert rope chunks to bytes
-struct ChunksBytes<'a> {
+pub(crate) struct ChunksBytes<'a> {
     chunks: ropey::iter::Chunks<'a>,
 }
 
@@ -0,0 +1,237 @@
+use ropey::Rope;
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Node, Point, Query, QueryCursor};
+
+use super::{config::LoadError, highlighter::RopeProvider};
+
+/// The meaning of a capture name in an indents query
+#[derive(Clone, Copy, PartialEq)]
+enum IndentCapture {
+    /// `@indent.begin`: the lines inside the node (after its first line) are indented a level
+    Begin,
+
+    /// `@indent.branch`: a line starting with the node lines up with the scope around it,
+    /// like `}` or `else`
+    Branch,
+
+    /// `@indent.dedent`: a statement that ends its block (like `return`),
+    /// so the line after it is dedented
+    Dedent,
+
+    /// `@_<name>`: only used by predicates
+    Ignore,
+}
+
+/// A compiled indents query (see `src/lang/definitions/<name>.indents.scm`)
+pub struct IndentsQuery {
+    query: Query,
+
+    /// What each capture does, indexed by capture index
+    captures: Vec<IndentCapture>,
+}
+
+/// What an indents query says about inserting a newline at a position
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct NewlineIndent {
+    /// A scope starts on the line and continues past the position, so the new line is indented
+    pub opens_scope: bool,
+
+    /// The text after the position closes the scope that was opened (like the `}` of `{}`),
+    /// so it gets moved to its own line after the new one
+    pub closes_scope: bool,
+
+    /// The scope that was opened is missing its closing text (like an unmatched `{`),
+    /// so it should be inserted on its own line after the new one
+    pub missing_close: Option<&'static str>,
+
+    /// The line is a statement that ends its block (like `return`), so the new line is dedented
+    pub ends_block: bool,
+}
+
+impl IndentsQuery {
+    pub(super) fn new(ts_lang: &tree_sitter::Language, source: &str) -> Result<Self, LoadError> {
+        let query = Query::new(ts_lang, source).map_err(LoadError::IndentsQuery)?;
+
+        let captures = query
+            .capture_names()
+            .iter()
+            .map(|name| match *name {
+                "indent.begin" => Ok(IndentCapture::Begin),
+                "indent.branch" => Ok(IndentCapture::Branch),
+                "indent.dedent" => Ok(IndentCapture::Dedent),
+                _ if name.starts_with('_') => Ok(IndentCapture::Ignore),
+                _ => Err(LoadError::UnknownIndentCapture(name.to_string())),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(IndentsQuery { query, captures })
+    }
+
+    /// Find what the query says about inserting a newline at `byte`
+    pub fn newline_at(&self, root: Node, source: &Rope, byte: usize) -> NewlineIndent {
+        let row = source.byte_to_line(byte);
+        let line_start = line_start_byte(source, row);
+
+        let mut indent = NewlineIndent::default();
+        let mut branch_after = false;
+        for (node, capture) in self.captures_on_row(root, source, row) {
+            match capture {
+                IndentCapture::Begin => {
+                    // a scope missing its closing text runs to the end of the file,
+                    // so it can end right at the position
+                    let missing_close = node
+                        .child(node.child_count().saturating_sub(1))
+                        .filter(|last| last.is_missing());
+                    let continues = byte < node.end_byte() || missing_close.is_some();
+
+                    if node.start_position().row == row && node.start_byte() < byte && continues {
+                        indent.opens_scope = true;
+                        if let Some(missing_close) = missing_close {
+                            indent.missing_close = Some(missing_close.kind());
+                        }
+                    }
+                }
+                IndentCapture::Branch => branch_after |= node.start_byte() == byte,
+                IndentCapture::Dedent => {
+                    // only statements that take up the line, not ones after a header (`if a: pass`)
+                    indent.ends_block |= node.start_byte() == line_start
+                        && node.end_position().row == row
+                        && node.end_byte() <= byte;
+                }
+                IndentCapture::Ignore => {}
+            }
+        }
+        indent.closes_scope = indent.opens_scope && branch_after;
+        indent
+    }
+
+    /// If the line starts with a branch (like `else` or `}`),
+    /// find the row of the scope it should line up with
+    pub fn branch_scope_row(&self, root: Node, source: &Rope, row: usize) -> Option<usize> {
+        let line_start = line_start_byte(source, row);
+        let captures = self.captures_on_row(root, source, row);
+
+        let (branch, _) = captures.iter().find(|(node, capture)| {
+            *capture == IndentCapture::Branch && node.start_byte() == line_start
+        })?;
+
+        // the closest scope around the branch that starts on an earlier line
+        captures
+            .iter()
+            .filter(|(node, capture)| {
+                *capture == IndentCapture::Begin
+                    && node.start_position().row < row
+                    && node.start_byte() <= branch.start_byte()
+                    && branch.end_byte() <= node.end_byte()
+            })
+            .map(|(node, _)| node.start_byte())
+            .max()
+            .map(|start| source.byte_to_line(start))
+    }
+
+    /// All the nodes captured by the query that are on the row
+    fn captures_on_row<'tree>(
+        &self,
+        root: Node<'tree>,
+        source: &Rope,
+        row: usize,
+    ) -> Vec<(Node<'tree>, IndentCapture)> {
+        let mut cursor = QueryCursor::new();
+        cursor.set_point_range(Point::new(row, 0)..Point::new(row + 1, 0));
+
+        let mut found = vec![];
+        let mut captures = cursor.captures(&self.query, root, RopeProvider(source.slice(..)));
+        while let Some((query_match, index)) = captures.next() {
+            let capture = query_match.captures[*index];
+            match self.captures[capture.index as usize] {
+                IndentCapture::Ignore => {}
+                indent_capture => found.push((capture.node, indent_capture)),
+            }
+        }
+        found
+    }
+}
+
+/// The byte of the first character after the indent of the row
+fn line_start_byte(source: &Rope, row: usize) -> usize {
+    let indent: usize = source
+        .line(row)
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(char::len_utf8)
+        .sum();
+    source.line_to_byte(row) + indent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::config::LanguageConfig;
+
+    /// Find the newline indent at the `|` in the code
+    fn newline_at(file_name: &str, code: &str) -> NewlineIndent {
+        let byte = code.find('|').unwrap();
+        let code = code.replace('|', "");
+
+        let config = LanguageConfig::for_file(file_name);
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&config.tree_sitter().unwrap()).unwrap();
+        let tree = parser.parse(&code, None).unwrap();
+
+        let rope = Rope::from_str(&code);
+        config.newline_indent(tree.root_node(), &rope, byte)
+    }
+
+    #[test]
+    fn scopes_open_and_close() {
+        let opened = newline_at("main.rs", "fn a() {|}\n");
+        assert!(opened.opens_scope && opened.closes_scope);
+        assert_eq!(opened.missing_close, None);
+
+        let args = newline_at("main.rs", "fn a() {\n    b(|);\n}\n");
+        assert!(args.opens_scope && args.closes_scope);
+
+        let after = newline_at("main.rs", "fn a() {}|\n");
+        assert!(!after.opens_scope && !after.closes_scope);
+    }
+
+    #[test]
+    fn missing_braces_are_found() {
+        let unclosed = newline_at("main.c", "int main() {|");
+        assert!(unclosed.opens_scope);
+        assert_eq!(unclosed.missing_close, Some("}"));
+
+        // the rust grammar makes the rest of the file an error instead
+        let unclosed = newline_at("main.rs", "fn main() {|\n");
+        assert!(unclosed.opens_scope);
+        assert_eq!(unclosed.missing_close, Some("}"));
+    }
+
+    #[test]
+    fn branches_line_up_with_scope() {
+        let branch_scope_line = |file_name: &str, code: &str, line: usize| {
+            let config = LanguageConfig::for_file(file_name);
+            let mut parser = tree_sitter::Parser::new();
+            parser.set_language(&config.tree_sitter().unwrap()).unwrap();
+            let tree = parser.parse(code, None).unwrap();
+            config.branch_scope_line(tree.root_node(), &Rope::from_str(code), line)
+        };
+
+        let rust = "fn a() {\n    if b {\n        c();\n        }\n}\n";
+        assert_eq!(branch_scope_line("main.rs", rust, 3), Some(1));
+        assert_eq!(branch_scope_line("main.rs", rust, 2), None);
+
+        // the else is still being typed inside the if's block
+        let python = "def f():\n    if a:\n        b\n        else:\n";
+        assert_eq!(branch_scope_line("test.py", python, 3), Some(1));
+    }
+
+    #[test]
+    fn returns_end_blocks() {
+        let code = "def f():\n    return 1|\n";
+        assert!(newline_at("test.py", code).ends_block);
+
+        let one_line = "if a: pass|\n";
+        assert!(!newline_at("test.py", one_line).ends_block);
+    }
+}
@@ -0,0 +1,55 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use super::{config::LanguageConfig, registry, Language};
+
+thread_local! {
+    /// Languages created for injections, keyed by the address of their config.
+    ///
+    /// These are leaked so that highlighting can keep a reference to them while it runs.
+    /// There is at most one per registered config (which are also leaked), so this is bounded.
+    static LANGUAGES: RefCell<HashMap<usize, &'static Language>> = RefCell::new(HashMap::new());
+}
+
+/// Find the language for a name used by an injection, like `javascript` or `js`.
+/// Returns `None` if no registered language with a grammar matches.
+pub fn resolve(name: &str) -> Option<&'static Language> {
+    let config = registry::for_name(name)?;
+    let key = config as *const LanguageConfig as usize;
+
+    LANGUAGES.with_borrow_mut(|languages| {
+        if let Some(lang) = languages.get(&key) {
+            return Some(*lang);
+        }
+
+        // registered configs are checked when they are added, so this only fails for plain text
+        let lang = Language::new(config)
+            .ok()
+            .filter(|lang| lang.syntax.is_some())?;
+        let lang: &'static Language = Box::leak(Box::new(lang));
+        languages.insert(key, lang);
+        Some(lang)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_resolve_to_languages() {
+        assert_eq!(resolve("javascript").unwrap().config.name, "javascript");
+        assert_eq!(resolve("CSS").unwrap().config.name, "css");
+        assert_eq!(resolve("js").unwrap().config.name, "javascript");
+        assert_eq!(resolve("c++").unwrap().config.name, "cpp");
+        assert_eq!(resolve("hpp").unwrap().config.name, "cpp");
+        assert!(resolve("plain text").is_none());
+        assert!(resolve("not a language").is_none());
+    }
+
+    #[test]
+    fn languages_are_reused() {
+        let first = resolve("python").unwrap();
+        let second = resolve("py").unwrap();
+        assert!(std::ptr::eq(first, second));
+    }
+}
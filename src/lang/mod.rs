@@ -1,47 +1,98 @@
 pub mod config;
+mod detection;
+mod grammar;
 pub mod highlighter;
+pub mod indents;
+pub mod injections;
+pub mod registry;
 pub mod tree_manager;
+mod validation;
 
 use std::cell::RefCell;
 
 use config::LanguageConfig;
+pub use validation::{LanguageError, StringNodeIDs};
 
 pub struct Language {
     pub config: &'static config::LanguageConfig,
+
+    /// parsing and highlighting for the grammar. None if the language doesn't have one
+    pub syntax: Option<Syntax>,
+}
+
+pub struct Syntax {
     _ts_language: tree_sitter::Language,
     pub parser: tree_sitter::Parser,
     pub highlighter: RefCell<highlighter::Highlighter>, // use ref cells because you can only have one mutable reference to a property of a struct at a time
-    pub highlight_config: RefCell<highlighter::HighlightConfiguration>,
+    pub highlight_config: highlighter::HighlightConfiguration,
+
+    /// the string node kinds from the config, resolved for the grammar
+    pub string_node_ids: Option<StringNodeIDs>,
 }
 
 impl Language {
     pub fn for_file(file_name: &str) -> Self {
         Self::new(LanguageConfig::for_file(file_name))
+            .expect("registered languages should be valid")
+    }
+
+    /// Find the language for a file, using its contents if the extension is ambiguous
+    pub fn detect(file_name: &str, contents: &str) -> Self {
+        Self::new(detection::detect(file_name, contents))
+            .expect("registered languages should be valid")
+    }
+
+    /// Create a language from a config, checking the config against its grammar
+    pub fn new(config: &'static config::LanguageConfig) -> Result<Self, LanguageError> {
+        let syntax = Syntax::for_config(config)?;
+        Ok(Self { config, syntax })
     }
+}
 
-    fn new(config: &'static config::LanguageConfig) -> Self {
-        let ts_language = config.tree_sitter();
+impl Syntax {
+    /// Create the syntax for a config, checking the config against its grammar.
+    /// Returns `None` if the language doesn't have a grammar.
+    pub(super) fn for_config(config: &LanguageConfig) -> Result<Option<Self>, LanguageError> {
+        match (config.tree_sitter(), config.highlight_query()) {
+            (Some(ts_language), Some(highlight_query)) => {
+                Ok(Some(Syntax::new(config, ts_language, highlight_query)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn new(
+        config: &LanguageConfig,
+        ts_language: tree_sitter::Language,
+        highlight_query: &str,
+    ) -> Result<Self, LanguageError> {
+        let string_node_ids = config
+            .string_nodes
+            .as_ref()
+            .map(|kinds| StringNodeIDs::resolve(kinds, &ts_language))
+            .transpose()?;
 
         let mut parser = tree_sitter::Parser::new();
         parser.set_language(&ts_language).unwrap();
 
         let mut highlight_config = highlighter::HighlightConfiguration::new(
-            config.tree_sitter(),
-            config.name,
-            config.highlight_query,
-            "",
+            ts_language.clone(),
+            config.name.as_str(),
+            highlight_query,
+            config.injection_query(),
             "",
         )
-        .unwrap();
+        .map_err(LanguageError::HighlightQuery)?;
+        validation::check_highlights(config, &highlight_config.query)?;
         highlight_config.configure(&config.highlight.iter().map(|x| x.0).collect::<Vec<&str>>());
 
         let highlighter = highlighter::Highlighter::new();
-        Self {
-            config,
+        Ok(Self {
             _ts_language: ts_language,
             parser,
             highlighter: RefCell::new(highlighter),
-            highlight_config: RefCell::new(highlight_config),
-        }
+            highlight_config,
+            string_node_ids,
+        })
     }
 }
@@ -0,0 +1,290 @@
+use std::sync::{LazyLock, Mutex};
+
+use super::{
+    config::{LanguageConfig, PLAIN_TEXT},
+    Language, LanguageError, Syntax,
+};
+
+/// A language definition that ships with Lilypad
+struct BuiltIn {
+    /// The file name (without extension) in `src/lang/definitions`
+    file_name: &'static str,
+    definition: &'static str,
+    blocks_query: &'static str,
+    indents_query: &'static str,
+}
+
+macro_rules! built_in {
+    ($file_name:literal) => {
+        BuiltIn {
+            file_name: $file_name,
+            definition: include_str!(concat!("definitions/", $file_name, ".json")),
+            blocks_query: include_str!(concat!("definitions/", $file_name, ".blocks.scm")),
+            indents_query: include_str!(concat!("definitions/", $file_name, ".indents.scm")),
+        }
+    };
+}
+
+const BUILT_IN_DEFINITIONS: &[BuiltIn] = &[
+    built_in!("python"),
+    built_in!("java"),
+    built_in!("c_sharp"),
+    built_in!("c"),
+    built_in!("cpp"),
+    built_in!("rust"),
+    built_in!("go"),
+    built_in!("javascript"),
+    built_in!("typescript"),
+    built_in!("tsx"),
+    built_in!("html"),
+    built_in!("css"),
+];
+
+/// All known languages. Later entries take priority when matching a file.
+///
+/// Configs are leaked so that languages can keep a static reference to them.
+/// They are kept even if they get replaced (since languages using them could still be around),
+/// but registering a definition that was loaded before reuses its config,
+/// so only each distinct definition is leaked.
+static LANGUAGES: LazyLock<Mutex<Vec<&'static LanguageConfig>>> = LazyLock::new(|| {
+    let built_ins = BUILT_IN_DEFINITIONS.iter().map(|built_in| {
+        LanguageConfig::load(
+            built_in.definition,
+            built_in.blocks_query,
+            built_in.indents_query,
+        )
+        .expect("built-in language definitions should be valid")
+    });
+    let languages = std::iter::once(LanguageConfig::plain_text())
+        .chain(built_ins)
+        .map(|config| &*Box::leak(Box::new(config)))
+        .collect();
+    Mutex::new(languages)
+});
+
+/// Configs that were replaced by registering another definition with the same name,
+/// which are reused if their definition is registered again
+static REPLACED: LazyLock<Mutex<Vec<&'static LanguageConfig>>> =
+    LazyLock::new(|| Mutex::new(vec![]));
+
+/// Find the language to use for a file based on its extension,
+/// falling back to plain text if no language matches
+pub fn for_file(file_name: &str) -> &'static LanguageConfig {
+    let extension = file_name.split('.').next_back().unwrap_or("");
+    let languages = LANGUAGES.lock().unwrap();
+    languages
+        .iter()
+        .rev()
+        .find(|lang| lang.extensions.iter().any(|ext| ext == extension))
+        .or_else(|| languages.iter().find(|lang| lang.name == PLAIN_TEXT))
+        .copied()
+        .expect("plain text should always be registered")
+}
+
+/// Find a registered language by name
+pub fn by_name(name: &str) -> Option<&'static LanguageConfig> {
+    let languages = LANGUAGES.lock().unwrap();
+    languages.iter().find(|lang| lang.name == name).copied()
+}
+
+/// Other names that are used for languages (by injection queries, modelines, and shebangs),
+/// and the names of the registered languages they refer to
+const ALIASES: &[(&str, &str)] = &[
+    ("js", "javascript"),
+    ("jsx", "javascript"),
+    ("javascriptreact", "javascript"),
+    ("node", "javascript"),
+    ("ts", "typescript"),
+    ("typescriptreact", "tsx"),
+    ("py", "python"),
+    ("rs", "rust"),
+    ("c++", "cpp"),
+    ("cs", "c#"),
+    ("csharp", "c#"),
+    ("c_sharp", "c#"),
+    ("golang", "go"),
+    ("text", PLAIN_TEXT),
+];
+
+/// Find a registered language by a looser name: its name, a common alias,
+/// or one of its file extensions (case insensitive)
+pub fn for_name(name: &str) -> Option<&'static LanguageConfig> {
+    let name = name.trim().to_lowercase();
+    let name = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name.as_str(), |(_, target)| target);
+
+    by_name(name).or_else(|| {
+        let config = for_file(&format!("file.{name}"));
+        (config.name != PLAIN_TEXT).then_some(config)
+    })
+}
+
+/// Get the blocks query of a built-in definition by its file name, for `; inherits:`
+pub(super) fn built_in_blocks_query(file_name: &str) -> Option<&'static str> {
+    BUILT_IN_DEFINITIONS
+        .iter()
+        .find(|built_in| built_in.file_name == file_name)
+        .map(|built_in| built_in.blocks_query)
+}
+
+/// Get the indents query of a built-in definition by its file name, for `; inherits:`
+pub(super) fn built_in_indents_query(file_name: &str) -> Option<&'static str> {
+    BUILT_IN_DEFINITIONS
+        .iter()
+        .find(|built_in| built_in.file_name == file_name)
+        .map(|built_in| built_in.indents_query)
+}
+
+/// All registered languages, in registration order
+pub fn all() -> Vec<&'static LanguageConfig> {
+    LANGUAGES.lock().unwrap().clone()
+}
+
+/// Add a language definition, replacing any existing definition with the same name.
+/// The definition is only added if it is valid for its grammar.
+pub fn register(config: LanguageConfig) -> Result<Language, LanguageError> {
+    let loaded = {
+        let languages = LANGUAGES.lock().unwrap();
+        let replaced = REPLACED.lock().unwrap();
+        languages
+            .iter()
+            .chain(replaced.iter())
+            .find(|loaded| loaded.is_same_definition(&config))
+            .copied()
+    };
+
+    let lang = match loaded {
+        Some(config) => Language::new(config)?,
+        None => {
+            // check the definition before leaking it, so invalid ones are dropped
+            let syntax = Syntax::for_config(&config)?;
+            let config: &'static LanguageConfig = Box::leak(Box::new(config));
+            Language { config, syntax }
+        }
+    };
+    let config = lang.config;
+
+    let mut languages = LANGUAGES.lock().unwrap();
+    let mut replaced = REPLACED.lock().unwrap();
+    replaced.retain(|old| !std::ptr::eq(*old, config));
+    for old in languages.iter().filter(|old| old.name == config.name) {
+        if !std::ptr::eq(*old, config) {
+            replaced.push(old);
+        }
+    }
+    languages.retain(|lang| lang.name != config.name);
+    languages.push(config);
+
+    Ok(lang)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_definitions_load() {
+        for built_in in BUILT_IN_DEFINITIONS {
+            let loaded = LanguageConfig::load(
+                built_in.definition,
+                built_in.blocks_query,
+                built_in.indents_query,
+            );
+            if let Err(err) = loaded {
+                panic!("invalid built-in definition {}: {err}", built_in.file_name);
+            }
+        }
+    }
+
+    #[test]
+    fn blocks_queries_inherit_built_ins() {
+        let python = &BUILT_IN_DEFINITIONS[0];
+        let query = "; inherits: python\n(function_definition) @block.none\n";
+        let config = LanguageConfig::load(python.definition, query, python.indents_query).unwrap();
+
+        let code = "def f():\n    pass\n";
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&config.tree_sitter().unwrap()).unwrap();
+        let tree = parser.parse(code, None).unwrap();
+        let categories = config.categorize_tree(tree.root_node(), &ropey::Rope::from_str(code));
+
+        // the function is overridden, but the inherited pass statement is still a block
+        let blocks: Vec<_> = categories.into_values().collect();
+        assert_eq!(blocks, [crate::block_editor::BlockType::Generic]);
+    }
+
+    #[test]
+    fn invalid_definitions_are_not_registered() {
+        let python = &BUILT_IN_DEFINITIONS[0];
+        let mut config =
+            LanguageConfig::load(python.definition, python.blocks_query, python.indents_query)
+                .unwrap();
+        config.name = "broken python".to_string();
+        config.string_nodes = Some(crate::lang::config::StringNodeKinds {
+            string: "not_a_real_node".to_string(),
+            string_bounds: vec![],
+        });
+
+        assert!(register(config).is_err());
+        assert!(by_name("broken python").is_none());
+    }
+
+    #[test]
+    fn registering_a_definition_again_reuses_it() {
+        let python = &BUILT_IN_DEFINITIONS[0];
+        let name = "\"name\": \"python\"";
+        let load = |blocks_query: &str| {
+            // with its own extension, so other tests still find python for .py files
+            let definition = python
+                .definition
+                .replacen(name, "\"name\": \"reused python\"", 1)
+                .replacen("[\"py\"]", "[\"reusedpy\"]", 1);
+            LanguageConfig::load(&definition, blocks_query, python.indents_query).unwrap()
+        };
+        let first = register(load(python.blocks_query)).unwrap().config;
+        let again = register(load(python.blocks_query)).unwrap().config;
+        assert!(std::ptr::eq(first, again));
+
+        // a replaced definition is still reused when it comes back
+        let other = register(load("; inherits: python\n")).unwrap().config;
+        assert!(!std::ptr::eq(first, other));
+        let back = register(load(python.blocks_query)).unwrap().config;
+        assert!(std::ptr::eq(first, back));
+        assert!(std::ptr::eq(by_name("reused python").unwrap(), first));
+    }
+
+    #[test]
+    fn files_match_extensions() {
+        assert_eq!(for_file("main.py").name, "python");
+        assert_eq!(for_file("Main.java").name, "java");
+        assert_eq!(for_file("Program.cs").name, "c#");
+        assert_eq!(for_file("test.cpp").name, "cpp");
+        assert_eq!(for_file("test.cc").name, "cpp");
+        assert_eq!(for_file("test.hxx").name, "cpp");
+        assert_eq!(for_file("test.c").name, "c");
+        assert_eq!(for_file("test.h").name, "c");
+        assert_eq!(for_file("main.rs").name, "rust");
+        assert_eq!(for_file("main.go").name, "go");
+        assert_eq!(for_file("app.js").name, "javascript");
+        assert_eq!(for_file("module.mjs").name, "javascript");
+        assert_eq!(for_file("app.ts").name, "typescript");
+        assert_eq!(for_file("App.tsx").name, "tsx");
+        assert_eq!(for_file("index.html").name, "html");
+        assert_eq!(for_file("style.css").name, "css");
+        assert_eq!(for_file("notes.txt").name, PLAIN_TEXT);
+        assert_eq!(for_file("README.md").name, PLAIN_TEXT);
+        assert_eq!(for_file("Makefile").name, PLAIN_TEXT);
+    }
+
+    #[test]
+    fn names_match_aliases_and_extensions() {
+        assert_eq!(for_name("Python").unwrap().name, "python");
+        assert_eq!(for_name("js").unwrap().name, "javascript");
+        assert_eq!(for_name("c++").unwrap().name, "cpp");
+        assert_eq!(for_name("hpp").unwrap().name, "cpp");
+        assert_eq!(for_name("text").unwrap().name, PLAIN_TEXT);
+        assert!(for_name("not a language").is_none());
+    }
+}
@@ -1,10 +1,11 @@
-﻿use ropey::Rope;
+use ropey::Rope;
 use tree_sitter::{InputEdit, Tree, TreeCursor};
 
 use super::Language;
 
 pub struct TreeManager {
-    tree: Tree,
+    /// none if the language doesn't have a grammar
+    tree: Option<Tree>,
 }
 
 /* ------- Parsing  ------- */
@@ -12,12 +13,15 @@ impl TreeManager {
     /// create empty tree
     pub fn new(lang: &mut Language) -> TreeManager {
         TreeManager {
-            tree: lang.parser.parse("", None).unwrap(),
+            tree: lang
+                .syntax
+                .as_mut()
+                .map(|syntax| syntax.parser.parse("", None).unwrap()),
         }
     }
 
-    pub fn get_cursor(&self) -> TreeCursor<'_> {
-        self.tree.walk()
+    pub fn get_cursor(&self) -> Option<TreeCursor<'_>> {
+        self.tree.as_ref().map(Tree::walk)
     }
 
     pub fn replace(&mut self, source: &Rope, lang: &mut Language) {
@@ -25,27 +29,31 @@ impl TreeManager {
     }
 
     pub fn update(&mut self, source: &Rope, change: InputEdit, lang: &mut Language) {
-        self.tree.edit(&change);
+        if let Some(tree) = &mut self.tree {
+            tree.edit(&change);
+        }
         self.parse(source, true, lang);
     }
 
     fn parse(&mut self, source: &Rope, use_old: bool, lang: &mut Language) {
-        self.tree = lang
-            .parser
-            .parse_with_options(
-                &mut |byte, _| {
-                    if byte <= source.len_bytes() {
-                        let (chunk, start_byte, _, _) = source.chunk_at_byte(byte);
-                        &chunk.as_bytes()[byte - start_byte..]
-                    } else {
-                        // out of range
-                        &[]
-                    }
-                },
-                if use_old { Some(&self.tree) } else { None },
-                None,
-            )
-            .unwrap();
+        let Some(syntax) = &mut lang.syntax else {
+            return;
+        };
+
+        let tree = syntax.parser.parse_with_options(
+            &mut |byte, _| {
+                if byte <= source.len_bytes() {
+                    let (chunk, start_byte, _, _) = source.chunk_at_byte(byte);
+                    &chunk.as_bytes()[byte - start_byte..]
+                } else {
+                    // out of range
+                    &[]
+                }
+            },
+            if use_old { self.tree.as_ref() } else { None },
+            None,
+        );
+        self.tree = Some(tree.unwrap());
     }
 }
 
@@ -53,7 +61,10 @@ impl TreeManager {
 #[allow(dead_code)]
 impl TreeManager {
     pub fn make_tree_str(&self) -> String {
-        let mut cursor = self.tree.root_node().walk();
+        let Some(tree) = &self.tree else {
+            return String::new();
+        };
+        let mut cursor = tree.root_node().walk();
         Self::make_branch(&mut cursor, "", true)
     }
 
@@ -0,0 +1,123 @@
+use std::fmt;
+
+use tree_sitter::{Query, QueryError};
+
+use super::config::{LanguageConfig, StringNodeKinds};
+
+/// A problem with a language config, found when checking it against its grammar
+#[derive(Debug)]
+pub enum LanguageError {
+    /// A node kind named in the config does not exist in the grammar
+    UnknownNodeKind { field: &'static str, kind: String },
+
+    /// The highlight query could not be compiled for the grammar
+    HighlightQuery(QueryError),
+
+    /// None of the captures in the highlight query have a color in the config
+    NoRecognizedHighlights,
+}
+
+impl fmt::Display for LanguageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LanguageError::UnknownNodeKind { field, kind } => {
+                write!(f, "unknown node kind in {field}: {kind:?}")
+            }
+            LanguageError::HighlightQuery(err) => write!(f, "invalid highlight query: {err}"),
+            LanguageError::NoRecognizedHighlights => {
+                write!(f, "highlight query has no captures with a configured color")
+            }
+        }
+    }
+}
+
+/// The IDs for a string, and the start and end. Used for pseudo-selections
+pub struct StringNodeIDs {
+    pub string: u16,
+    pub string_bounds: Vec<u16>,
+}
+
+impl StringNodeIDs {
+    pub(super) fn resolve(
+        kinds: &StringNodeKinds,
+        ts_lang: &tree_sitter::Language,
+    ) -> Result<Self, LanguageError> {
+        Ok(StringNodeIDs {
+            string: resolve_kind(ts_lang, "string_nodes.string", &kinds.string)?,
+            string_bounds: kinds
+                .string_bounds
+                .iter()
+                .map(|kind| resolve_kind(ts_lang, "string_nodes.string_bounds", kind))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Find the ID of a node kind by name, preferring named nodes over anonymous ones
+fn resolve_kind(
+    ts_lang: &tree_sitter::Language,
+    field: &'static str,
+    kind: &str,
+) -> Result<u16, LanguageError> {
+    // an ID of 0 means the kind was not found
+    [true, false]
+        .into_iter()
+        .map(|named| ts_lang.id_for_node_kind(kind, named))
+        .find(|id| *id != 0)
+        .ok_or_else(|| LanguageError::UnknownNodeKind {
+            field,
+            kind: kind.to_string(),
+        })
+}
+
+/// Check that at least one capture in the highlight query gets a color.
+/// Names match the same way as in highlighting, so `function` also colors `function.method`.
+pub(super) fn check_highlights(
+    config: &LanguageConfig,
+    query: &Query,
+) -> Result<(), LanguageError> {
+    let recognized = query.capture_names().iter().any(|capture| {
+        config.highlight.iter().any(|(name, _)| {
+            capture
+                .strip_prefix(*name)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+    });
+
+    if recognized {
+        Ok(())
+    } else {
+        Err(LanguageError::NoRecognizedHighlights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::{registry, Language};
+
+    #[test]
+    fn registered_languages_are_valid() {
+        for config in registry::all() {
+            if let Err(err) = Language::new(config) {
+                panic!("invalid language {}: {err}", config.name);
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_kinds_are_reported() {
+        let ts_lang = LanguageConfig::for_file("test.py").tree_sitter().unwrap();
+        let kinds = StringNodeKinds {
+            string: "not_a_real_node".to_string(),
+            string_bounds: vec![],
+        };
+        assert!(matches!(
+            StringNodeIDs::resolve(&kinds, &ts_lang),
+            Err(LanguageError::UnknownNodeKind {
+                field: "string_nodes.string",
+                ..
+            })
+        ));
+    }
+}
@@ -14,6 +14,13 @@ impl BlocksTheme {
         Self { color_for }
     }
 
+    /// The name of each theme (for `for_str`) and the title shown to users
+    pub const NAMES: [(&'static str, &'static str); 3] = [
+        ("syntax_colored", "Syntax Colored"),
+        ("depth_grayscale", "Depth Grayscale"),
+        ("alternating_colored", "Alternating Colored"),
+    ];
+
     pub fn for_str(string: &str) -> Self {
         match string {
             "syntax_colored" => SYNTAX_COLORED_BLOCKS,
@@ -35,6 +42,8 @@ static SYNTAX_COLORED_BLOCKS: BlocksTheme = BlocksTheme::new(|block_type, _| {
         For => Some(Color32::from_rgb(255, 131, 193)),
         Try => Some(Color32::from_rgb(84, 129, 230)),
         Switch => Some(Color32::from_rgb(255, 192, 203)),
+        With => Some(Color32::from_rgb(46, 196, 160)),
+        Match => Some(Color32::from_rgb(230, 104, 138)),
         Generic => Some(Color32::from_rgb(42, 189, 218)),
         Error => Some(Color32::from_rgb(255, 0, 0)),
         Comment => None,
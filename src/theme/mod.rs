@@ -21,8 +21,13 @@ pub mod syntax {
     pub const LITERAL: Color32 = DARK_YELLOW;
     pub const ESCAPE_SEQUENCE: Color32 = CYAN;
     pub const COMMENT: Color32 = COMMENT_GREY;
+    pub const TAG: Color32 = LIGHT_RED;
+    pub const ATTRIBUTE: Color32 = DARK_YELLOW;
 
     pub const DEFAULT: Color32 = WHITE;
+
+    /// brackets are colored by how deeply they are nested (when turned on)
+    pub const BRACKET_PALETTE: [Color32; 3] = [LIGHT_YELLOW, MAGENTA, BLUE];
 }
 
 pub mod diagnostic {
@@ -41,6 +46,8 @@ pub const SELECTION: Color32 = Color32::from_rgba_premultiplied(0x56, 0x63, 0x78
 pub const SEARCH_RESULT: Color32 = Color32::from_rgb(0x3D, 0x45, 0x55);
 pub const SEARCH_RESULT_SELECTED: Color32 = Color32::from_rgb(0x97, 0x91, 0x8E);
 pub const PSEUDO_SELECTION: Color32 = Color32::from_rgba_premultiplied(0x32, 0x09, 0x03, 0x50);
+pub const MATCHING_BRACKET: Color32 = Color32::from_rgba_premultiplied(0x2A, 0x2F, 0x38, 0x50);
+pub const MATCHING_BRACKET_BORDER: Color32 = Color32::from_rgb(0x51, 0x5A, 0x6B);
 pub const POPUP_BACKGROUND: Color32 = Color32::from_rgb(0x1E, 0x22, 0x27);
 pub const LINE_NUMBERS: Color32 = one_dark::GUTTER_GREY;
 
@@ -1,4 +1,4 @@
-use egui::{Event, Key};
+use egui::Event;
 use egui_inbox::{UiInbox, UiInboxSender};
 use log::error;
 use std::panic::{self, PanicHookInfo};
@@ -6,7 +6,7 @@ use wasm_bindgen::prelude::*;
 use web_sys::HtmlCanvasElement;
 
 use crate::block_editor::{
-    source::TextEdit, text_editor::StackFrameLines, text_range::TextRange, ExternalCommand,
+    keymap, source::TextEdit, text_editor::StackFrameLines, text_range::TextRange, ExternalCommand,
 };
 use crate::lsp::{
     completion::VSCodeCompletionItem,
@@ -96,26 +96,7 @@ impl LilypadWebHandle {
             modifiers,
         } = event
         {
-            // pass through hotkeys (other than undo/redo/find) and function keys
-            if modifiers.any() && !modifiers.shift_only() {
-                matches!(key, Key::Z | Key::Y | Key::F) == false
-            } else {
-                matches!(
-                    key,
-                    Key::F1
-                        | Key::F2
-                        | Key::F3
-                        | Key::F4
-                        | Key::F5
-                        | Key::F6
-                        | Key::F7
-                        | Key::F8
-                        | Key::F9
-                        | Key::F10
-                        | Key::F11
-                        | Key::F12
-                )
-            }
+            keymap::should_propagate(*key, *modifiers)
         } else {
             false
         }
@@ -149,6 +130,17 @@ impl LilypadWebHandle {
         }
     }
 
+    #[wasm_bindgen]
+    pub fn set_language(&self, name: String) {
+        if let Some(sender) = &self.command_sender {
+            if sender.send(ExternalCommand::SetLanguage(name)).is_err() {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
     #[wasm_bindgen]
     pub fn set_font(&self, font_name: String, font_size: f32) {
         if let Some(sender) = &self.command_sender {
@@ -163,6 +155,68 @@ impl LilypadWebHandle {
         }
     }
 
+    #[wasm_bindgen]
+    pub fn set_tab_width(&self, tab_width: usize) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::SetTabWidth(tab_width))
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn set_keymap(&self, keymap: String) {
+        if let Some(sender) = &self.command_sender {
+            if sender.send(ExternalCommand::SetKeymap(keymap)).is_err() {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn set_bracket_colorization(&self, enabled: bool) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::SetBracketColorization(enabled))
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn register_language(
+        &self,
+        definition: String,
+        blocks_query: String,
+        indents_query: Option<String>,
+    ) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::RegisterLanguage(
+                    definition,
+                    blocks_query,
+                    indents_query.unwrap_or_default(),
+                ))
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
     #[wasm_bindgen]
     pub fn apply_edit(&self, json: JsValue) {
         #[derive(serde::Deserialize)]
@@ -7,3 +7,4 @@ int iswspace(wchar_t ch);
 int iswalnum(wint_t _wc);
 int iswdigit(wint_t c);
 int iswalpha(wint_t c);
+wint_t towupper(wint_t c);